@@ -0,0 +1,73 @@
+//! Builder throughput, printing, and export time for large generated modules.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use circt_sv_basic::always::{self, Posedge};
+use circt_sv_basic::constant::{self, Signedness};
+use circt_sv_basic::decl;
+use circt_sv_basic::export::export_verilog;
+use circt_sv_basic::ports::{self, PortDirection};
+use circt_sv_basic::prelude::*;
+
+const SIZES: &[usize] = &[1_000, 10_000, 50_000];
+
+/* A module with `size` operations: constants, wires, and always blocks in equal parts. */
+fn build<'c>(ctx: &'c Context, size: usize) -> Design<'c> {
+    let location = Location::unknown(ctx);
+    let design = Design::new(ctx, location);
+    let ports = [("clk", PortDirection::Input, IntegerType::new(ctx, 1).into())];
+    ports::module(&design, "large", &ports, |body| {
+        let clock = body.argument(0).unwrap().into();
+        for index in 0..size / 3 {
+            let width = (index % 64 + 1) as u32;
+            constant::const_int(ctx, body, width, 1, Signedness::Unsigned, location)?;
+            decl::wire(ctx, body, &format!("w{index}"), IntegerType::new(ctx, width).into(), location);
+            always::always(ctx, body, &[(Posedge, clock)], |_| {}, location);
+        }
+        Ok(Vec::new())
+    }, location).unwrap();
+    design
+}
+
+fn context() -> Context {
+    let ctx = Context::new();
+    load_dialects(&ctx);
+    ctx
+}
+
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    for &size in SIZES {
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            let ctx = context();
+            b.iter(|| build(&ctx, size));
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("print");
+    for &size in SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            let ctx = context();
+            let design = build(&ctx, size);
+            b.iter(|| design.module().as_operation().to_string());
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("export");
+    group.sample_size(10);
+    for &size in SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            let ctx = context();
+            // Export prepares the IR it emits, so each iteration needs a fresh design
+            b.iter_batched(|| build(&ctx, size), |design| export_verilog(&design).unwrap(),
+                           criterion::BatchSize::PerIteration);
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, generation);
+criterion_main!(benches);
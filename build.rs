@@ -0,0 +1,24 @@
+//! Record the versions of the LLVM/MLIR and CIRCT install the crate is built against, for
+//! `circt_sv_basic::version`; the libraries found at run time may differ.
+
+use std::path::Path;
+use std::process::Command;
+
+/* The first line of `tool --version` containing `marker`, with it and anything before removed. */
+fn tool_version(tool: &Path, marker: &str) -> Option<String> {
+    let output = Command::new(tool).arg("--version").output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).into_owned() + &String::from_utf8_lossy(&output.stderr);
+    let line = text.lines().find(|line| line.contains(marker))?;
+    Some(line[line.find(marker)? + marker.len()..].trim().to_string())
+}
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=MLIR_SYS_200_PREFIX");
+    let bin = std::env::var("MLIR_SYS_200_PREFIX").map(|prefix| Path::new(&prefix).join("bin")).unwrap_or_default();
+    let llvm = Command::new(bin.join("llvm-config")).arg("--version").output().ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|version| !version.is_empty());
+    let circt = tool_version(&bin.join("circt-opt"), "CIRCT");
+    println!("cargo:rustc-env=CIRCT_SV_BASIC_LLVM_VERSION={}", llvm.as_deref().unwrap_or("unknown"));
+    println!("cargo:rustc-env=CIRCT_SV_BASIC_CIRCT_VERSION={}", circt.as_deref().unwrap_or("unknown"));
+}
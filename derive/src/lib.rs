@@ -0,0 +1,150 @@
+//! `#[derive(Ports)]`, declaring a module's ports as a struct. Each field is a port, named after
+//! the field, typed `Input<W>`, `Output<W>`, or `InOut<W>` for a `W` bit port:
+//!
+//! ```ignore
+//! #[derive(Ports)]
+//! struct Counter {
+//!     clk: Input<1>,
+//!     enable: Input<1>,
+//!     count: Output<8>,
+//! }
+//! ```
+//!
+//! implements `circt_sv_basic::ports::PortBundle` for `Counter`, with a `CounterInputs` struct
+//! holding the input and inout ports' values in a module body and a `CounterOutputs` struct of the
+//! values the body drives the outputs with.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Fields, GenericArgument, Ident, PathArguments, Type};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Input,
+    Output,
+    InOut,
+}
+
+struct Port {
+    name: Ident,
+    direction: Direction,
+    width: Expr,
+}
+
+/* The direction and width of a field typed `Input<W>`, `Output<W>`, or `InOut<W>`. */
+fn port(name: Ident, r#type: &Type) -> Result<Port, Error> {
+    let invalid = || Error::new_spanned(r#type, "a port is typed `Input<W>`, `Output<W>`, or `InOut<W>`");
+    let Type::Path(path) = r#type else { return Err(invalid()) };
+    let segment = path.path.segments.last().ok_or_else(invalid)?;
+    let direction = match segment.ident.to_string().as_str() {
+        "Input" => Direction::Input,
+        "Output" => Direction::Output,
+        "InOut" => Direction::InOut,
+        _ => return Err(invalid()),
+    };
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else { return Err(invalid()) };
+    let width = match arguments.args.first() {
+        Some(GenericArgument::Const(width)) => width.clone(),
+        Some(GenericArgument::Type(Type::Path(width))) => Expr::Path(syn::ExprPath {
+            attrs: Vec::new(),
+            qself: None,
+            path: width.path.clone(),
+        }),
+        _ => return Err(invalid()),
+    };
+    Ok(Port { name, direction, width })
+}
+
+/// Implement `PortBundle` for a struct of `Input<W>`, `Output<W>`, and `InOut<W>` fields.
+#[proc_macro_derive(Ports)]
+pub fn derive_ports(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(&input, "`Ports` is derived for structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(&input, "`Ports` needs named fields, which name the ports"));
+    };
+    let ports = fields.named.iter()
+        .map(|field| port(field.ident.clone().expect("named field"), &field.ty))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let krate = quote!(::circt_sv_basic);
+    let name = &input.ident;
+    let visibility = &input.vis;
+    let inputs = format_ident!("{name}Inputs");
+    let outputs = format_ident!("{name}Outputs");
+
+    let declarations = ports.iter().map(|Port { name, direction, width }| {
+        let port = name.to_string();
+        let integer = quote!(#krate::melior::ir::r#type::IntegerType::new(ctx, (#width) as u32).into());
+        let direction = match direction {
+            Direction::Input => quote!(#krate::ports::PortDirection::Input),
+            Direction::Output => quote!(#krate::ports::PortDirection::Output),
+            Direction::InOut => quote!(#krate::ports::PortDirection::InOut),
+        };
+        quote!((#port, #direction, #integer))
+    });
+    let argument_fields = ports.iter().filter(|port| port.direction != Direction::Output)
+        .map(|Port { name, direction, width }| match direction {
+            Direction::InOut => quote!(pub #name: #krate::melior::ir::Value<'c, 'a>),
+            _ => quote!(pub #name: #krate::bits::Bits<'c, 'a, { #width }>),
+        });
+    let arguments = ports.iter().filter(|port| port.direction != Direction::Output).enumerate()
+        .map(|(index, Port { name, direction, .. })| {
+            let value = quote!(#krate::melior::ir::BlockLike::argument(block, #index).unwrap().into());
+            match direction {
+                Direction::InOut => quote!(#name: #value),
+                _ => quote!(#name: #krate::bits::Bits::new(#value).expect("the block has the bundle's ports")),
+            }
+        });
+    let output_ports: Vec<&Port> = ports.iter().filter(|port| port.direction == Direction::Output).collect();
+    let output_fields = output_ports.iter()
+        .map(|Port { name, width, .. }| quote!(pub #name: #krate::bits::Bits<'c, 'a, { #width }>));
+    let output_names: Vec<&Ident> = output_ports.iter().map(|port| &port.name).collect();
+    let inputs_doc = format!("The values of `{name}`'s input and inout ports in a module body.");
+    let outputs_doc = format!("The values driving `{name}`'s output ports.");
+    let lifetimes = if output_ports.is_empty() {
+        quote!(pub _lifetimes: ::core::marker::PhantomData<#krate::melior::ir::Value<'c, 'a>>,)
+    } else {
+        quote!()
+    };
+
+    Ok(quote! {
+        #[doc = #inputs_doc]
+        #visibility struct #inputs<'c, 'a> {
+            #(#argument_fields,)*
+            pub _lifetimes: ::core::marker::PhantomData<#krate::melior::ir::Value<'c, 'a>>,
+        }
+
+        #[doc = #outputs_doc]
+        #visibility struct #outputs<'c, 'a> {
+            #(#output_fields,)*
+            #lifetimes
+        }
+
+        impl #krate::ports::PortBundle for #name {
+            type Inputs<'c: 'a, 'a> = #inputs<'c, 'a>;
+            type Outputs<'c: 'a, 'a> = #outputs<'c, 'a>;
+
+            fn ports<'c>(ctx: &'c #krate::melior::Context)
+                -> ::std::vec::Vec<(&'static str, #krate::ports::PortDirection, #krate::melior::ir::Type<'c>)> {
+                ::std::vec![#(#declarations),*]
+            }
+
+            fn inputs<'c: 'a, 'a>(block: &'a #krate::melior::ir::Block<'c>) -> Self::Inputs<'c, 'a> {
+                #inputs { #(#arguments,)* _lifetimes: ::core::marker::PhantomData }
+            }
+
+            fn outputs<'c: 'a, 'a>(outputs: Self::Outputs<'c, 'a>)
+                -> ::std::vec::Vec<#krate::melior::ir::Value<'c, 'a>> {
+                let #outputs { #(#output_names,)* .. } = outputs;
+                ::std::vec![#(#output_names.value()),*]
+            }
+        }
+    })
+}
@@ -0,0 +1,36 @@
+/* C API of the circt-sv-basic generator, implemented in src/capi.rs. Link against the cdylib
+   (libcirct_sv_basic.so). */
+
+#ifndef SVGEN_H
+#define SVGEN_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct SvgenDesign svgen_design;
+
+/* Create an empty design. Free it with svgen_destroy_design. */
+svgen_design *svgen_create_design(void);
+
+void svgen_destroy_design(svgen_design *design);
+
+/* Add a module described by a JSON spec:
+     {"name": "adder", "ports": [{"name": "a", "direction": "input", "type": "i8"}, ...]}
+   Outputs are tied to zero. Returns 0 on success, -1 on error. */
+int svgen_add_module_from_spec(svgen_design *design, const char *spec);
+
+/* Export the design as SystemVerilog. Returns a string to free with svgen_free_string, or NULL on
+   error. */
+char *svgen_export_verilog(svgen_design *design);
+
+void svgen_free_string(char *string);
+
+/* The message for the last error on this thread, valid until the next failing call. */
+const char *svgen_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SVGEN_H */
@@ -0,0 +1,380 @@
+//! `sv.always`, `sv.alwaysff`, and `sv.alwayscomb` builders, and `hw.triggered`, which lowers to
+//! them later, and `sv.case`. [`always_ff_with_reset`] builds synchronous and asynchronous,
+//! active high and low resets. [`always_comb_with_defaults`], [`case_with_defaults`], and
+//! [`assign_defaults`] give combinational blocks a default for every signal they assign.
+//!
+//! ```ignore
+//! always::always(&ctx, &body, &[(Posedge, clk), (Negedge, rst_n)], |always| { ... }, here!(ctx));
+//! ```
+
+use melior::dialect::ods::{hw, sv};
+use melior::ir::attribute::{ArrayAttribute, IntegerAttribute, StringAttribute};
+use melior::ir::operation::{OperationBuilder, OperationLike, OperationRef, OperationResult};
+use melior::ir::r#type::IntegerType;
+use melior::ir::{
+    Attribute, Block, BlockLike, Identifier, Location, Operation, Region, RegionLike, Type, TypeLike, Value, ValueLike,
+};
+use melior::Context;
+
+use crate::attributes::{CaseStyle, ValidationQualifier};
+use crate::diagnostics::Append;
+use crate::lowering::LanguageStandard;
+use crate::{bit_width, operations, region, walk, Design, Error};
+
+/// The edge an `sv.always` event triggers on, `sv::EventControl` in CIRCT.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventControl {
+    Posedge,
+    Negedge,
+    Edge,
+}
+
+pub use EventControl::*;
+
+impl EventControl {
+    /// The `sv::EventControl` enum attribute for this edge.
+    pub fn attribute<'c>(self, ctx: &'c Context) -> Attribute<'c> {
+        let value = match self {
+            Posedge => 0,
+            Negedge => 1,
+            Edge => 2,
+        };
+        IntegerAttribute::new(IntegerType::new(ctx, 32).into(), value).into()
+    }
+}
+
+/// How a clocked process is reset: checked on the clock edge or as soon as it's asserted, and
+/// asserted high or low.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ResetKind {
+    #[default]
+    SyncActiveHigh,
+    SyncActiveLow,
+    AsyncActiveHigh,
+    AsyncActiveLow,
+}
+
+impl ResetKind {
+    pub fn is_async(self) -> bool {
+        matches!(self, ResetKind::AsyncActiveHigh | ResetKind::AsyncActiveLow)
+    }
+
+    pub fn is_active_low(self) -> bool {
+        matches!(self, ResetKind::SyncActiveLow | ResetKind::AsyncActiveLow)
+    }
+
+    /// The edge asserting the reset: what an asynchronous reset is sensitive to.
+    pub fn edge(self) -> EventControl {
+        if self.is_active_low() { Negedge } else { Posedge }
+    }
+
+    /* The `sv::ResetType` enum attribute: a synchronous or asynchronous reset. */
+    fn attribute<'c>(self, ctx: &'c Context) -> Attribute<'c> {
+        let value = if self.is_async() { 2 } else { 1 };
+        IntegerAttribute::new(IntegerType::new(ctx, 32).into(), value).into()
+    }
+}
+
+/* `sv.if condition { then } else { otherwise }`, for procedural blocks. */
+pub(crate) fn if_<'c>(condition: Value<'c, '_>, then: Region<'c>, otherwise: Region<'c>,
+                      location: Location<'c>) -> Operation<'c> {
+    OperationBuilder::new("sv.if", location)
+        .add_operands(&[condition])
+        .add_regions([then, otherwise])
+        .build()
+        .expect("valid operation")
+}
+
+/// Append `sv.always` sensitive to `events` to `block`. `body` fills in the always block, and
+/// whatever it returns is passed back so errors from nested builders can be propagated.
+pub fn always<'c, F, R>(ctx: &'c Context, block: &Block<'c>, events: &[(EventControl, Value<'c, '_>)], body: F,
+                        location: Location<'c>) -> R
+where
+    F: FnOnce(&Block<'c>) -> R,
+{
+    let always_block = Block::new(&[]);
+    let result = body(&always_block);
+    let always_region = Region::new();
+    always_region.append_block(always_block);
+
+    let edges: Vec<Attribute> = events.iter().map(|(event, _)| event.attribute(ctx)).collect();
+    let clocks: Vec<Value> = events.iter().map(|(_, clock)| *clock).collect();
+    let sv_always = sv::always(ctx, &clocks, always_region, ArrayAttribute::new(ctx, &edges), location);
+    block.append(sv_always.into());
+    result
+}
+
+/// Append a clocked process: `sv.alwaysff` for SystemVerilog, or `sv.always` for Verilog-2001.
+pub fn always_ff<'c, F, R>(design: &Design<'c>, block: &Block<'c>, edge: EventControl, clock: Value<'c, '_>, body: F,
+                           location: Location<'c>) -> R
+where
+    F: FnOnce(&Block<'c>) -> R,
+{
+    let ctx = design.context();
+    if design.language_standard() == LanguageStandard::Verilog2001 {
+        return always(ctx, block, &[(edge, clock)], body, location);
+    }
+    let ff_block = Block::new(&[]);
+    let result = body(&ff_block);
+    let ff_region = Region::new();
+    ff_region.append_block(ff_block);
+    let alwaysff = sv::alwaysff(ctx, clock, ff_region, region(|_| {}), edge.attribute(ctx), location);
+    block.append(alwaysff.into());
+    result
+}
+
+/// [`always_ff`] with a reset of kind `kind`: `on_reset` fills in the block run while `reset` is
+/// asserted and `body` the block run otherwise. An asynchronous reset is added to the sensitivity
+/// list. For SystemVerilog this is `sv.alwaysff` with a reset, for Verilog-2001 an `sv.always`
+/// around an `sv.if` on the reset.
+pub fn always_ff_with_reset<'c, G, F, R>(design: &Design<'c>, block: &Block<'c>, edge: EventControl,
+                                         clock: Value<'c, '_>, kind: ResetKind, reset: Value<'c, '_>,
+                                         on_reset: G, body: F, location: Location<'c>) -> R
+where
+    G: FnOnce(&Block<'c>),
+    F: FnOnce(&Block<'c>) -> R,
+{
+    let ctx = design.context();
+    if design.language_standard() == LanguageStandard::Verilog2001 {
+        let mut events = vec![(edge, clock)];
+        if kind.is_async() {
+            events.push((kind.edge(), reset));
+        }
+        return always(ctx, block, &events, |always| {
+            let reset_region = region(on_reset);
+            let mut result = None;
+            let body_region = region(|then| result = Some(body(then)));
+            let (then, otherwise) =
+                if kind.is_active_low() { (body_region, reset_region) } else { (reset_region, body_region) };
+            always.append(if_(reset, then, otherwise, location));
+            result.expect("the body is built")
+        }, location);
+    }
+    let ff_block = Block::new(&[]);
+    let result = body(&ff_block);
+    let ff_region = Region::new();
+    ff_region.append_block(ff_block);
+    let alwaysff = OperationBuilder::new("sv.alwaysff", location)
+        .add_operands(&[clock, reset])
+        .add_attributes(&[(Identifier::new(ctx, "clockEdge"), edge.attribute(ctx)),
+                          (Identifier::new(ctx, "resetStyle"), kind.attribute(ctx)),
+                          (Identifier::new(ctx, "resetEdge"), kind.edge().attribute(ctx))])
+        .add_regions([ff_region, region(on_reset)])
+        .build()
+        .expect("valid operation");
+    block.append(alwaysff);
+    result
+}
+
+/// Append `sv.alwayscomb`, which ExportVerilog prints as `always @(*)` for Verilog-2001 (see
+/// [`LoweringOptions::for_standard`](crate::lowering::LoweringOptions::for_standard)).
+pub fn always_comb<'c, F, R>(ctx: &'c Context, block: &Block<'c>, body: F, location: Location<'c>) -> R
+where
+    F: FnOnce(&Block<'c>) -> R,
+{
+    let comb_block = Block::new(&[]);
+    let result = body(&comb_block);
+    let comb_region = Region::new();
+    comb_region.append_block(comb_block);
+    block.append(sv::alwayscomb(ctx, comb_region, location).into());
+    result
+}
+
+/// [`always_comb`], then [`assign_defaults`] to the block `body` built, so every signal it assigns
+/// has a value on every path through it.
+pub fn always_comb_with_defaults<'c, F, R>(ctx: &'c Context, block: &Block<'c>, body: F,
+                                           location: Location<'c>) -> Result<R, Error>
+where
+    F: FnOnce(&Block<'c>) -> R,
+{
+    always_comb(ctx, block, |comb_block| {
+        let result = body(comb_block);
+        assign_defaults(ctx, comb_block, location)?;
+        Ok(result)
+    }, location)
+}
+
+/// Append `sv.case selector` to the procedural `block`, with an arm for each of `patterns`:
+/// `Some(value)` matches the integer `selector` being `value`, `None` is the default. `arm` fills
+/// in the block of each, given its pattern.
+pub fn case<'c, F>(ctx: &'c Context, block: &Block<'c>, selector: Value<'c, '_>, patterns: &[Option<u64>], arm: F,
+                   location: Location<'c>) -> Result<(), Error>
+where
+    F: FnMut(&Block<'c>, Option<u64>) -> Result<(), Error>,
+{
+    let arms = case_arms(selector, patterns, arm)?;
+    block.append(case_operation(ctx, selector, patterns, arms, location));
+    Ok(())
+}
+
+/// [`case`], with a blocking assignment of zero before it to each signal its arms assign, as
+/// [`assign_defaults`] gives a block, so an arm that doesn't assign a signal can't infer a latch.
+pub fn case_with_defaults<'c, F>(ctx: &'c Context, block: &Block<'c>, selector: Value<'c, '_>,
+                                 patterns: &[Option<u64>], arm: F, location: Location<'c>) -> Result<(), Error>
+where
+    F: FnMut(&Block<'c>, Option<u64>) -> Result<(), Error>,
+{
+    let arms = case_arms(selector, patterns, arm)?;
+    let mut dests = Vec::new();
+    for arm in &arms {
+        let assigned = assigned(arm);
+        check_declared_outside(&assigned, arm)?;
+        for dest in assigned {
+            if !dests.contains(&dest) {
+                dests.push(dest);
+            }
+        }
+    }
+    assign_zeros(ctx, &dests, |operation| block.append(operation), location);
+    block.append(case_operation(ctx, selector, patterns, arms, location));
+    Ok(())
+}
+
+/* The blocks of the arms of an `sv.case` on `selector` matching `patterns`, filled in by `arm`,
+   checking the selector is an integer each pattern fits in. */
+fn case_arms<'c, F>(selector: Value<'c, '_>, patterns: &[Option<u64>], mut arm: F) -> Result<Vec<Block<'c>>, Error>
+where
+    F: FnMut(&Block<'c>, Option<u64>) -> Result<(), Error>,
+{
+    let width = IntegerType::try_from(selector.r#type()).map_err(|_| {
+        Error::TypeMismatch { expected: "an integer".to_string(), found: selector.r#type().to_string() }
+    })?.width();
+    if let Some(&value) = patterns.iter().flatten().find(|&&value| width < 64 && value >> width != 0) {
+        return Err(Error::ConstantOutOfRange { value: value as i128, width, signed: false });
+    }
+    patterns.iter().map(|&pattern| {
+        let arm_block = Block::new(&[]);
+        arm(&arm_block, pattern)?;
+        Ok(arm_block)
+    }).collect()
+}
+
+/* `sv.case selector` with `arms` for `patterns`. A pattern is an integer of two bits per selector
+   bit, `sv::CasePatternBit` in CIRCT, of which only zero and one are used; the default is a unit. */
+fn case_operation<'c>(ctx: &'c Context, selector: Value<'c, '_>, patterns: &[Option<u64>], arms: Vec<Block<'c>>,
+                      location: Location<'c>) -> Operation<'c> {
+    let width = bit_width(selector.r#type()).expect("an integer selector");
+    let patterns: Vec<Attribute> = patterns.iter().map(|pattern| match pattern {
+        Some(value) => {
+            let bits = (0..64).filter(|bit| value >> bit & 1 == 1).fold(0u128, |bits, bit| bits | 1 << (2 * bit));
+            Attribute::parse(ctx, &format!("{bits} : i{}", 2 * width)).expect("valid case pattern")
+        }
+        None => Attribute::unit(ctx),
+    }).collect();
+    let regions: Vec<Region> = arms.into_iter().map(|arm| {
+        let region = Region::new();
+        region.append_block(arm);
+        region
+    }).collect();
+    OperationBuilder::new("sv.case", location)
+        .add_operands(&[selector])
+        .add_attributes(&[(Identifier::new(ctx, "caseStyle"), CaseStyle::Case.attribute(ctx)),
+                          (Identifier::new(ctx, "casePatterns"), ArrayAttribute::new(ctx, &patterns).into()),
+                          (Identifier::new(ctx, "validationQualifier"), ValidationQualifier::Plain.attribute(ctx))])
+        .add_regions(regions)
+        .build()
+        .expect("valid operation")
+}
+
+/* The inouts assigned with `sv.bpassign` anywhere in `block`, in order of first assignment. */
+fn assigned<'c, 'a>(block: &'a Block<'c>) -> Vec<Value<'c, 'a>> {
+    let mut assigned = Vec::new();
+    let mut visit = |operation: &Operation<'c>| {
+        if operation.name().as_string_ref().as_str() != Ok("sv.bpassign") {
+            return;
+        }
+        if let Ok(dest) = operation.operand(0) {
+            // Defined outside the assignment, so it outlives the borrow `walk` gives
+            let dest = unsafe { Value::from_raw(dest.to_raw()) };
+            if !assigned.contains(&dest) {
+                assigned.push(dest);
+            }
+        }
+    };
+    for operation in operations(block) {
+        visit(&operation);
+        walk(&operation, &mut visit);
+    }
+    assigned
+}
+
+/* Check none of `dests` is declared in `block`, or in an op nested in it, so a default assigned at
+   the top of `block` doesn't use it before its declaration. */
+fn check_declared_outside(dests: &[Value], block: &Block) -> Result<(), Error> {
+    for dest in dests {
+        let Ok(result) = OperationResult::try_from(*dest) else { continue };
+        let declaration = result.owner();
+        let mut owner = declaration.block();
+        while let Some(current) = owner {
+            if unsafe { mlir_sys::mlirBlockEqual(current.to_raw(), block.to_raw()) } {
+                let name = declaration.attribute("name").ok()
+                    .and_then(|name| StringAttribute::try_from(name).ok())
+                    .map_or_else(|| declaration.name().to_string(), |name| name.value().to_string());
+                return Err(Error::UseBeforeDeclaration(name));
+            }
+            owner = current.parent_operation().and_then(|operation| operation.block());
+        }
+    }
+    Ok(())
+}
+
+/* A blocking assignment of zero to each of `dests`, from operations `insert` places in order. Zero
+   is cast to struct and array types. */
+fn assign_zeros<'c, 'a, F>(ctx: &'c Context, dests: &[Value<'c, '_>], mut insert: F, location: Location<'c>)
+where
+    F: FnMut(Operation<'c>) -> OperationRef<'c, 'a>,
+{
+    for dest in dests {
+        let element = unsafe { Type::from_raw(mlir_sys::hwInOutTypeGetElementType(dest.r#type().to_raw())) };
+        let Some(width) = bit_width(element) else { continue };
+        let integer: Type = IntegerType::new(ctx, width as u32).into();
+        let zero = hw::constant(ctx, integer, IntegerAttribute::new(integer, 0).into(), location);
+        let mut value: Value = insert(zero.into()).result(0).unwrap().into();
+        if element != integer {
+            value = insert(hw::bitcast(ctx, element, value, location).into()).result(0).unwrap().into();
+        }
+        insert(sv::bpassign(ctx, *dest, value, location).into());
+    }
+}
+
+/// Insert a blocking assignment of zero to each signal assigned in the procedural `block` (an
+/// `always_comb` body, or an arm of a case or if) at the top of it, so a path through the block
+/// that doesn't assign a signal can't infer a latch or leave it X. Zero is cast to struct and array
+/// types. A signal declared in the block itself can't be given a default there.
+pub fn assign_defaults<'c>(ctx: &'c Context, block: &Block<'c>, location: Location<'c>) -> Result<(), Error> {
+    let dests = assigned(block);
+    check_declared_outside(&dests, block)?;
+    let mut index = 0;
+    assign_zeros(ctx, &dests, |operation| {
+        index += 1;
+        block.insert_operation(index - 1, operation)
+    }, location);
+    Ok(())
+}
+
+/// Append `hw.triggered edge trigger (inputs)` to `block`: a procedural region run on `edge` of
+/// `trigger`, kept at the `hw` level until
+/// [`PassPipeline::lower_hw_to_sv`](crate::pipeline::PassPipeline::lower_hw_to_sv) turns it into
+/// an `sv.always`. The region is isolated from above, so `body` sees `inputs` as its block's
+/// arguments instead of capturing values.
+pub fn triggered<'c, F, R>(ctx: &'c Context, block: &Block<'c>, edge: EventControl, trigger: Value<'c, '_>,
+                           inputs: &[Value<'c, '_>], body: F, location: Location<'c>) -> R
+where
+    F: FnOnce(&Block<'c>) -> R,
+{
+    let arguments: Vec<(Type, Location)> = inputs.iter().map(|input| (input.r#type(), location)).collect();
+    let triggered_block = Block::new(&arguments);
+    let result = body(&triggered_block);
+    let triggered_region = Region::new();
+    triggered_region.append_block(triggered_block);
+    let mut operands = vec![trigger];
+    operands.extend_from_slice(inputs);
+    let triggered = OperationBuilder::new("hw.triggered", location)
+        .add_attributes(&[(Identifier::new(ctx, "event"), edge.attribute(ctx))])
+        .add_operands(&operands)
+        .add_regions([triggered_region])
+        .build()
+        .expect("valid operation");
+    block.append(triggered);
+    result
+}
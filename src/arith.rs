@@ -0,0 +1,136 @@
+//! Signedness aware arithmetic. Wrapping a value in [`Signed`] or [`Unsigned`] picks the matching
+//! `comb` comparison predicates and division, modulo, and right shift operations, so a two's
+//! complement datapath can't silently use unsigned semantics.
+//!
+//! ```ignore
+//! let quotient = Signed(dividend).div(&body, Signed(divisor), here!(ctx))?;
+//! let negative = quotient.lt(&ctx, &body, Signed(zero), here!(ctx))?;
+//! ```
+
+use melior::ir::operation::OperationBuilder;
+use melior::ir::{Block, Location, Value, ValueLike};
+use melior::Context;
+
+use crate::compare::{icmp, Predicate};
+use crate::constant::Signedness;
+use crate::diagnostics::Append;
+use crate::{bit_width, Error};
+
+/// An integer read as two's complement.
+#[derive(Clone, Copy, Debug)]
+pub struct Signed<'c, 'a>(pub Value<'c, 'a>);
+
+/// An integer read as unsigned.
+#[derive(Clone, Copy, Debug)]
+pub struct Unsigned<'c, 'a>(pub Value<'c, 'a>);
+
+/* Check `lhs` and `rhs` are integers of the same width. */
+fn same_width(lhs: Value, rhs: Value) -> Result<(), Error> {
+    let width = |value: Value| bit_width(value.r#type()).ok_or(Error::UnknownWidth(value.r#type().to_string()));
+    let (expected, found) = (width(lhs)?, width(rhs)?);
+    if expected != found {
+        return Err(Error::WidthMismatch { expected, found });
+    }
+    Ok(())
+}
+
+/* Append the binary `comb` operation `name`, whose result has the operands' type. */
+fn binary<'c, 'a>(block: &'a Block<'c>, name: &str, lhs: Value<'c, '_>, rhs: Value<'c, '_>,
+                  location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    same_width(lhs, rhs)?;
+    let operation = OperationBuilder::new(name, location)
+        .add_operands(&[lhs, rhs])
+        .add_results(&[lhs.r#type()])
+        .build()
+        .expect("valid operation");
+    Ok(block.append(operation).result(0).unwrap().into())
+}
+
+/// An integer with a signedness, implemented by [`Signed`] and [`Unsigned`].
+pub trait Integer<'c, 'a>: Copy + Sized {
+    const SIGNEDNESS: Signedness;
+
+    fn value(self) -> Value<'c, 'a>;
+
+    fn wrap(value: Value<'c, 'a>) -> Self;
+
+    /// `self < rhs`, an `i1`.
+    fn lt(self, ctx: &'c Context, block: &'a Block<'c>, rhs: Self,
+          location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+        compare::<Self>(ctx, block, (Predicate::Slt, Predicate::Ult), self, rhs, location)
+    }
+
+    /// `self <= rhs`, an `i1`.
+    fn le(self, ctx: &'c Context, block: &'a Block<'c>, rhs: Self,
+          location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+        compare::<Self>(ctx, block, (Predicate::Sle, Predicate::Ule), self, rhs, location)
+    }
+
+    /// `self > rhs`, an `i1`.
+    fn gt(self, ctx: &'c Context, block: &'a Block<'c>, rhs: Self,
+          location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+        compare::<Self>(ctx, block, (Predicate::Sgt, Predicate::Ugt), self, rhs, location)
+    }
+
+    /// `self >= rhs`, an `i1`.
+    fn ge(self, ctx: &'c Context, block: &'a Block<'c>, rhs: Self,
+          location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+        compare::<Self>(ctx, block, (Predicate::Sge, Predicate::Uge), self, rhs, location)
+    }
+
+    /// `comb.divs` or `comb.divu`.
+    fn div(self, block: &'a Block<'c>, rhs: Self, location: Location<'c>) -> Result<Self, Error> {
+        let name = pick::<Self, _>("comb.divs", "comb.divu");
+        binary(block, name, self.value(), rhs.value(), location).map(Self::wrap)
+    }
+
+    /// `comb.mods` or `comb.modu`. The signed remainder has the sign of the dividend.
+    fn rem(self, block: &'a Block<'c>, rhs: Self, location: Location<'c>) -> Result<Self, Error> {
+        let name = pick::<Self, _>("comb.mods", "comb.modu");
+        binary(block, name, self.value(), rhs.value(), location).map(Self::wrap)
+    }
+
+    /// `comb.shrs`, shifting in copies of the sign bit, or `comb.shru`, shifting in zeros.
+    fn shr(self, block: &'a Block<'c>, amount: Value<'c, '_>, location: Location<'c>) -> Result<Self, Error> {
+        let name = pick::<Self, _>("comb.shrs", "comb.shru");
+        binary(block, name, self.value(), amount, location).map(Self::wrap)
+    }
+}
+
+/* `signed` or `unsigned`, whichever matches the signedness of `I`. */
+fn pick<'c, 'a, I: Integer<'c, 'a>, T>(signed: T, unsigned: T) -> T {
+    match I::SIGNEDNESS {
+        Signedness::Signed => signed,
+        Signedness::Unsigned => unsigned,
+    }
+}
+
+fn compare<'c, 'a, I: Integer<'c, 'a>>(ctx: &'c Context, block: &'a Block<'c>, predicates: (Predicate, Predicate),
+                                       lhs: I, rhs: I, location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    same_width(lhs.value(), rhs.value())?;
+    Ok(icmp(ctx, block, pick::<I, _>(predicates.0, predicates.1), lhs.value(), rhs.value(), location))
+}
+
+impl<'c, 'a> Integer<'c, 'a> for Signed<'c, 'a> {
+    const SIGNEDNESS: Signedness = Signedness::Signed;
+
+    fn value(self) -> Value<'c, 'a> {
+        self.0
+    }
+
+    fn wrap(value: Value<'c, 'a>) -> Self {
+        Signed(value)
+    }
+}
+
+impl<'c, 'a> Integer<'c, 'a> for Unsigned<'c, 'a> {
+    const SIGNEDNESS: Signedness = Signedness::Unsigned;
+
+    fn value(self) -> Value<'c, 'a> {
+        self.0
+    }
+
+    fn wrap(value: Value<'c, 'a>) -> Self {
+        Unsigned(value)
+    }
+}
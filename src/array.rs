@@ -0,0 +1,204 @@
+//! `hw.uarray` unpacked arrays, for memories and unpacked array ports that must stay distinct
+//! from packed `hw.array`s in the emitted Verilog.
+//!
+//! ```ignore
+//! let memory = decl::reg(&ctx, &body, "memory", array::unpacked_array_type(&ctx, i8, 256), here!(ctx));
+//! let slot = array::index_inout(&body, memory, address, here!(ctx));
+//! let byte = array::read(&body, memory, address, here!(ctx));
+//! ```
+//!
+//! Packed `hw.array` ports group the repeated ports of a multi-lane design into one port:
+//!
+//! ```ignore
+//! let data = array::array_type(&ctx, i32, lanes);
+//! ports::module(&design, "Lanes", &[("in", Input, data), ("out", Output, data)], |body| {
+//!     let inputs = array::lanes(&ctx, body, body.argument(0)?.into(), here!(ctx));
+//!     ...
+//!     Ok(vec![array::array_create(&ctx, body, &outputs, here!(ctx))?])
+//! }, here!(ctx))?;
+//! ```
+//!
+//! or, with the same logic in each lane, unrolled or in a loop:
+//!
+//! ```ignore
+//! let outputs = array::map_lanes(&ctx, body, input, Replication::Loop, |block, index, lane| ..., here!(ctx))?;
+//! ```
+
+use melior::dialect::ods::{comb, hw, sv};
+use melior::ir::attribute::{IntegerAttribute, StringAttribute};
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, BlockLike, Identifier, Location, Region, RegionLike, Type, TypeLike, Value, ValueLike};
+use melior::Context;
+
+use crate::always::always_comb;
+use crate::decl::logic;
+use crate::diagnostics::Append;
+use crate::Error;
+
+/// `!hw.array<size x element>`, a packed array, emitted as `[size-1:0][width-1:0]`.
+pub fn array_type<'c>(ctx: &'c Context, element: Type<'c>, size: u64) -> Type<'c> {
+    let source = format!("!hw.array<{size} x {element}>");
+    Type::parse(ctx, &source).unwrap_or_else(|| panic!("`{source}` is a valid array type"))
+}
+
+/// Each lane of the packed `array`, lane 0 first, read with `hw.array_get` at a constant index.
+pub fn lanes<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, array: Value<'c, '_>,
+                     location: Location<'c>) -> Vec<Value<'c, 'a>> {
+    let size = unsafe { mlir_sys::hwArrayTypeGetSize(array.r#type().to_raw()) } as u64;
+    // `hw.array_get` indices are exactly clog2(size) bits, none for a single lane
+    let index_type: Type = IntegerType::new(ctx, size.next_power_of_two().trailing_zeros()).into();
+    (0..size).map(|lane| {
+        let constant = hw::constant(ctx, index_type, IntegerAttribute::new(index_type, lane as i64).into(), location);
+        let index: Value = block.append(constant.into()).result(0).unwrap().into();
+        let get = OperationBuilder::new("hw.array_get", location)
+            .add_operands(&[array, index])
+            .enable_result_type_inference()
+            .build()
+            .expect("valid operation");
+        block.append(get).result(0).unwrap().into()
+    }).collect()
+}
+
+/// Append `hw.array_create` of `lanes`, given lane 0 first, to `block`, for driving an array
+/// typed output port. The lanes, at least one, must all have the same type.
+pub fn array_create<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, lanes: &[Value<'c, '_>],
+                            location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let Some(first) = lanes.first() else {
+        return Err(Error::TooFewOperands { operation: "hw.array_create", minimum: 1, found: 0 });
+    };
+    let r#type = array_type(ctx, first.r#type(), lanes.len() as u64);
+    // `hw.array_create` takes the highest index first
+    let operands: Vec<Value> = lanes.iter().rev().copied().collect();
+    let create = OperationBuilder::new("hw.array_create", location)
+        .add_operands(&operands)
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    Ok(block.append(create).result(0).unwrap().into())
+}
+
+/// How [`map_lanes`] replicates the logic of a lane.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Replication {
+    /// A copy of the logic for each lane, unrolled at build time.
+    #[default]
+    Unroll,
+    /// One copy, in a loop over the lanes, for consumers that prefer compact output. CIRCT's `sv`
+    /// dialect has `sv.generate` but no `generate for`, so this is a procedural `sv.for` in an
+    /// `always_comb`, assigning each lane of a variable. A single lane is never looped over.
+    Loop,
+}
+
+/// Build per-lane logic: `body` maps each lane of the packed `array`, with its index, to a lane of
+/// the result, building it in the block it is given, which is procedural for a loop. The index is
+/// an integer wide enough to count the lanes, a constant when they are unrolled.
+pub fn map_lanes<'c, 'a, F>(ctx: &'c Context, block: &'a Block<'c>, array: Value<'c, '_>, replication: Replication,
+                            mut body: F, location: Location<'c>) -> Result<Value<'c, 'a>, Error>
+where
+    F: for<'b> FnMut(&'b Block<'c>, Value<'c, 'b>, Value<'c, 'b>) -> Result<Value<'c, 'b>, Error>,
+{
+    let size = unsafe { mlir_sys::hwArrayTypeGetSize(array.r#type().to_raw()) } as u64;
+    let counter: Type = IntegerType::new(ctx, 64 - size.leading_zeros()).into();
+    if replication == Replication::Unroll || size == 1 {
+        let lanes = lanes(ctx, block, array, location).into_iter().enumerate().map(|(index, lane)| {
+            let constant = hw::constant(ctx, counter, IntegerAttribute::new(counter, index as i64).into(), location);
+            let index = block.append(constant.into()).result(0).unwrap().into();
+            body(block, index, lane)
+        }).collect::<Result<Vec<_>, _>>()?;
+        return array_create(ctx, block, &lanes, location);
+    }
+    let index_type: Type = IntegerType::new(ctx, size.next_power_of_two().trailing_zeros()).into();
+    let loop_block = Block::new(&[(counter, location)]);
+    let variable = {
+        let counter_value: Value = loop_block.argument(0).unwrap().into();
+        // `hw.array_get` indices are exactly clog2(size) bits, where counting to a power of two takes one more
+        let index = if index_type == counter {
+            counter_value
+        } else {
+            let low_bit = IntegerAttribute::new(IntegerType::new(ctx, 32).into(), 0);
+            loop_block.append(comb::extract(ctx, index_type, counter_value, low_bit, location).into())
+                .result(0).unwrap().into()
+        };
+        let get = OperationBuilder::new("hw.array_get", location)
+            .add_operands(&[array, index])
+            .enable_result_type_inference()
+            .build()
+            .expect("valid operation");
+        let lane = loop_block.append(get).result(0).unwrap().into();
+        let result = body(&loop_block, counter_value, lane)?;
+        let variable = logic(ctx, block, "lanes", array_type(ctx, result.r#type(), size), location);
+        let slot = index_inout(&loop_block, variable, index, location);
+        loop_block.append(sv::bpassign(ctx, slot, result, location).into());
+        variable
+    };
+    always_comb(ctx, block, |comb_block| {
+        let bounds: Vec<Value> = [0, size, 1].into_iter().map(|bound| {
+            let constant = hw::constant(ctx, counter, IntegerAttribute::new(counter, bound as i64).into(), location);
+            comb_block.append(constant.into()).result(0).unwrap().into()
+        }).collect();
+        let region = Region::new();
+        region.append_block(loop_block);
+        let for_loop = OperationBuilder::new("sv.for", location)
+            .add_operands(&bounds)
+            .add_attributes(&[(Identifier::new(ctx, "inductionVarName"), StringAttribute::new(ctx, "lane").into())])
+            .add_regions([region])
+            .build()
+            .expect("valid operation");
+        comb_block.append(for_loop);
+    }, location);
+    let read = OperationBuilder::new("sv.read_inout", location)
+        .add_operands(&[variable])
+        .enable_result_type_inference()
+        .build()
+        .expect("valid operation");
+    Ok(block.append(read).result(0).unwrap().into())
+}
+
+/// `!hw.uarray<size x element>`, emitted as `element name [0:size-1]`.
+pub fn unpacked_array_type<'c>(ctx: &'c Context, element: Type<'c>, size: u64) -> Type<'c> {
+    let source = format!("!hw.uarray<{size} x {element}>");
+    Type::parse(ctx, &source).unwrap_or_else(|| panic!("`{source}` is a valid unpacked array type"))
+}
+
+/// Append `sv.unpacked_array_create` of `elements` to `block`, highest index first as with
+/// `hw.array_create`. The elements, at least one, must all have the same type.
+pub fn unpacked_array_create<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, elements: &[Value<'c, '_>],
+                                     location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let Some(first) = elements.first() else {
+        return Err(Error::TooFewOperands { operation: "sv.unpacked_array_create", minimum: 1, found: 0 });
+    };
+    let r#type = unpacked_array_type(ctx, first.r#type(), elements.len() as u64);
+    let create = OperationBuilder::new("sv.unpacked_array_create", location)
+        .add_operands(elements)
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    Ok(block.append(create).result(0).unwrap().into())
+}
+
+/// Append `sv.array_index_inout array[index]` to `block`: the inout element of the inout packed or
+/// unpacked `array` at `index`, which must be `clog2(size)` bits wide. The element can be read,
+/// or assigned like any other variable.
+pub fn index_inout<'c, 'a>(block: &'a Block<'c>, array: Value<'c, '_>, index: Value<'c, '_>,
+                           location: Location<'c>) -> Value<'c, 'a> {
+    let index = OperationBuilder::new("sv.array_index_inout", location)
+        .add_operands(&[array, index])
+        .enable_result_type_inference()
+        .build()
+        .expect("valid operation");
+    block.append(index).result(0).unwrap().into()
+}
+
+/// Read the element of the inout packed or unpacked `array` at `index`, an `sv.array_index_inout`
+/// followed by `sv.read_inout`.
+pub fn read<'c, 'a>(block: &'a Block<'c>, array: Value<'c, '_>, index: Value<'c, '_>,
+                    location: Location<'c>) -> Value<'c, 'a> {
+    let element = index_inout(block, array, index, location);
+    let read = OperationBuilder::new("sv.read_inout", location)
+        .add_operands(&[element])
+        .enable_result_type_inference()
+        .build()
+        .expect("valid operation");
+    block.append(read).result(0).unwrap().into()
+}
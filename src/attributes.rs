@@ -0,0 +1,114 @@
+//! Safe constructors for the `sv` dialect attributes operations built with `OperationBuilder`
+//! need, so none of them has to be made with `Attribute::from_raw`. Edges and deferred assertion
+//! modes are [`EventControl::attribute`](crate::always::EventControl::attribute) and
+//! [`Defer::attribute`](crate::verif::Defer::attribute), macro names in `` `ifdef `` conditions
+//! [`macros::ident`](crate::macros::ident).
+//!
+//! ```ignore
+//! let keep = SvAttribute::new("keep");
+//! set_sv_attributes(&wire, &[keep, SvAttribute::with_expression("max_fanout", "8")]);
+//! ```
+
+use melior::ir::attribute::{ArrayAttribute, IntegerAttribute};
+use melior::ir::operation::OperationLike;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Attribute, AttributeLike, Operation};
+use melior::{Context, StringRef};
+
+/// The kind of an `sv.case` statement, `sv::CaseStmtType` in CIRCT.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CaseStyle {
+    /// `case`, matching patterns exactly.
+    #[default]
+    Case,
+    /// `casex`, treating `x` and `z` in patterns as don't cares.
+    CaseX,
+    /// `casez`, treating `z` in patterns as don't cares.
+    CaseZ,
+}
+
+impl CaseStyle {
+    /// The `caseStyle` attribute of `sv.case`.
+    pub fn attribute<'c>(self, ctx: &'c Context) -> Attribute<'c> {
+        let value = match self {
+            CaseStyle::Case => 0,
+            CaseStyle::CaseX => 1,
+            CaseStyle::CaseZ => 2,
+        };
+        IntegerAttribute::new(IntegerType::new(ctx, 32).into(), value).into()
+    }
+}
+
+/// The qualifier of an `sv.case` statement, telling synthesis which arms can match at once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ValidationQualifier {
+    #[default]
+    Plain,
+    /// `unique case`: exactly one arm matches.
+    Unique,
+    /// `unique0 case`: at most one arm matches.
+    Unique0,
+    /// `priority case`: the first matching arm is taken, and one always matches.
+    Priority,
+}
+
+impl ValidationQualifier {
+    fn keyword(self) -> &'static str {
+        match self {
+            ValidationQualifier::Plain => "plain",
+            ValidationQualifier::Unique => "unique",
+            ValidationQualifier::Unique0 => "unique0",
+            ValidationQualifier::Priority => "priority",
+        }
+    }
+
+    /// The `validationQualifier` attribute of `sv.case`.
+    pub fn attribute<'c>(self, ctx: &'c Context) -> Attribute<'c> {
+        Attribute::parse(ctx, &format!("#sv<validation_qualifier {}>", self.keyword()))
+            .expect("valid validation qualifier")
+    }
+}
+
+/// A SystemVerilog attribute, `(* name = expression *)`, on a declaration or statement, such as
+/// `(* keep *)` on a wire or `(* ram_style = "block" *)` on a memory. `#sv.attribute` in CIRCT.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SvAttribute {
+    pub name: String,
+    /// The value, emitted as is: string values need their quotes.
+    pub expression: Option<String>,
+    /// Emit it as a comment, `/* name = expression */`, for tools that reject unknown attributes.
+    pub emit_as_comment: bool,
+}
+
+impl SvAttribute {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), expression: None, emit_as_comment: false }
+    }
+
+    pub fn with_expression(name: &str, expression: &str) -> Self {
+        Self { expression: Some(expression.to_string()), ..Self::new(name) }
+    }
+
+    pub fn attribute<'c>(&self, ctx: &'c Context) -> Attribute<'c> {
+        let mut text = format!("#sv.attribute<{:?}", self.name);
+        if let Some(expression) = &self.expression {
+            text.push_str(&format!(" = {expression:?}"));
+        }
+        if self.emit_as_comment {
+            text.push_str(", emitAsComment");
+        }
+        text.push('>');
+        Attribute::parse(ctx, &text).expect("valid sv attribute")
+    }
+}
+
+/// Emit `attributes` before `operation`, a declaration like `sv.wire` or `sv.reg`, or a
+/// statement, replacing any it had.
+pub fn set_sv_attributes(operation: &Operation, attributes: &[SvAttribute]) {
+    let ctx = unsafe { operation.context().to_ref() };
+    let attributes: Vec<Attribute> = attributes.iter().map(|attribute| attribute.attribute(ctx)).collect();
+    unsafe {
+        mlir_sys::mlirOperationSetAttributeByName(operation.to_raw(), StringRef::new("sv.attributes").to_raw(),
+                                                  ArrayAttribute::new(ctx, &attributes).to_raw());
+    }
+}
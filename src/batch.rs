@@ -0,0 +1,215 @@
+//! Generating one module per row of a parameter table, for design space exploration. Rows are
+//! generated in parallel, each in its own [`Context`] since contexts can't be shared between
+//! threads.
+//!
+//! ```ignore
+//! let table = ParameterTable::from_csv("width,depth\n8,16\n16,32\n")?;
+//! let options = BatchOptions { base_name: "fifo".to_string(), wrapper: Some("fifos".to_string()) };
+//! let files = batch::generate(&table, Path::new("out"), &options, |design, name, row| {
+//!     build_fifo(design, name, row.parse("width")?, row.parse("depth")?)
+//! })?;
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use melior::ir::{BlockLike, Location, Type, Value};
+use melior::Context;
+
+use crate::export::{export_split_verilog, ExportOptions};
+use crate::ports::{declare_extern, find_module, instance, module, module_ports, Port, PortDirection};
+use crate::{load_dialects, Design, Error};
+
+/// One row of a [`ParameterTable`]: parameter name -> value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParameterSet {
+    values: BTreeMap<String, String>,
+}
+
+impl ParameterSet {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// The value of `name` parsed as a `T`.
+    pub fn parse<T: FromStr>(&self, name: &str) -> Result<T, Error> {
+        let value = self.get(name).ok_or_else(|| Error::InvalidTable(format!("no `{name}` parameter")))?;
+        value.parse().map_err(|_| Error::InvalidTable(format!("invalid `{name}` parameter `{value}`")))
+    }
+}
+
+/// The parameter sets to generate variants for.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParameterTable {
+    pub rows: Vec<ParameterSet>,
+}
+
+impl ParameterTable {
+    /// Parse a CSV table with a header row naming the parameters. Fields are separated by commas
+    /// and trimmed; quoting isn't supported.
+    pub fn from_csv(text: &str) -> Result<Self, Error> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header: Vec<&str> = lines.next().unwrap_or_default().split(',').map(str::trim).collect();
+        let mut rows = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != header.len() {
+                return Err(Error::InvalidTable(format!("row {} has {} fields, the header has {}", index + 1,
+                                                       fields.len(), header.len())));
+            }
+            let values = header.iter().zip(fields).map(|(name, value)| (name.to_string(), value.to_string())).collect();
+            rows.push(ParameterSet { values });
+        }
+        Ok(Self { rows })
+    }
+
+    /// Parse a JSON array of objects, one per row, whose values are strings, numbers, or booleans.
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        let table: serde_json::Value = serde_json::from_str(text).map_err(|err| Error::InvalidTable(err.to_string()))?;
+        let serde_json::Value::Array(objects) = table else {
+            return Err(Error::InvalidTable("expected an array of parameter sets".to_string()));
+        };
+        let mut rows = Vec::new();
+        for (index, object) in objects.into_iter().enumerate() {
+            let serde_json::Value::Object(object) = object else {
+                return Err(Error::InvalidTable(format!("row {} is not an object", index + 1)));
+            };
+            let mut values = BTreeMap::new();
+            for (name, value) in object {
+                let value = match value {
+                    serde_json::Value::String(value) => value,
+                    serde_json::Value::Number(_) | serde_json::Value::Bool(_) => value.to_string(),
+                    _ => return Err(Error::InvalidTable(format!("row {} `{name}` is not a scalar", index + 1))),
+                };
+                values.insert(name, value);
+            }
+            rows.push(ParameterSet { values });
+        }
+        Ok(Self { rows })
+    }
+}
+
+/// Options for [`generate`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// Variants are named `<base_name>_<row>`, unless their row has a `name` parameter.
+    pub base_name: String,
+    /// Also generate a module with this name instantiating every variant, with their ports
+    /// exposed as `<variant>_<port>`.
+    pub wrapper: Option<String>,
+}
+
+/* A variant that was generated: its name and ports, for the wrapper. */
+struct Variant {
+    name: String,
+    ports: Vec<Port>,
+}
+
+/// Generate a variant for each row of `table` with `generate`, which builds a module with the
+/// name it is given in a fresh design, and export each variant to `dir/<name>/`. Returns the files
+/// written, in row order, then the wrapper's.
+pub fn generate<F>(table: &ParameterTable, dir: &Path, options: &BatchOptions, generate: F)
+                   -> Result<Vec<PathBuf>, Error>
+where
+    F: Fn(&Design, &str, &ParameterSet) -> Result<(), Error> + Sync,
+{
+    let names: Vec<String> = table.rows.iter().enumerate()
+        .map(|(index, row)| row.get("name").map_or_else(|| format!("{}_{index}", options.base_name), str::to_string))
+        .collect();
+    let results: Vec<Mutex<Option<Result<(Variant, Vec<PathBuf>), Error>>>> =
+        table.rows.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get()).min(table.rows.len());
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= table.rows.len() {
+                        break;
+                    }
+                    let result = generate_variant(&names[index], &table.rows[index], dir, &generate);
+                    *results[index].lock().unwrap() = Some(result);
+                }
+            });
+        }
+    });
+
+    let mut variants = Vec::new();
+    let mut files = Vec::new();
+    for result in results {
+        let (variant, variant_files) = result.into_inner().unwrap().expect("every row is generated")?;
+        variants.push(variant);
+        files.extend(variant_files);
+    }
+    if let Some(wrapper) = &options.wrapper {
+        files.extend(generate_wrapper(wrapper, &variants, dir)?);
+    }
+    Ok(files)
+}
+
+#[tracing::instrument(skip_all, fields(variant = name))]
+fn generate_variant<F>(name: &str, row: &ParameterSet, dir: &Path, generate: &F)
+                       -> Result<(Variant, Vec<PathBuf>), Error>
+where
+    F: Fn(&Design, &str, &ParameterSet) -> Result<(), Error>,
+{
+    let ctx = Context::new();
+    load_dialects(&ctx);
+    let design = Design::new(&ctx, Location::unknown(&ctx));
+    generate(&design, name, row)?;
+    let ports = find_module(&design, name)
+        .map(|module| module_ports(&module))
+        .ok_or_else(|| Error::InvalidTable(format!("the generator didn't build a module named `{name}`")))?;
+    let files = export_variant(&design, &dir.join(name))?;
+    Ok((Variant { name: name.to_string(), ports }, files))
+}
+
+fn export_variant(design: &Design, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    std::fs::create_dir_all(dir).map_err(|err| Error::Io(format!("{}: {err}", dir.display())))?;
+    export_split_verilog(design, dir, &ExportOptions::default())
+}
+
+/* Build and export the module instantiating every variant. The variants are declared with
+   `hw.module.extern` so they aren't emitted again. */
+fn generate_wrapper(name: &str, variants: &[Variant], dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let ctx = Context::new();
+    load_dialects(&ctx);
+    let location = Location::unknown(&ctx);
+    let design = Design::new(&ctx, location);
+
+    let mut wrapper_ports = Vec::new();
+    for variant in variants {
+        for port in &variant.ports {
+            wrapper_ports.push((format!("{}_{}", variant.name, port.name), port.direction, port.parse_type(&ctx)?));
+        }
+        declare_extern(&design, &variant.name, &variant.ports, location)?;
+    }
+    let ports: Vec<(&str, PortDirection, Type)> = wrapper_ports.iter()
+        .map(|(name, direction, r#type)| (name.as_str(), *direction, *r#type))
+        .collect();
+
+    module(&design, name, &ports, |body| {
+        let mut next_input = 0;
+        let mut outputs: Vec<Value> = Vec::new();
+        for variant in variants {
+            let mut inputs = Vec::new();
+            let mut results = Vec::new();
+            for port in &variant.ports {
+                if port.direction == PortDirection::Output {
+                    results.push((port.name.as_str(), port.parse_type(&ctx)?));
+                } else {
+                    inputs.push((port.name.as_str(), body.argument(next_input).unwrap().into()));
+                    next_input += 1;
+                }
+            }
+            outputs.extend(instance(&design, body, Some(&variant.name), &variant.name, &inputs, &results, location));
+        }
+        Ok(outputs)
+    }, location)?;
+    export_variant(&design, &dir.join(name))
+}
@@ -0,0 +1,139 @@
+//! Integers whose width is part of their Rust type, so mismatched widths in arithmetic,
+//! concatenation, and slicing are compile errors instead of verifier errors when the design is
+//! built. Values are checked once, when they are wrapped.
+//!
+//! ```ignore
+//! let a = Bits::<8>::new(body.argument(0)?.into())?;
+//! let b = Bits::<8>::constant(&ctx, &body, 3, here!(ctx))?;
+//! let sum: Bits<9> = a.zext(&ctx, &body, here!(ctx)).add(&body, b.zext(&ctx, &body, here!(ctx)), here!(ctx));
+//! let word: Bits<16> = a.concat(&ctx, &body, b, here!(ctx));
+//! ```
+
+use melior::dialect::ods::comb;
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, Location, Value, ValueLike};
+use melior::Context;
+
+use crate::compare::{icmp, Predicate};
+use crate::constant::{const_int, Signedness};
+use crate::diagnostics::Append;
+use crate::width::{self, extract};
+use crate::Error;
+
+/// A `W` bit integer.
+#[derive(Clone, Copy, Debug)]
+pub struct Bits<'c, 'a, const W: usize>(Value<'c, 'a>);
+
+impl<'c, 'a, const W: usize> Bits<'c, 'a, W> {
+    /// Wrap `value`, which must be a `W` bit integer, an `iW`.
+    pub fn new(value: Value<'c, 'a>) -> Result<Self, Error> {
+        const { assert!(W > 0, "a value has at least one bit") };
+        let r#type = IntegerType::try_from(value.r#type()).map_err(|_| {
+            Error::TypeMismatch { expected: format!("an i{W}"), found: value.r#type().to_string() }
+        })?;
+        let found = r#type.width() as u64;
+        if found != W as u64 {
+            return Err(Error::WidthMismatch { expected: W as u64, found });
+        }
+        Ok(Bits(value))
+    }
+
+    /// Append a `W` bit `hw.constant` of the unsigned `value`.
+    pub fn constant(ctx: &'c Context, block: &'a Block<'c>, value: u64,
+                    location: Location<'c>) -> Result<Self, Error> {
+        const { assert!(W > 0, "a value has at least one bit") };
+        const_int(ctx, block, W as u32, i128::from(value), Signedness::Unsigned, location).map(Bits)
+    }
+
+    pub fn value(self) -> Value<'c, 'a> {
+        self.0
+    }
+
+    /* The `comb` operation `name` over `self` and `rhs`, of the same width. */
+    fn binary(self, block: &'a Block<'c>, name: &str, rhs: Self, location: Location<'c>) -> Self {
+        let operation = OperationBuilder::new(name, location)
+            .add_operands(&[self.0, rhs.0])
+            .add_results(&[self.0.r#type()])
+            .build()
+            .expect("valid operation");
+        Bits(block.append(operation).result(0).unwrap().into())
+    }
+
+    /// `self + rhs`, wrapping.
+    pub fn add(self, block: &'a Block<'c>, rhs: Self, location: Location<'c>) -> Self {
+        self.binary(block, "comb.add", rhs, location)
+    }
+
+    /// `self - rhs`, wrapping.
+    pub fn sub(self, block: &'a Block<'c>, rhs: Self, location: Location<'c>) -> Self {
+        self.binary(block, "comb.sub", rhs, location)
+    }
+
+    /// The low `W` bits of `self * rhs`.
+    pub fn mul(self, block: &'a Block<'c>, rhs: Self, location: Location<'c>) -> Self {
+        self.binary(block, "comb.mul", rhs, location)
+    }
+
+    pub fn and(self, block: &'a Block<'c>, rhs: Self, location: Location<'c>) -> Self {
+        self.binary(block, "comb.and", rhs, location)
+    }
+
+    pub fn or(self, block: &'a Block<'c>, rhs: Self, location: Location<'c>) -> Self {
+        self.binary(block, "comb.or", rhs, location)
+    }
+
+    pub fn xor(self, block: &'a Block<'c>, rhs: Self, location: Location<'c>) -> Self {
+        self.binary(block, "comb.xor", rhs, location)
+    }
+
+    /// `self == rhs`.
+    pub fn eq(self, ctx: &'c Context, block: &'a Block<'c>, rhs: Self, location: Location<'c>) -> Bits<'c, 'a, 1> {
+        Bits(icmp(ctx, block, Predicate::Eq, self.0, rhs.0, location))
+    }
+
+    /// `select ? self : otherwise`.
+    pub fn mux(self, block: &'a Block<'c>, select: Bits<'c, '_, 1>, otherwise: Self, location: Location<'c>) -> Self {
+        let operation = OperationBuilder::new("comb.mux", location)
+            .add_operands(&[select.0, self.0, otherwise.0])
+            .add_results(&[self.0.r#type()])
+            .build()
+            .expect("valid operation");
+        Bits(block.append(operation).result(0).unwrap().into())
+    }
+
+    /// `{self, low}`: `self` in the high bits. `R` must be the sum of the widths.
+    pub fn concat<const L: usize, const R: usize>(self, ctx: &'c Context, block: &'a Block<'c>, low: Bits<'c, '_, L>,
+                                                  location: Location<'c>) -> Bits<'c, 'a, R> {
+        const { assert!(W + L == R, "a concatenation is as wide as its operands together") };
+        Bits(block.append(comb::concat(ctx, &[self.0, low.0], location).into()).result(0).unwrap().into())
+    }
+
+    /// The `R` bits of `self` starting at bit `LOW`, which must all be within `self`.
+    pub fn slice<const LOW: usize, const R: usize>(self, ctx: &'c Context, block: &'a Block<'c>,
+                                                   location: Location<'c>) -> Bits<'c, 'a, R> {
+        const { assert!(R > 0 && LOW + R <= W, "a slice is within the value") };
+        Bits(extract(ctx, block, self.0, LOW as u64, R as u64, location))
+    }
+
+    /// Zero extend to `R` bits, at least `W`.
+    pub fn zext<const R: usize>(self, ctx: &'c Context, block: &'a Block<'c>,
+                                location: Location<'c>) -> Bits<'c, 'a, R> {
+        const { assert!(R >= W, "zero extension doesn't narrow") };
+        Bits(width::zext(ctx, block, self.0, R as u64, location).expect("the widths are checked at compile time"))
+    }
+
+    /// Sign extend to `R` bits, at least `W`.
+    pub fn sext<const R: usize>(self, ctx: &'c Context, block: &'a Block<'c>,
+                                location: Location<'c>) -> Bits<'c, 'a, R> {
+        const { assert!(R >= W, "sign extension doesn't narrow") };
+        Bits(width::sext(ctx, block, self.0, R as u64, location).expect("the widths are checked at compile time"))
+    }
+
+    /// The low `R` bits, at most `W`.
+    pub fn trunc<const R: usize>(self, ctx: &'c Context, block: &'a Block<'c>,
+                                 location: Location<'c>) -> Bits<'c, 'a, R> {
+        const { assert!(R > 0 && R <= W, "truncation doesn't widen") };
+        Bits(width::trunc(ctx, block, self.0, R as u64, location).expect("the widths are checked at compile time"))
+    }
+}
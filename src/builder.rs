@@ -0,0 +1,145 @@
+//! An insertion point for building operations: the block they are appended to and the location
+//! they get, so nested procedural regions can be built with closures instead of passing blocks
+//! around.
+//!
+//! ```ignore
+//! let builder = IrBuilder::new(&ctx, &body, here!(ctx));
+//! builder.always(&[(Posedge, clk)], |always| {
+//!     always.if_(reset, |then| { then.append(sv::passign(&ctx, count, zero, then.location()).into()); },
+//!                |otherwise| { ... });
+//! });
+//! ```
+//!
+//! To add to an existing block, switch to it for a scope; the previous block is restored after:
+//!
+//! ```ignore
+//! builder.with_block(&other, |builder| { builder.append(...); });
+//! ```
+
+use std::cell::Cell;
+
+use melior::dialect::ods::sv;
+use melior::ir::operation::{OperationLike, OperationRef};
+use melior::ir::{Block, Location, Operation, Region, RegionLike, Value};
+use melior::Context;
+
+use crate::always::{self, if_, EventControl};
+use crate::diagnostics::Append;
+use crate::locations;
+
+/// Appends operations to the end of a block, at a location.
+#[derive(Clone)]
+pub struct IrBuilder<'c, 'a> {
+    ctx: &'c Context,
+    block: Cell<&'a Block<'c>>,
+    location: Location<'c>,
+}
+
+/// Restores the insertion block a builder had before [`IrBuilder::insertion_guard`] when dropped.
+#[must_use = "the insertion block is restored when the guard is dropped"]
+pub struct InsertionGuard<'g, 'c, 'a> {
+    builder: &'g IrBuilder<'c, 'a>,
+    previous: &'a Block<'c>,
+}
+
+impl Drop for InsertionGuard<'_, '_, '_> {
+    fn drop(&mut self) {
+        self.builder.block.set(self.previous);
+    }
+}
+
+impl<'c, 'a> IrBuilder<'c, 'a> {
+    pub fn new(ctx: &'c Context, block: &'a Block<'c>, location: Location<'c>) -> Self {
+        Self { ctx, block: Cell::new(block), location }
+    }
+
+    pub fn context(&self) -> &'c Context {
+        self.ctx
+    }
+
+    /// The block operations are appended to.
+    pub fn block(&self) -> &'a Block<'c> {
+        self.block.get()
+    }
+
+    /// Append to `block` until the returned guard is dropped, then to the current block again.
+    pub fn insertion_guard(&self, block: &'a Block<'c>) -> InsertionGuard<'_, 'c, 'a> {
+        InsertionGuard { builder: self, previous: self.block.replace(block) }
+    }
+
+    /// Run `build` appending to `block`, restoring the insertion block afterwards, even if `build`
+    /// panics.
+    pub fn with_block<R>(&self, block: &'a Block<'c>, build: impl FnOnce(&Self) -> R) -> R {
+        let _guard = self.insertion_guard(block);
+        build(self)
+    }
+
+    pub fn location(&self) -> Location<'c> {
+        self.location
+    }
+
+    /// This insertion point, with operations built at `location`.
+    pub fn at(&self, location: Location<'c>) -> Self {
+        Self { location, ..self.clone() }
+    }
+
+    /// This insertion point, with its location under `name`, see [`locations::named`].
+    pub fn named(&self, name: &str) -> Self {
+        self.at(locations::named(self.ctx, name, self.location))
+    }
+
+    /// This insertion point, with its location fused with `other`, see [`locations::fused`].
+    pub fn fused_with(&self, other: Location<'c>) -> Self {
+        self.at(locations::fused(self.ctx, &[other, self.location]))
+    }
+
+    /// A builder for `block` at this builder's location.
+    pub fn within<'b>(&self, block: &'b Block<'c>) -> IrBuilder<'c, 'b> {
+        IrBuilder::new(self.ctx, block, self.location)
+    }
+
+    /// Append `operation` at the insertion point.
+    pub fn append(&self, operation: Operation<'c>) -> OperationRef<'c, 'a> {
+        self.block().append(operation)
+    }
+
+    /// Append `operation`, returning its first result.
+    pub fn value(&self, operation: Operation<'c>) -> Value<'c, 'a> {
+        self.append(operation).result(0).unwrap().into()
+    }
+
+    /// A region of one block, which `build` fills in through a builder for it.
+    pub fn region<R>(&self, build: impl FnOnce(&IrBuilder<'c, '_>) -> R) -> (Region<'c>, R) {
+        let block = Block::new(&[]);
+        let result = build(&self.within(&block));
+        let region = Region::new();
+        region.append_block(block);
+        (region, result)
+    }
+
+    /// Append `sv.if condition`, with `then` and `otherwise` building its branches.
+    pub fn if_<R>(&self, condition: Value<'c, '_>, then: impl FnOnce(&IrBuilder<'c, '_>) -> R,
+                  otherwise: impl FnOnce(&IrBuilder<'c, '_>)) -> R {
+        let (then, result) = self.region(then);
+        let (otherwise, ()) = self.region(otherwise);
+        self.append(if_(condition, then, otherwise, self.location));
+        result
+    }
+
+    /// [`always::always`] sensitive to `events`, with `body` building its block.
+    pub fn always<R>(&self, events: &[(EventControl, Value<'c, '_>)], body: impl FnOnce(&IrBuilder<'c, '_>) -> R) -> R {
+        always::always(self.ctx, self.block(), events, |block| body(&self.within(block)), self.location)
+    }
+
+    /// [`always::always_comb`], with `body` building its block.
+    pub fn always_comb<R>(&self, body: impl FnOnce(&IrBuilder<'c, '_>) -> R) -> R {
+        always::always_comb(self.ctx, self.block(), |block| body(&self.within(block)), self.location)
+    }
+
+    /// Append `sv.initial`, with `body` building its block.
+    pub fn initial<R>(&self, body: impl FnOnce(&IrBuilder<'c, '_>) -> R) -> R {
+        let (region, result) = self.region(body);
+        self.append(sv::initial(self.ctx, region, self.location).into());
+        result
+    }
+}
@@ -0,0 +1,153 @@
+//! Incremental regeneration: skip building modules whose builder inputs haven't changed since the
+//! last run, and only rewrite the files whose contents changed, so large generated trees refresh
+//! quickly and their timestamps only move when they really changed.
+//!
+//! ```ignore
+//! let mut cache = ModuleCache::load(Path::new("out/.circt-sv-basic-cache.json"))?;
+//! for spec in &specs {
+//!     if !cache.reuse(&design, &spec.name, spec, here!(ctx))? {
+//!         build_module(&design, spec)?;
+//!         cache.record(&design, &spec.name, spec)?;
+//!     }
+//! }
+//! cache.save()?;
+//! export_changed(&design, Path::new("out"), &ExportOptions::default())?;
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use melior::ir::Location;
+use serde_json::{json, Value};
+
+use crate::export::{export_split_verilog, temp_dir, ExportOptions, FILE_LIST};
+use crate::ports::{declare_extern, find_module, module_ports, Port, PortDirection};
+use crate::{Design, Error};
+
+/* What the cache remembers about a module: the hash of the inputs it was built from, and its
+   ports, to declare it when it is reused. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    hash: u64,
+    ports: Vec<Port>,
+}
+
+/* The hash of `inputs`, and of this crate's version, since a different version may build the
+   same inputs differently. `DefaultHasher` isn't guaranteed stable across Rust releases; a
+   different hash only costs a rebuild. */
+fn hash(inputs: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    inputs.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The inputs each module was last built from, persisted in a JSON file between runs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleCache {
+    path: PathBuf,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl ModuleCache {
+    /// Load the cache saved at `path`, or start an empty one if there is none.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut cache = Self { path: path.to_path_buf(), entries: BTreeMap::new() };
+        let Ok(text) = fs::read_to_string(path) else {
+            return Ok(cache);
+        };
+        let invalid = || Error::Io(format!("{}: invalid cache", path.display()));
+        let modules: Value = serde_json::from_str(&text).map_err(|_| invalid())?;
+        for (module, entry) in modules.as_object().ok_or_else(invalid)? {
+            let hash = entry["hash"].as_u64().ok_or_else(invalid)?;
+            let mut ports = Vec::new();
+            for port in entry["ports"].as_array().ok_or_else(invalid)? {
+                ports.push(Port {
+                    name: port["name"].as_str().ok_or_else(invalid)?.to_string(),
+                    direction: port["direction"].as_str().and_then(PortDirection::from_keyword).ok_or_else(invalid)?,
+                    r#type: port["type"].as_str().ok_or_else(invalid)?.to_string(),
+                });
+            }
+            cache.entries.insert(module.clone(), Entry { hash, ports });
+        }
+        Ok(cache)
+    }
+
+    /// Whether `module` was last built from the same `inputs`. If it was, the generator should skip
+    /// building it: it is declared in `design` as an `hw.module.extern`, so instances of it still
+    /// verify, and its files from the last run are kept.
+    pub fn reuse<'c>(&self, design: &Design<'c>, module: &str, inputs: &impl Hash,
+                     location: Location<'c>) -> Result<bool, Error> {
+        match self.entries.get(module) {
+            Some(entry) if entry.hash == hash(inputs) => {
+                tracing::debug!(module, "reusing unchanged module");
+                declare_extern(design, module, &entry.ports, location)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Record that `module`, now built in `design`, was built from `inputs`.
+    pub fn record(&mut self, design: &Design, module: &str, inputs: &impl Hash) -> Result<(), Error> {
+        let built = find_module(design, module).ok_or_else(|| Error::UnknownModule(module.to_string()))?;
+        self.entries.insert(module.to_string(), Entry { hash: hash(inputs), ports: module_ports(&built) });
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let modules: serde_json::Map<String, Value> = self.entries.iter().map(|(module, entry)| {
+            let ports: Vec<Value> = entry.ports.iter().map(|port| json!({
+                "name": port.name,
+                "direction": port.direction.keyword(),
+                "type": port.r#type,
+            })).collect();
+            (module.clone(), json!({ "hash": entry.hash, "ports": ports }))
+        }).collect();
+        let text = serde_json::to_string_pretty(&Value::Object(modules)).expect("cache serializes");
+        fs::write(&self.path, text).map_err(|err| Error::Io(format!("{}: {err}", self.path.display())))
+    }
+}
+
+/// Write `contents` to `path` unless it already has them, returning whether it was written.
+pub fn write_if_changed(path: &Path, contents: &[u8]) -> Result<bool, Error> {
+    if fs::read(path).is_ok_and(|existing| existing == contents) {
+        return Ok(false);
+    }
+    fs::write(path, contents).map_err(|err| Error::Io(format!("{}: {err}", path.display())))?;
+    Ok(true)
+}
+
+/// Like [`export_split_verilog`], but only the files whose contents changed are written to `dir`,
+/// and the file list keeps the files of modules reused from the last run. Returns the files that
+/// were written.
+pub fn export_changed(design: &Design, dir: &Path, options: &ExportOptions) -> Result<Vec<PathBuf>, Error> {
+    let staging = temp_dir("incremental")?;
+    let result = export_split_verilog(design, &staging, options).and_then(|files| {
+        let mut changed = Vec::new();
+        let mut file_list: Vec<String> = fs::read_to_string(dir.join(FILE_LIST)).unwrap_or_default()
+            .lines().map(str::to_string).collect();
+        for file in files {
+            let name = file.strip_prefix(&staging).expect("exported files are in the staging directory");
+            let contents = fs::read(&file).map_err(|err| Error::Io(format!("{}: {err}", file.display())))?;
+            let target = dir.join(name);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|err| Error::Io(format!("{}: {err}", parent.display())))?;
+            }
+            if write_if_changed(&target, &contents)? {
+                changed.push(target);
+            }
+            let name = name.to_string_lossy().into_owned();
+            if file.extension().is_some_and(|extension| extension != "dd") && !file_list.contains(&name) {
+                file_list.push(name);
+            }
+        }
+        write_if_changed(&dir.join(FILE_LIST), format!("{}\n", file_list.join("\n")).as_bytes())?;
+        Ok(changed)
+    });
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
@@ -0,0 +1,112 @@
+//! A C API for driving the generator from non-Rust build systems and scripts, built into the
+//! `cdylib`. The declarations are in `include/svgen.h`.
+//!
+//! Functions that can fail return a status or a null pointer and leave a message for
+//! `svgen_last_error`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::mem::ManuallyDrop;
+use std::ptr;
+
+use melior::ir::Location;
+use melior::Context;
+
+use crate::export::export_verilog;
+use crate::spec::ModuleSpec;
+use crate::{load_dialects, Design, Error};
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_last_error(error: &Error) {
+    let message = CString::new(error.to_string().replace('\0', " ")).expect("no nul bytes");
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// A design and the context it lives in, `svgen_design` in C.
+pub struct SvgenDesign {
+    /* Dropped before the context it borrows. */
+    design: ManuallyDrop<Design<'static>>,
+    context: *mut Context,
+}
+
+impl Drop for SvgenDesign {
+    fn drop(&mut self) {
+        unsafe {
+            ManuallyDrop::drop(&mut self.design);
+            drop(Box::from_raw(self.context));
+        }
+    }
+}
+
+/// Create an empty design in a new context. Free it with `svgen_destroy_design`.
+#[unsafe(no_mangle)]
+pub extern "C" fn svgen_create_design() -> *mut SvgenDesign {
+    let context = Box::into_raw(Box::new(Context::new()));
+    let ctx: &'static Context = unsafe { &*context };
+    load_dialects(ctx);
+    let design = Design::new(ctx, Location::unknown(ctx));
+    Box::into_raw(Box::new(SvgenDesign { design: ManuallyDrop::new(design), context }))
+}
+
+/// # Safety
+/// `design` must come from `svgen_create_design` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn svgen_destroy_design(design: *mut SvgenDesign) {
+    if !design.is_null() {
+        drop(unsafe { Box::from_raw(design) });
+    }
+}
+
+/// Add a module described by the JSON `spec` (see [`crate::spec`]) to `design`. Returns 0 on
+/// success, -1 on error.
+///
+/// # Safety
+/// `design` must come from `svgen_create_design` and `spec` must be a nul terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn svgen_add_module_from_spec(design: *mut SvgenDesign, spec: *const c_char) -> c_int {
+    let design = unsafe { &*design };
+    let spec = unsafe { CStr::from_ptr(spec) }.to_string_lossy();
+    let location = Location::unknown(design.design.context());
+    match ModuleSpec::from_json(&spec).and_then(|spec| spec.build(&design.design, location)) {
+        Ok(_) => 0,
+        Err(error) => {
+            set_last_error(&error);
+            -1
+        }
+    }
+}
+
+/// Export `design` as SystemVerilog. Returns a string to free with `svgen_free_string`, or null on
+/// error.
+///
+/// # Safety
+/// `design` must come from `svgen_create_design`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn svgen_export_verilog(design: *mut SvgenDesign) -> *mut c_char {
+    let design = unsafe { &*design };
+    match export_verilog(&design.design) {
+        Ok(verilog) => CString::new(verilog).map_or(ptr::null_mut(), CString::into_raw),
+        Err(error) => {
+            set_last_error(&error);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `string` must come from `svgen_export_verilog` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn svgen_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// The message for the last error on this thread. Valid until the next failing call.
+#[unsafe(no_mangle)]
+pub extern "C" fn svgen_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
@@ -0,0 +1,21 @@
+//! `hw.bitcast` builder.
+
+use melior::dialect::ods::hw;
+use melior::ir::{Block, Location, Type, Value, ValueLike};
+use melior::Context;
+
+use crate::diagnostics::Append;
+use crate::{bit_width, Error};
+
+/// Append `hw.bitcast value : (T) -> target` to `block`, reinterpreting `value` between integer,
+/// struct, and array representations of the same bits. Both types must have the same, known,
+/// bit width.
+pub fn bitcast<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, '_>, target: Type<'c>,
+                       location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let from = bit_width(value.r#type()).ok_or(Error::UnknownWidth(value.r#type().to_string()))?;
+    let to = bit_width(target).ok_or(Error::UnknownWidth(target.to_string()))?;
+    if from != to {
+        return Err(Error::WidthMismatch { expected: to, found: from });
+    }
+    Ok(block.append(hw::bitcast(ctx, target, value, location).into()).result(0).unwrap().into())
+}
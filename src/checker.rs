@@ -0,0 +1,106 @@
+//! Generated assertions kept out of the module they check: a checker module observing the target's
+//! ports, attached with `sv.bind` so the target's RTL is unchanged.
+//!
+//! ```ignore
+//! checker::bind_checker(&design, "Fifo", "FifoChecker", |block, signals| {
+//!     let not_both = ...; // from signals["full"] and signals["empty"]
+//!     verif::assert(&ctx, block, not_both, Some("full_and_empty"), here!(ctx));
+//!     Ok(())
+//! }, here!(ctx))?;
+//! ```
+
+use std::collections::HashMap;
+
+use melior::ir::attribute::{ArrayAttribute, FlatSymbolRefAttribute, StringAttribute};
+use melior::ir::operation::{OperationBuilder, OperationLike};
+use melior::ir::{Attribute, Block, BlockLike, Identifier, Location, RegionLike, Type, Value};
+
+use crate::diagnostics::Append;
+use crate::export::OutputFile;
+use crate::ports::{self, find_module, module_ports, PortDirection};
+use crate::{operations, Design, Error};
+
+/// Add a checker module `checker` to `design` and bind an instance of it into the module `target`.
+/// The checker has an input for each input and output port of `target`, named the same, and
+/// `properties` builds its body from them, with the ports by name. Inout ports aren't observed.
+///
+/// The checker is emitted to `<checker>.sv` and the `sv.bind` to `<checker>_bind.sv`; the instance
+/// in `target` is marked `doNotPrint`, so only the bind statement instantiates it.
+pub fn bind_checker<'c, F>(design: &Design<'c>, target: &str, checker: &str, properties: F,
+                           location: Location<'c>) -> Result<(), Error>
+where
+    F: FnOnce(&Block<'c>, &HashMap<String, Value<'c, '_>>) -> Result<(), Error>,
+{
+    let ctx = design.context();
+    let target_op = find_module(design, target).ok_or_else(|| Error::UnknownModule(target.to_string()))?;
+    let target = design.module_name(target);
+    let body = target_op.region(0).ok().and_then(|region| region.first_block())
+        .ok_or_else(|| Error::UnknownModule(target.clone()))?;
+    let output = operations(&body).into_iter()
+        .find(|operation| operation.name().as_string_ref().as_str() == Ok("hw.output"))
+        .ok_or_else(|| Error::UnknownModule(target.clone()))?;
+
+    // The target's ports and the values they have in its body
+    let mut observed: Vec<(String, Type<'c>, Value)> = Vec::new();
+    let (mut argument, mut result) = (0, 0);
+    for port in module_ports(&target_op) {
+        let r#type = port.parse_type(ctx)?;
+        match port.direction {
+            PortDirection::Input => {
+                observed.push((port.name, r#type, body.argument(argument).unwrap().into()));
+                argument += 1;
+            }
+            PortDirection::InOut => argument += 1,
+            PortDirection::Output => {
+                observed.push((port.name, r#type, output.operand(result).unwrap()));
+                result += 1;
+            }
+        }
+    }
+
+    let checker_ports: Vec<(&str, PortDirection, Type)> = observed.iter()
+        .map(|(name, r#type, _)| (name.as_str(), PortDirection::Input, *r#type))
+        .collect();
+    ports::module(design, checker, &checker_ports, |block| {
+        let signals = observed.iter().enumerate()
+            .map(|(index, (name, _, _))| (name.clone(), block.argument(index).unwrap().into()))
+            .collect();
+        properties(block, &signals)?;
+        Ok(Vec::new())
+    }, location)?;
+    let checker = design.module_name(checker);
+    let checker_op = find_module(design, &checker).ok_or_else(|| Error::UnknownModule(checker.clone()))?;
+    OutputFile::new(&format!("{checker}.sv")).set(&checker_op);
+
+    let instance_name = design.instance_name(&checker);
+    let inner_sym = Attribute::parse(ctx, &format!("#hw<innerSym@{instance_name}>")).expect("valid inner symbol");
+    let names = |names: Vec<&str>| -> Attribute<'c> {
+        let names: Vec<Attribute> = names.into_iter().map(|name| StringAttribute::new(ctx, name).into()).collect();
+        ArrayAttribute::new(ctx, &names).into()
+    };
+    let operands: Vec<Value> = observed.iter().map(|(_, _, value)| *value).collect();
+    let instance = OperationBuilder::new("hw.instance", location)
+        .add_attributes(&[(Identifier::new(ctx, "instanceName"), StringAttribute::new(ctx, &instance_name).into()),
+                          (Identifier::new(ctx, "moduleName"), FlatSymbolRefAttribute::new(ctx, &checker).into()),
+                          (Identifier::new(ctx, "argNames"),
+                           names(observed.iter().map(|(name, _, _)| name.as_str()).collect())),
+                          (Identifier::new(ctx, "resultNames"), names(Vec::new())),
+                          (Identifier::new(ctx, "parameters"), ArrayAttribute::new(ctx, &[]).into()),
+                          (Identifier::new(ctx, "inner_sym"), inner_sym),
+                          (Identifier::new(ctx, "doNotPrint"), Attribute::unit(ctx))])
+        .add_operands(&operands)
+        .build()
+        .expect("valid operation");
+    body.insert_operation_before(output, instance);
+
+    let reference = format!("#hw.innerNameRef<@{target}::@{instance_name}>");
+    let bind = OperationBuilder::new("sv.bind", location)
+        .add_attributes(&[(Identifier::new(ctx, "instance"),
+                           Attribute::parse(ctx, &reference).expect("valid instance reference")),
+                          (Identifier::new(ctx, "output_file"),
+                           OutputFile::new(&format!("{checker}_bind.sv")).attribute(ctx))])
+        .build()
+        .expect("valid operation");
+    design.body().append(bind);
+    Ok(())
+}
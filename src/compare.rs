@@ -0,0 +1,55 @@
+//! `comb.icmp` comparisons.
+
+use melior::ir::attribute::IntegerAttribute;
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, Identifier, Location, Value};
+use melior::Context;
+
+use crate::diagnostics::Append;
+
+/// A `comb.icmp` predicate, `comb::ICmpPredicate` in CIRCT.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Predicate {
+    Eq,
+    Ne,
+    Slt,
+    Sle,
+    Sgt,
+    Sge,
+    Ult,
+    Ule,
+    Ugt,
+    Uge,
+}
+
+impl Predicate {
+    /// The `comb::ICmpPredicate` enum attribute for this predicate.
+    pub fn attribute(self, ctx: &Context) -> IntegerAttribute<'_> {
+        let value = match self {
+            Predicate::Eq => 0,
+            Predicate::Ne => 1,
+            Predicate::Slt => 2,
+            Predicate::Sle => 3,
+            Predicate::Sgt => 4,
+            Predicate::Sge => 5,
+            Predicate::Ult => 6,
+            Predicate::Ule => 7,
+            Predicate::Ugt => 8,
+            Predicate::Uge => 9,
+        };
+        IntegerAttribute::new(IntegerType::new(ctx, 64).into(), value)
+    }
+}
+
+/// Append `comb.icmp predicate lhs, rhs` to `block`, an `i1`. The operands must have the same type.
+pub fn icmp<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, predicate: Predicate, lhs: Value<'c, '_>,
+                    rhs: Value<'c, '_>, location: Location<'c>) -> Value<'c, 'a> {
+    let icmp = OperationBuilder::new("comb.icmp", location)
+        .add_attributes(&[(Identifier::new(ctx, "predicate"), predicate.attribute(ctx).into())])
+        .add_operands(&[lhs, rhs])
+        .add_results(&[IntegerType::new(ctx, 1).into()])
+        .build()
+        .expect("valid operation");
+    block.append(icmp).result(0).unwrap().into()
+}
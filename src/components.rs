@@ -0,0 +1,167 @@
+//! Standard components generated as modules, built from the register and `comb` builders.
+//!
+//! ```ignore
+//! components::lfsr(&design, "Lfsr16", 16, &[16, 14, 13, 11], LfsrKind::Galois, 1, here!(ctx))?;
+//! components::round_robin_arbiter(&design, "Arbiter4", 4, true, here!(ctx))?;
+//! ```
+
+use melior::dialect::ods::{comb, hw};
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, BlockLike, Location, Type, Value};
+use melior::Context;
+
+use crate::always::ResetKind;
+use crate::constant::{const_int, wide_integer_attribute, Signedness};
+use crate::diagnostics::Append;
+use crate::ports::{self, PortDirection};
+use crate::reduce::reduce_or;
+use crate::register::{register, EnableStyle, Reset};
+use crate::width::extract;
+use crate::{Design, Error};
+
+/* Append the `comb` operation `name` over `operands`, with a result of type `r#type`. */
+fn comb<'c, 'a>(block: &'a Block<'c>, name: &str, operands: &[Value<'c, '_>], r#type: Type<'c>,
+                location: Location<'c>) -> Value<'c, 'a> {
+    let operation = OperationBuilder::new(name, location)
+        .add_operands(operands)
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    block.append(operation).result(0).unwrap().into()
+}
+
+/* A `width` bit `hw.constant` with `bits` set, for masks wider than `const_int` takes. */
+fn mask<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, width: u32, bits: impl IntoIterator<Item = u32>,
+                location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let mut nibbles = vec![0u8; width.div_ceil(4) as usize];
+    for bit in bits {
+        nibbles[(bit / 4) as usize] |= 1 << (bit % 4);
+    }
+    let hex: String = nibbles.iter().rev().map(|nibble| format!("{nibble:x}")).collect();
+    let value = wide_integer_attribute(ctx, width, &format!("0x{hex}"))?;
+    let constant = hw::constant(ctx, IntegerType::new(ctx, width).into(), value, location);
+    Ok(block.append(constant.into()).result(0).unwrap().into())
+}
+
+/// The structure of an [`lfsr`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LfsrKind {
+    /// Shift left, shifting in the XOR of the tapped bits.
+    #[default]
+    Fibonacci,
+    /// Shift right, XORing the bit shifted out into the tapped bits: one gate between registers,
+    /// for high clock rates.
+    Galois,
+}
+
+/// Append a module `name` with `clk`, `reset` (synchronous, active high), and `enable` inputs and
+/// a `width` bit `state` output: a linear feedback shift register with feedback `taps`, the
+/// 1-based bit positions of a maximal length polynomial (e.g. `[16, 14, 13, 11]`), reset to
+/// `seed`, which must not be 0.
+pub fn lfsr<'c>(design: &Design<'c>, name: &str, width: u32, taps: &[u32], kind: LfsrKind, seed: u64,
+                location: Location<'c>) -> Result<(), Error> {
+    let ctx = design.context();
+    if width < 2 {
+        return Err(Error::InvalidParameter(format!("an LFSR needs at least 2 bits, not {width}")));
+    }
+    if let Some(tap) = taps.iter().find(|tap| **tap == 0 || **tap > width) {
+        return Err(Error::InvalidParameter(format!("tap {tap} is outside the LFSR's bits 1 to {width}")));
+    }
+    if seed == 0 {
+        return Err(Error::InvalidParameter("an LFSR seeded with 0 never leaves 0".to_string()));
+    }
+    let i1 = IntegerType::new(ctx, 1).into();
+    let state_type: Type = IntegerType::new(ctx, width).into();
+    let ports = [("clk", PortDirection::Input, i1), ("reset", PortDirection::Input, i1),
+                 ("enable", PortDirection::Input, i1), ("state", PortDirection::Output, state_type)];
+    ports::module(design, name, &ports, |body| {
+        let argument = |index| -> Value { body.argument(index).unwrap().into() };
+        let seed = const_int(ctx, body, width, i128::from(seed), Signedness::Unsigned, location)?;
+        let reset = Reset { kind: ResetKind::SyncActiveHigh, signal: argument(1), value: seed };
+        let enable = (argument(2), EnableStyle::Gated);
+        let state = register(design, body, "state", state_type, argument(0), Some(reset), Some(enable), |state| {
+            let taps = mask(ctx, body, width, taps.iter().map(|tap| tap - 1), location)?;
+            Ok(match kind {
+                LfsrKind::Fibonacci => {
+                    let tapped = comb(body, "comb.and", &[state, taps], state_type, location);
+                    let feedback = comb(body, "comb.parity", &[tapped], i1, location);
+                    let low = extract(ctx, body, state, 0, u64::from(width) - 1, location);
+                    body.append(comb::concat(ctx, &[low, feedback], location).into()).result(0).unwrap().into()
+                }
+                LfsrKind::Galois => {
+                    let out = extract(ctx, body, state, 0, 1, location);
+                    let zero = const_int(ctx, body, 1, 0, Signedness::Unsigned, location)?;
+                    let high = extract(ctx, body, state, 1, u64::from(width) - 1, location);
+                    let shifted = body.append(comb::concat(ctx, &[zero, high], location).into())
+                        .result(0).unwrap().into();
+                    let outs = comb(body, "comb.replicate", &[out], state_type, location);
+                    let feedback = comb(body, "comb.and", &[outs, taps], state_type, location);
+                    comb(body, "comb.xor", &[shifted, feedback], state_type, location)
+                }
+            })
+        }, location)?;
+        Ok(vec![state])
+    }, location)
+}
+
+/* `value & -value`: the lowest set bit of `value`. */
+fn lowest_set_bit<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, '_>, width: u32,
+                          location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let r#type = IntegerType::new(ctx, width).into();
+    let zero = const_int(ctx, block, width, 0, Signedness::Unsigned, location)?;
+    let negated = comb(block, "comb.sub", &[zero, value], r#type, location);
+    Ok(comb(block, "comb.and", &[value, negated], r#type, location))
+}
+
+/// Append a module `name` arbitrating between `requesters`: `clk`, `reset` (synchronous, active
+/// high), and `request` inputs, and a one-hot `grant` output. Grants rotate round-robin, starting
+/// after the last requester granted. With `locking`, a `lock` input keeps the grant with the
+/// current requester for as long as it is set and that requester keeps requesting, for
+/// multi-cycle transfers.
+pub fn round_robin_arbiter<'c>(design: &Design<'c>, name: &str, requesters: u32, locking: bool,
+                               location: Location<'c>) -> Result<(), Error> {
+    let ctx = design.context();
+    if requesters == 0 {
+        return Err(Error::InvalidParameter("an arbiter needs at least one requester".to_string()));
+    }
+    let i1 = IntegerType::new(ctx, 1).into();
+    let request_type: Type = IntegerType::new(ctx, requesters).into();
+    let mut ports = vec![("clk", PortDirection::Input, i1), ("reset", PortDirection::Input, i1),
+                         ("request", PortDirection::Input, request_type)];
+    if locking {
+        ports.push(("lock", PortDirection::Input, i1));
+    }
+    ports.push(("grant", PortDirection::Output, request_type));
+    ports::module(design, name, &ports, |body| {
+        let argument = |index| -> Value { body.argument(index).unwrap().into() };
+        let request = argument(2);
+        let zero = const_int(ctx, body, requesters, 0, Signedness::Unsigned, location)?;
+        let mut grant = None;
+        let reset = Reset { kind: ResetKind::SyncActiveHigh, signal: argument(1), value: zero };
+        register(design, body, "last", request_type, argument(0), Some(reset), None, |last| {
+            // Requesters above the last grant: `~((last << 1) - 1)`, none after reset.
+            let one = const_int(ctx, body, requesters, 1, Signedness::Unsigned, location)?;
+            let ones = mask(ctx, body, requesters, 0..requesters, location)?;
+            let shifted = comb(body, "comb.shl", &[last, one], request_type, location);
+            let below = comb(body, "comb.sub", &[shifted, one], request_type, location);
+            let above = comb(body, "comb.xor", &[below, ones], request_type, location);
+            let masked = comb(body, "comb.and", &[request, above], request_type, location);
+            let masked_grant = lowest_set_bit(ctx, body, masked, requesters, location)?;
+            let wrapped_grant = lowest_set_bit(ctx, body, request, requesters, location)?;
+            let any_masked = reduce_or(ctx, body, masked, location)?;
+            let mut next = comb(body, "comb.mux", &[any_masked, masked_grant, wrapped_grant], request_type, location);
+            if locking {
+                let still_requesting = comb(body, "comb.and", &[request, last], request_type, location);
+                let still_requesting = reduce_or(ctx, body, still_requesting, location)?;
+                let locked = comb(body, "comb.and", &[argument(3), still_requesting], i1, location);
+                next = comb(body, "comb.mux", &[locked, last, next], request_type, location);
+            }
+            grant = Some(next);
+            // Remember the last requester granted through cycles with no requests.
+            let granted = reduce_or(ctx, body, next, location)?;
+            Ok(comb(body, "comb.mux", &[granted, next, last], request_type, location))
+        }, location)?;
+        Ok(vec![grant.expect("the register's next value is built")])
+    }, location)
+}
@@ -0,0 +1,98 @@
+//! Checked `hw.constant` builders, including values `IntegerAttribute::new`'s `i64` can't hold,
+//! and the four-state `sv.constantX`/`sv.constantZ` constants.
+
+use melior::dialect::ods::{hw, sv};
+use melior::ir::attribute::IntegerAttribute;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Attribute, Block, Location, Type, Value};
+use melior::Context;
+
+use crate::diagnostics::Append;
+use crate::Error;
+
+/* Canonical MLIR integer literal for `literal`: an optional `-` followed by decimal digits, or a
+   `0x` prefixed hex value. `_` separators are dropped. */
+fn canonical_literal(literal: &str) -> Option<String> {
+    let literal: String = literal.chars().filter(|c| *c != '_').collect();
+    let (sign, digits) = match literal.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", literal.as_str()),
+    };
+    if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        if sign.is_empty() && !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Some(format!("0x{hex}"));
+        }
+        return None;
+    }
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        return Some(format!("{sign}{digits}"));
+    }
+    None
+}
+
+/// An integer attribute of type `i<width>` holding `literal`, a decimal (optionally negative)
+/// or `0x` hex string of any width, e.g. `"0x0123_4567_89ab_cdef_0123_4567_89ab_cdef"`.
+pub fn wide_integer_attribute<'c>(ctx: &'c Context, width: u32, literal: &str) -> Result<Attribute<'c>, Error> {
+    let invalid = || Error::InvalidConstant { literal: literal.to_string(), width };
+    let canonical = canonical_literal(literal).ok_or_else(invalid)?;
+    // MLIR's parser builds an APInt of the full width, and rejects values that don't fit.
+    Attribute::parse(ctx, &format!("{canonical} : i{width}")).ok_or_else(invalid)
+}
+
+/// Append `hw.constant <literal> : i<width>` to `block`, see [`wide_integer_attribute`].
+pub fn wide_constant<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, width: u32, literal: &str,
+                             location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let value = wide_integer_attribute(ctx, width, literal)?;
+    let constant = hw::constant(ctx, IntegerType::new(ctx, width).into(), value, location);
+    Ok(block.append(constant.into()).result(0).unwrap().into())
+}
+
+/// How a constant's value is meant to be interpreted, which decides the range it must fit in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signedness {
+    /// `-2^(width-1) ..= 2^(width-1) - 1`
+    Signed,
+    /// `0 ..= 2^width - 1`
+    Unsigned,
+}
+
+/* Does `value` fit in `width` bits, read with `signedness`? */
+fn fits(value: i128, width: u32, signedness: Signedness) -> bool {
+    match signedness {
+        Signedness::Unsigned => value >= 0 && (width >= 127 || value < 1i128 << width),
+        Signedness::Signed => width >= 128 || {
+            let half = 1i128 << (width - 1);
+            (-half..half).contains(&value)
+        },
+    }
+}
+
+/// Append `hw.constant value : i<width>` to `block`, or return an error if `value` doesn't fit
+/// in `width` bits with the given signedness instead of silently truncating it.
+pub fn const_int<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, width: u32, value: i128, signedness: Signedness,
+                         location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    if width == 0 || !fits(value, width, signedness) {
+        return Err(Error::ConstantOutOfRange { value, width, signed: signedness == Signedness::Signed });
+    }
+    let value = if width <= 64 {
+        // In range, so the low 64 bits are the constant's bit pattern.
+        IntegerAttribute::new(IntegerType::new(ctx, width).into(), value as i64).into()
+    } else {
+        wide_integer_attribute(ctx, width, &value.to_string())?
+    };
+    let constant = hw::constant(ctx, IntegerType::new(ctx, width).into(), value, location);
+    Ok(block.append(constant.into()).result(0).unwrap().into())
+}
+
+/// Append `sv.constantX : type` to `block`, an all `x` value for default assignments and
+/// don't care case arms.
+pub fn constant_x<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, r#type: Type<'c>,
+                          location: Location<'c>) -> Value<'c, 'a> {
+    block.append(sv::constant_x(ctx, r#type, location).into()).result(0).unwrap().into()
+}
+
+/// Append `sv.constantZ : type` to `block`, an all `z` value for tristate drivers.
+pub fn constant_z<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, r#type: Type<'c>,
+                          location: Location<'c>) -> Value<'c, 'a> {
+    block.append(sv::constant_z(ctx, r#type, location).into()).result(0).unwrap().into()
+}
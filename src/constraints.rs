@@ -0,0 +1,62 @@
+//! Timing constraint stubs for the clocks of a generated design, in the subset of Tcl that SDC and
+//! Vivado's XDC share, so the clock definitions are generated alongside the RTL they constrain.
+//!
+//! ```ignore
+//! let clocks = [Clock::new("core", "clk", 4.0), Clock::new("bus", "pclk", 10.0)];
+//! std::fs::write("top.sdc", constraints::sdc(&design, "top", &clocks, &[&["core"], &["bus"]])?)?;
+//! ```
+
+use std::fmt::Write;
+
+use melior::ir::Type;
+
+use crate::ports::{find_module, module_ports, PortDirection};
+use crate::{bit_width, Design, Error};
+
+/// A clock entering the design through a top level port.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clock {
+    /// The clock's name in the constraints.
+    pub name: String,
+    /// The top level input the clock drives.
+    pub port: String,
+    /// The period in nanoseconds.
+    pub period: f64,
+}
+
+impl Clock {
+    pub fn new(name: &str, port: &str, period: f64) -> Self {
+        Self { name: name.to_string(), port: port.to_string(), period }
+    }
+}
+
+/// A `create_clock` for each of `clocks` with a 50% duty cycle, and a `set_clock_groups
+/// -asynchronous` declaring each of `asynchronous` unrelated to the others, for clocks whose
+/// crossings are synchronized in the RTL. An empty list of groups leaves all clocks related. Each
+/// clock's port must be an input of the module `top` in `design`, one bit wide or a `!seq.clock`.
+pub fn sdc(design: &Design, top: &str, clocks: &[Clock], asynchronous: &[&[&str]]) -> Result<String, Error> {
+    let module = find_module(design, top).ok_or_else(|| Error::UnknownModule(top.to_string()))?;
+    let ports = module_ports(&module);
+    let mut source = String::from("# Generated by circt-sv-basic; do not edit.\n");
+    for clock in clocks {
+        let port = ports.iter().find(|port| port.name == clock.port && port.direction == PortDirection::Input)
+            .ok_or_else(|| Error::UnknownPort(format!("{top}::{}", clock.port)))?;
+        if port.r#type != "!seq.clock" {
+            let width = Type::parse(design.context(), &port.r#type).and_then(bit_width)
+                .ok_or_else(|| Error::UnknownWidth(port.r#type.clone()))?;
+            if width != 1 {
+                return Err(Error::WidthMismatch { expected: 1, found: width });
+            }
+        }
+        let _ = writeln!(source, "create_clock -name {} -period {:.3} -waveform {{0 {:.3}}} [get_ports {{{}}}]",
+                         clock.name, clock.period, clock.period / 2.0, clock.port);
+    }
+    if asynchronous.len() > 1 {
+        let _ = write!(source, "set_clock_groups -asynchronous");
+        for group in asynchronous {
+            let _ = write!(source, " -group [get_clocks {{{}}}]", group.join(" "));
+        }
+        source.push('\n');
+    }
+    Ok(source)
+}
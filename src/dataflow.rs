@@ -0,0 +1,39 @@
+//! Lowering dataflow IR from `handshake` or `dc` front ends to `hw` and `sv`, so it can be
+//! exported like any other design.
+//!
+//! ```ignore
+//! let design = dataflow::import_handshake(&ctx, &fs::read_to_string("kernel.mlir")?)?;
+//! export_split_verilog(&design, &out, &ExportOptions::default())?;
+//! ```
+
+use melior::Context;
+
+use crate::pipeline::PassPipeline;
+use crate::{Design, Error};
+
+/// Lower the `handshake.func`s in `design` to `hw.module`s with valid/ready handshaking.
+#[tracing::instrument(skip_all)]
+pub fn lower_handshake(design: &Design) -> Result<(), Error> {
+    PassPipeline::new().lower_handshake().lower_dc().run(design)
+}
+
+/// Lower the `dc` operations in `design`'s modules to valid/ready handshaking.
+#[tracing::instrument(skip_all)]
+pub fn lower_dc(design: &Design) -> Result<(), Error> {
+    PassPipeline::new().lower_dc().run(design)
+}
+
+/// Parse `source`, a module of `handshake.func`s, and lower it to `hw` and `sv`.
+pub fn import_handshake<'c>(ctx: &'c Context, source: &str) -> Result<Design<'c>, Error> {
+    let design = Design::parse(ctx, source)?;
+    lower_handshake(&design)?;
+    Ok(design)
+}
+
+/// Parse `source`, a module of `hw.module`s built from `dc` operations, and lower it to `hw` and
+/// `sv`.
+pub fn import_dc<'c>(ctx: &'c Context, source: &str) -> Result<Design<'c>, Error> {
+    let design = Design::parse(ctx, source)?;
+    lower_dc(&design)?;
+    Ok(design)
+}
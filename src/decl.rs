@@ -0,0 +1,63 @@
+//! `sv.reg`, `sv.logic`, and `sv.wire` declarations, and `sv.alias` between nets.
+
+use melior::dialect::ods::sv;
+use melior::ir::attribute::StringAttribute;
+use melior::ir::{Block, Location, Type, TypeLike, Value, ValueLike};
+use melior::Context;
+
+use crate::diagnostics::Append;
+use crate::lowering::LanguageStandard;
+use crate::{Design, Error};
+
+/// `!hw.inout<element>`, the type of declared variables and wires.
+pub fn inout_type<'c>(element: Type<'c>) -> Type<'c> {
+    unsafe { Type::from_raw(mlir_sys::hwInOutTypeGet(element.to_raw())) }
+}
+
+/// Append `sv.reg` named `name` holding an `element` to `block`, returning the inout value.
+pub fn reg<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, name: &str, element: Type<'c>,
+                   location: Location<'c>) -> Value<'c, 'a> {
+    let reg = sv::reg(ctx, inout_type(element), StringAttribute::new(ctx, name), location);
+    block.append(reg.into()).result(0).unwrap().into()
+}
+
+/// Append `sv.logic` named `name` holding an `element` to `block`, returning the inout value.
+pub fn logic<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, name: &str, element: Type<'c>,
+                     location: Location<'c>) -> Value<'c, 'a> {
+    let logic = sv::logic(ctx, inout_type(element), StringAttribute::new(ctx, name), location);
+    block.append(logic.into()).result(0).unwrap().into()
+}
+
+/// Append `sv.wire` named `name` carrying an `element` to `block`, returning the inout value.
+pub fn wire<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, name: &str, element: Type<'c>,
+                    location: Location<'c>) -> Value<'c, 'a> {
+    let wire = sv::wire(ctx, inout_type(element), StringAttribute::new(ctx, name), location);
+    block.append(wire.into()).result(0).unwrap().into()
+}
+
+/// A procedurally assigned variable in the design's language standard: `logic` for
+/// SystemVerilog, `reg` for Verilog-2001.
+pub fn variable<'c, 'a>(design: &Design<'c>, block: &'a Block<'c>, name: &str, element: Type<'c>,
+                        location: Location<'c>) -> Value<'c, 'a> {
+    match design.language_standard() {
+        LanguageStandard::SystemVerilog => logic(design.context(), block, name, element, location),
+        LanguageStandard::Verilog2001 => reg(design.context(), block, name, element, location),
+    }
+}
+
+/// Append `sv.alias` to `block`, making the inout `nets` (at least two, of the same type) one net,
+/// as in a pad ring where a pin is wired straight through to a pin of a neighbouring cell.
+pub fn alias<'c>(ctx: &'c Context, block: &Block<'c>, nets: &[Value<'c, '_>],
+                 location: Location<'c>) -> Result<(), Error> {
+    if nets.len() < 2 {
+        return Err(Error::TooFewOperands { operation: "sv.alias", minimum: 2, found: nets.len() });
+    }
+    let is_inout = |net: &Value| unsafe { mlir_sys::hwTypeIsAInOut(net.r#type().to_raw()) };
+    let first = nets[0];
+    if let Some(net) = nets.iter().find(|net| !is_inout(*net) || net.r#type() != first.r#type()) {
+        let expected = if is_inout(&first) { format!("`{}`", first.r#type()) } else { "an inout net".to_string() };
+        return Err(Error::TypeMismatch { expected, found: net.r#type().to_string() });
+    }
+    block.append(sv::alias(ctx, nets, location).into());
+    Ok(())
+}
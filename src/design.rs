@@ -0,0 +1,576 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::{c_char, c_void};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::slice;
+
+use melior::Context;
+use melior::dialect::ods::sv;
+use melior::ir::attribute::{ArrayAttribute, StringAttribute};
+use melior::ir::block::BlockRef;
+use melior::ir::operation::{OperationLike, OperationPrintingFlags};
+use melior::ir::{Attribute, AttributeLike, Location, Module, Operation};
+use melior::StringRef;
+use mlir_sys::MlirStringRef;
+
+use crate::diagnostics::{self, Append};
+use crate::legalize::{Legalizer, NamingPolicy, Rename};
+use crate::{operations, symbol_name, Error};
+use crate::lowering::{EmissionStyle, LanguageStandard, LoweringOptions, LOWERING_OPTIONS_ATTRIBUTE};
+use crate::pipeline::{PassBackend, PassInstrumentation};
+use crate::verif::AssertionGuard;
+
+/// How [`Design::order_top_level`] arranges the top level block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TopLevelOrder {
+    /// The order operations were appended in. Builders only append, so this is already
+    /// deterministic for a deterministic generator.
+    #[default]
+    Insertion,
+    /// Macro declarations, then type scopes, then everything else, then modules. Macro
+    /// declarations, type scopes, and modules are sorted by symbol name; the other operations
+    /// (the preamble's `sv.ifdef`s, `sv.verbatim`s, ...) keep their relative order since it is
+    /// significant. Output doesn't depend on the order generators ran in, so regenerated files
+    /// diff cleanly.
+    Sorted,
+}
+
+/// How [`Design::print`] prints the IR, for debugging builders: the generic form shows
+/// operations that print wrong or fail to verify in their custom form as they really are, and
+/// locations show where each one was built.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrintOptions {
+    /// Print operations in the generic `"dialect.op"(...) ({...}) : (...) -> ...` form.
+    pub generic: bool,
+    /// Print each operation's location.
+    pub locations: bool,
+    /// Print locations inline, at the operations, rather than as `#loc` aliases at the end.
+    pub pretty_locations: bool,
+    /// Print elements attributes with more elements than this as `dense_resource` placeholders.
+    pub elide_elements_above: Option<usize>,
+}
+
+impl PrintOptions {
+    pub fn flags(&self) -> OperationPrintingFlags {
+        let mut flags = OperationPrintingFlags::new();
+        if self.generic {
+            flags = flags.print_generic_op_form();
+        }
+        if self.locations || self.pretty_locations {
+            flags = flags.enable_debug_info(true, self.pretty_locations);
+        }
+        if let Some(limit) = self.elide_elements_above {
+            flags = flags.elide_large_elements_attributes(limit);
+        }
+        flags
+    }
+}
+
+/* Sort group of a top level operation, and whether its members are sorted by name. */
+fn top_level_group(name: &str) -> (u8, bool) {
+    match name {
+        "sv.macro.decl" => (0, true),
+        "hw.type_scope" => (1, true),
+        "hw.module" | "hw.module.extern" | "hw.module.generated" | "sv.interface" => (3, true),
+        _ => (2, false),
+    }
+}
+
+/// A top level `builtin.module` under construction.
+///
+/// Besides the module itself this tracks which macros have been declared, so generators can
+/// declare the macros they reference without emitting duplicate `sv.macro.decl` ops, the
+/// language standard builders should target, the options for ExportVerilog, the naming policy
+/// names are generated with, the guards assertions are exported behind, and how passes are run
+/// and measured.
+pub struct Design<'c> {
+    context: &'c Context,
+    module: Module<'c>,
+    macros: RefCell<BTreeSet<String>>,
+    /* Function-like macro -> its argument count. */
+    macro_args: RefCell<HashMap<String, usize>>,
+    standard: Cell<LanguageStandard>,
+    lowering: RefCell<LoweringOptions>,
+    style: RefCell<EmissionStyle>,
+    names: RefCell<Legalizer>,
+    naming: RefCell<NamingPolicy>,
+    assertion_guard: Cell<AssertionGuard>,
+    multithreading: Cell<bool>,
+    instrumentation: RefCell<PassInstrumentation>,
+    reproducer: RefCell<Option<PathBuf>>,
+    backend: Cell<PassBackend>,
+    /* Module name given to a builder -> the name the policy and legalization made of it. */
+    modules: RefCell<HashMap<String, String>>,
+    /* Module -> instances of it named by `instance_name`. */
+    instances: RefCell<HashMap<String, usize>>,
+}
+
+impl<'c> Design<'c> {
+    pub fn new(context: &'c Context, location: Location<'c>) -> Self {
+        Self {
+            context,
+            module: Module::new(location),
+            macros: RefCell::new(BTreeSet::new()),
+            macro_args: RefCell::new(HashMap::new()),
+            standard: Cell::new(LanguageStandard::default()),
+            lowering: RefCell::new(LoweringOptions::default()),
+            style: RefCell::new(EmissionStyle::default()),
+            names: RefCell::new(Legalizer::new()),
+            naming: RefCell::new(NamingPolicy::default()),
+            assertion_guard: Cell::new(AssertionGuard::default()),
+            multithreading: Cell::new(true),
+            instrumentation: RefCell::new(PassInstrumentation::default()),
+            reproducer: RefCell::new(None),
+            backend: Cell::new(PassBackend::default()),
+            modules: RefCell::new(HashMap::new()),
+            instances: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// A design holding the `builtin.module` parsed from `source`, IR produced by another front
+    /// end, for example.
+    pub fn parse(context: &'c Context, source: &str) -> Result<Self, Error> {
+        Self::parsed(context, || Module::parse(context, source))
+    }
+
+    /// Like [`Design::parse`], for `source` that is either IR text or MLIR bytecode, told apart the
+    /// way `circt-opt` does, by the bytecode's magic number.
+    pub fn parse_bytes(context: &'c Context, source: &[u8]) -> Result<Self, Error> {
+        Self::parsed(context, || unsafe {
+            let source = MlirStringRef { data: source.as_ptr() as *const c_char, length: source.len() };
+            Module::from_option_raw(mlir_sys::mlirModuleCreateParse(context.to_raw(), source))
+        })
+    }
+
+    /// Read a design from the `.mlir` or `.mlirbc` file `path`.
+    pub fn read(context: &'c Context, path: &Path) -> Result<Self, Error> {
+        let source = fs::read(path).map_err(|err| Error::Io(format!("{}: {err}", path.display())))?;
+        Self::parse_bytes(context, &source)
+    }
+
+    /* A design holding the module `parse` returns, or the diagnostics it reported. */
+    fn parsed(context: &'c Context, parse: impl FnOnce() -> Option<Module<'c>>) -> Result<Self, Error> {
+        let (module, reported) = diagnostics::capture(context, parse);
+        let module = module.ok_or_else(|| Error::ParseFailed(reported.into_iter()
+            .map(|(message, location)| diagnostics::Diagnostic { message, location, snippet: None })
+            .collect()))?;
+        let location = module.as_operation().location();
+        let design = Self { module, ..Self::new(context, location) };
+        /* Macros the input declares, so guards on them and function-like uses check as if built here. */
+        for operation in operations(&design.body()) {
+            if operation.name().as_string_ref().as_str() != Ok("sv.macro.decl") {
+                continue;
+            }
+            let Some(name) = symbol_name(&operation) else { continue };
+            if let Some(args) = operation.attribute("args").ok().and_then(|args| ArrayAttribute::try_from(args).ok()) {
+                design.macro_args.borrow_mut().insert(name.clone(), args.len());
+            }
+            design.names.borrow_mut().reserve(&name);
+            design.macros.borrow_mut().insert(name);
+        }
+        Ok(design)
+    }
+
+    pub fn context(&self) -> &'c Context {
+        self.context
+    }
+
+    pub fn module(&self) -> &Module<'c> {
+        &self.module
+    }
+
+    /// The top level block: macro declarations, modules, etc. are appended here.
+    pub fn body(&self) -> BlockRef<'c, '_> {
+        self.module.body()
+    }
+
+    pub fn language_standard(&self) -> LanguageStandard {
+        self.standard.get()
+    }
+
+    /// Target `standard`: builders pick ops it supports, and the lowering options it needs are
+    /// turned on.
+    pub fn set_language_standard(&self, standard: LanguageStandard) {
+        self.standard.set(standard);
+        let required = LoweringOptions::for_standard(standard);
+        let mut options = self.lowering_options();
+        options.no_always_comb |= required.no_always_comb;
+        options.disallow_packed_arrays |= required.disallow_packed_arrays;
+        options.disallow_packed_struct_assignments |= required.disallow_packed_struct_assignments;
+        options.disallow_local_variables |= required.disallow_local_variables;
+        self.set_lowering_options(options);
+    }
+
+    pub fn lowering_options(&self) -> LoweringOptions {
+        self.lowering.borrow().clone()
+    }
+
+    /// Replace the lowering options and update the `circt.loweringOptions` attribute to match.
+    pub fn set_lowering_options(&self, options: LoweringOptions) {
+        self.set_attribute(LOWERING_OPTIONS_ATTRIBUTE, options.attribute(self.context));
+        *self.lowering.borrow_mut() = options;
+    }
+
+    pub fn emission_style(&self) -> EmissionStyle {
+        self.style.borrow().clone()
+    }
+
+    /// Use `style` for emitted Verilog, updating the lowering options it affects.
+    pub fn set_emission_style(&self, style: EmissionStyle) {
+        let mut options = self.lowering_options();
+        style.configure(&mut options);
+        self.set_lowering_options(options);
+        *self.style.borrow_mut() = style;
+    }
+
+    /// The legal SystemVerilog identifier for a user provided module or macro name. Port names
+    /// live in their own namespace; use a [`Legalizer`] per module for those.
+    pub fn legal_name(&self, name: &str) -> String {
+        self.names.borrow_mut().legalize(name)
+    }
+
+    pub fn naming_policy(&self) -> NamingPolicy {
+        self.naming.borrow().clone()
+    }
+
+    /// Name modules, macros, and instances built from now on according to `policy`.
+    pub fn set_naming_policy(&self, policy: NamingPolicy) {
+        *self.naming.borrow_mut() = policy;
+    }
+
+    /// The name the module builders give a module named `name`: the naming policy's prefix and
+    /// suffix, legalized. The same for the same `name`, and unchanged for a name it returned, so
+    /// instances can refer to modules by the names they were built with.
+    pub fn module_name(&self, name: &str) -> String {
+        let mut modules = self.modules.borrow_mut();
+        if let Some(module) = modules.get(name) {
+            return module.clone();
+        }
+        if modules.values().any(|module| module == name) {
+            return name.to_string();
+        }
+        let module = self.legal_name(&self.naming.borrow().module_name(name));
+        modules.insert(name.to_string(), module.clone());
+        module
+    }
+
+    /// The name macro `name` is declared and referred to by under the naming policy.
+    pub fn macro_name(&self, name: &str) -> String {
+        self.naming.borrow().macro_name(name)
+    }
+
+    /// A new instance name for an instance of `module`, from the naming policy's template.
+    pub fn instance_name(&self, module: &str) -> String {
+        let mut instances = self.instances.borrow_mut();
+        let count = instances.entry(module.to_string()).or_default();
+        *count += 1;
+        self.naming.borrow().instance_name(module, *count - 1)
+    }
+
+    /// Every module and macro name [`Design::legal_name`] had to change.
+    pub fn renames(&self) -> Vec<Rename> {
+        self.names.borrow().renames().to_vec()
+    }
+
+    /// Append `sv.macro.decl @name` to the top level block, unless it was already declared.
+    pub fn declare_macro(&self, name: &str, location: Location<'c>) {
+        self.declare_macro_with_args(name, &[], location)
+    }
+
+    /// Append `sv.macro.decl @name[args]`, a function-like macro such as `` `PRINTF_COND_(clock,
+    /// cond) ``, unless it was already declared. It can be tested by name in `` `ifdef `` guards
+    /// like any other macro.
+    pub fn declare_macro_with_args(&self, name: &str, args: &[&str], location: Location<'c>) {
+        let name = &self.macro_name(name);
+        if !self.macros.borrow_mut().insert(name.to_string()) {
+            return;
+        }
+        let ctx = self.context;
+        let macro_decl: Operation = sv::macro_decl(ctx, StringAttribute::new(ctx, name), location).into();
+        if !args.is_empty() {
+            let args: Vec<Attribute> = args.iter().map(|arg| StringAttribute::new(ctx, arg).into()).collect();
+            unsafe {
+                mlir_sys::mlirOperationSetAttributeByName(macro_decl.to_raw(), StringRef::new("args").to_raw(),
+                                                          ArrayAttribute::new(ctx, &args).to_raw());
+            }
+            self.macro_args.borrow_mut().insert(name.to_string(), args.len());
+        }
+        self.body().append(macro_decl);
+    }
+
+    /// The number of arguments macro `name` was declared with, 0 for plain macros.
+    pub fn macro_arity(&self, name: &str) -> usize {
+        self.macro_args.borrow().get(&self.macro_name(name)).copied().unwrap_or_default()
+    }
+
+    pub fn is_macro_declared(&self, name: &str) -> bool {
+        self.macros.borrow().contains(&self.macro_name(name))
+    }
+
+    /// Move the top level ops of `others`, designs built separately in the same context, into this
+    /// one, so they can be emitted together. Macro declarations and ops identical to one already
+    /// here are dropped; a module or other symbol whose name is taken is renamed with a suffix,
+    /// along with the references to it in its design. Returns the renames.
+    pub fn merge(&self, others: Vec<Design<'c>>) -> Vec<Rename> {
+        let body = self.body();
+        let mut renames = Vec::new();
+        for other in others {
+            let mut taken: HashMap<String, String> = operations(&body).into_iter()
+                .filter_map(|operation| symbol_name(&operation).map(|name| (name, operation.to_string())))
+                .collect();
+            let mut unnamed: BTreeSet<String> = operations(&body).into_iter()
+                .filter(|operation| symbol_name(operation).is_none())
+                .map(|operation| operation.to_string())
+                .collect();
+            let mut other_names: BTreeSet<String> = operations(&other.body()).into_iter()
+                .filter_map(|operation| symbol_name(&operation))
+                .collect();
+            let is_macro = |operation: &Operation| operation.name().as_string_ref().as_str() == Ok("sv.macro.decl");
+
+            // Every rename first, so ops moved later don't keep references to the old names. A
+            // symbol is dropped as a duplicate if it prints the same as the one here once the
+            // symbols it refers to are renamed, so renaming one can make another differ.
+            let mut duplicates: BTreeSet<String> = BTreeSet::new();
+            let mut renamed: HashMap<String, String> = HashMap::new();
+            loop {
+                let mut changed = false;
+                for operation in operations(&other.body()) {
+                    let Some(name) = symbol_name(&operation) else { continue };
+                    let Some(existing) = taken.get(&name).filter(|_| !is_macro(&operation)) else { continue };
+                    if *existing == operation.to_string() {
+                        changed |= duplicates.insert(name);
+                        continue;
+                    }
+                    duplicates.remove(&name);
+                    let mut suffix = 1;
+                    let mut new = format!("{name}_{suffix}");
+                    while taken.contains_key(&new) || other_names.contains(&new) || self.names.borrow().is_taken(&new) {
+                        suffix += 1;
+                        new = format!("{name}_{suffix}");
+                    }
+                    unsafe {
+                        mlir_sys::mlirSymbolTableReplaceAllSymbolUses(
+                            StringRef::new(&name).to_raw(), StringRef::new(&new).to_raw(),
+                            other.module.as_operation().to_raw());
+                        mlir_sys::mlirOperationSetAttributeByName(
+                            operation.to_raw(), StringRef::new("sym_name").to_raw(),
+                            StringAttribute::new(self.context, &new).to_raw());
+                    }
+                    other_names.insert(new.clone());
+                    renamed.insert(name, new);
+                    changed = true;
+                }
+                if !changed {
+                    break;
+                }
+            }
+
+            for operation in operations(&other.body()) {
+                let duplicate = match symbol_name(&operation) {
+                    Some(name) if is_macro(&operation) => !self.macros.borrow_mut().insert(name),
+                    Some(name) if duplicates.contains(&name) => true,
+                    Some(name) => {
+                        if matches!(operation.name().as_string_ref().as_str(), Ok("hw.module" | "hw.module.extern")) {
+                            self.modules.borrow_mut().insert(name.clone(), name.clone());
+                        }
+                        self.names.borrow_mut().reserve(&name);
+                        taken.insert(name, operation.to_string());
+                        false
+                    }
+                    None => !unnamed.insert(operation.to_string()),
+                };
+                unsafe {
+                    if duplicate {
+                        mlir_sys::mlirOperationDestroy(operation.to_raw());
+                    } else {
+                        mlir_sys::mlirOperationRemoveFromParent(operation.to_raw());
+                        mlir_sys::mlirBlockAppendOwnedOperation(body.to_raw(), operation.to_raw());
+                    }
+                }
+            }
+            self.macro_args.borrow_mut().extend(other.macro_args.take());
+            let mut renamed: Vec<_> = renamed.into_iter().collect();
+            renamed.sort();
+            renames.extend(renamed.into_iter().map(|(original, legalized)| Rename { original, legalized }));
+        }
+        renames
+    }
+
+    /// Set a discardable attribute, like `circt.loweringOptions`, on the top level module.
+    pub fn set_attribute(&self, name: &str, attribute: Attribute<'c>) {
+        unsafe {
+            mlir_sys::mlirOperationSetAttributeByName(self.module.as_operation().to_raw(),
+                                                      StringRef::new(name).to_raw(),
+                                                      attribute.to_raw());
+        }
+    }
+
+    pub fn assertion_guard(&self) -> AssertionGuard {
+        self.assertion_guard.get()
+    }
+
+    /// Put all assertions, assumptions, and cover points behind `guard` when the design is
+    /// exported to Verilog, see [`verif::guard_assertions`](crate::verif::guard_assertions).
+    pub fn set_assertion_guard(&self, guard: AssertionGuard) {
+        self.assertion_guard.set(guard);
+    }
+
+    /// Whether [`PassPipeline::run`](crate::pipeline::PassPipeline::run), and so export, runs
+    /// passes over modules on multiple threads, as it does by default.
+    pub fn multithreading(&self) -> bool {
+        self.multithreading.get()
+    }
+
+    /// Run passes on a single thread, for debugging a pass or getting its diagnostics in a
+    /// deterministic order, or on multiple threads again.
+    pub fn set_multithreading(&self, enabled: bool) {
+        self.multithreading.set(enabled);
+    }
+
+    pub fn pass_backend(&self) -> PassBackend {
+        self.backend.get()
+    }
+
+    /// Run passes, export's included, with `backend`.
+    pub fn set_pass_backend(&self, backend: PassBackend) {
+        self.backend.set(backend);
+    }
+
+    pub fn pass_instrumentation(&self) -> PassInstrumentation {
+        self.instrumentation.borrow().clone()
+    }
+
+    /// Turn on the pass instrumentation `instrumentation` asks for in the passes run on the design
+    /// from now on, including export's.
+    pub fn set_pass_instrumentation(&self, instrumentation: PassInstrumentation) {
+        self.instrumentation.replace(instrumentation);
+    }
+
+    pub fn crash_reproducer(&self) -> Option<PathBuf> {
+        self.reproducer.borrow().clone()
+    }
+
+    /// Write the design and pipeline to `file` before each pass pipeline runs on it, and keep it
+    /// if the passes fail or crash, for bug reports against CIRCT or this crate. `circt-opt
+    /// --run-reproducer file` reruns the failing pipeline.
+    pub fn set_crash_reproducer(&self, file: Option<PathBuf>) {
+        self.reproducer.replace(file);
+    }
+
+    /// Reorder the top level block according to `order`.
+    pub fn order_top_level(&self, order: TopLevelOrder) {
+        if order == TopLevelOrder::Insertion {
+            return;
+        }
+        let body = self.body();
+        let mut operations: Vec<_> = operations(&body).into_iter().map(|operation| {
+            let (group, by_name) = top_level_group(operation.name().as_string_ref().as_str().unwrap_or_default());
+            let name = if by_name { symbol_name(&operation).unwrap_or_default() } else { String::new() };
+            ((group, name), operation.to_raw())
+        }).collect();
+        // Stable, so unnamed operations keep their relative order.
+        operations.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (_, operation) in operations {
+            unsafe {
+                mlir_sys::mlirOperationRemoveFromParent(operation);
+                mlir_sys::mlirBlockAppendOwnedOperation(body.to_raw(), operation);
+            }
+        }
+    }
+
+    /* Replace the top level operations with `other`'s, the design as an external tool rewrote it. */
+    pub(crate) fn replace_body(&self, other: Design<'c>) {
+        let body = self.body();
+        for operation in operations(&body) {
+            unsafe { mlir_sys::mlirOperationDestroy(operation.to_raw()) };
+        }
+        for operation in operations(&other.body()) {
+            unsafe {
+                mlir_sys::mlirOperationRemoveFromParent(operation.to_raw());
+                mlir_sys::mlirBlockAppendOwnedOperation(body.to_raw(), operation.to_raw());
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn verify(&self) -> bool {
+        let valid = self.module.as_operation().verify();
+        if !valid {
+            tracing::warn!("design failed verification");
+        }
+        valid
+    }
+
+    /// Verify the design, returning the verifier's diagnostics, with the operations they refer to
+    /// and the locations they were built at, if it isn't valid. In verify each op mode, an invalid
+    /// operation appended since the last check is reported instead.
+    pub fn check(&self) -> Result<(), Error> {
+        if let Some(diagnostics) = diagnostics::take_invalid_op() {
+            return Err(Error::VerificationFailed(diagnostics));
+        }
+        let (valid, reported) = diagnostics::capture(self.context, || self.verify());
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::VerificationFailed(diagnostics::resolve(&self.module.as_operation(), reported)))
+        }
+    }
+
+    /// The design's IR, printed as `options` say.
+    pub fn print(&self, options: &PrintOptions) -> Result<String, Error> {
+        self.module.as_operation().to_string_with_flags(options.flags()).map_err(|err| Error::Io(err.to_string()))
+    }
+
+    /// The design as MLIR bytecode, the `.mlirbc` format CIRCT tools read and write much faster
+    /// than text, and in far less space for large designs.
+    pub fn bytecode(&self) -> Vec<u8> {
+        unsafe extern "C" fn append(data: MlirStringRef, bytes: *mut c_void) {
+            let bytes = unsafe { &mut *(bytes as *mut Vec<u8>) };
+            bytes.extend_from_slice(unsafe { slice::from_raw_parts(data.data as *const u8, data.length) });
+        }
+        let mut bytes = Vec::new();
+        unsafe {
+            mlir_sys::mlirOperationWriteBytecode(self.module.as_operation().to_raw(), Some(append),
+                                                 &mut bytes as *mut Vec<u8> as *mut c_void);
+        }
+        bytes
+    }
+
+    /// Write the design as MLIR bytecode to `path`, see [`Design::bytecode`].
+    pub fn write_bytecode(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, self.bytecode()).map_err(|err| Error::Io(format!("{}: {err}", path.display())))
+    }
+
+    /// Print the design, parse the text back into a fresh context, and check that it verifies and
+    /// prints the same, catching attributes and types that print in a form that doesn't parse
+    /// before a downstream tool reads the output.
+    pub fn check_roundtrip(&self) -> Result<(), Error> {
+        let text = self.module.as_operation().to_string();
+        let ctx = Context::new();
+        crate::load_dialects(&ctx);
+        let (parsed, reported) = diagnostics::capture(&ctx, || Module::parse(&ctx, &text));
+        let failed = |message: String, location: String| {
+            Error::RoundtripFailed(vec![diagnostics::Diagnostic { message, location, snippet: None }])
+        };
+        let Some(parsed) = parsed else {
+            return Err(Error::RoundtripFailed(reported.into_iter()
+                .map(|(message, location)| diagnostics::Diagnostic { message, location, snippet: None })
+                .collect()));
+        };
+        let (valid, reported) = diagnostics::capture(&ctx, || parsed.as_operation().verify());
+        if !valid {
+            return Err(Error::RoundtripFailed(diagnostics::resolve(&parsed.as_operation(), reported)));
+        }
+        let reprinted = parsed.as_operation().to_string();
+        match text.lines().zip(reprinted.lines()).position(|(original, reparsed)| original != reparsed) {
+            Some(line) => Err(failed(format!("prints differently after parsing: `{}` became `{}`",
+                                             text.lines().nth(line).unwrap_or_default().trim(),
+                                             reprinted.lines().nth(line).unwrap_or_default().trim()),
+                                     format!("line {}", line + 1))),
+            None if text.lines().count() != reprinted.lines().count() =>
+                Err(failed("prints a different number of lines after parsing".to_string(), "module".to_string())),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! MLIR diagnostics reported while verifying a [`Design`] or running passes over it, with the IR
+//! they refer to, so builder bugs can be found without a debugger.
+//!
+//! For builder bugs that are hard to place, [`set_verify_each_op`] turns on verifying operations
+//! as the builders in this crate append them, instead of only when the whole design is verified.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+use melior::ir::block::BlockLike;
+use melior::ir::operation::{OperationLike, OperationPrintingFlags, OperationRef};
+use melior::ir::{Block, Operation};
+use melior::Context;
+
+use crate::walk;
+
+thread_local! {
+    static VERIFY_EACH_OP: Cell<bool> = const { Cell::new(false) };
+    static INVALID_OP: RefCell<Option<Vec<Diagnostic>>> = const { RefCell::new(None) };
+}
+
+/// Verify every operation the builders in this crate append, on this thread, keeping the
+/// verifier's diagnostics for the first invalid one, which [`Design::check`](crate::Design::check)
+/// then returns. Operations appended to a block that isn't yet part of a module are verified when
+/// the operation containing the block is appended, since many operations are only valid within
+/// their parents. For debugging; this slows construction down.
+pub fn set_verify_each_op(enabled: bool) {
+    VERIFY_EACH_OP.with(|verify| verify.set(enabled));
+}
+
+pub fn verify_each_op() -> bool {
+    VERIFY_EACH_OP.with(Cell::get)
+}
+
+/* The diagnostics of the first invalid operation appended in verify each op mode since the last
+   call, if there was one. */
+pub(crate) fn take_invalid_op() -> Option<Vec<Diagnostic>> {
+    INVALID_OP.with(|invalid| invalid.borrow_mut().take())
+}
+
+/// An error MLIR reported, with the operation at its location if there is one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// Where the operation was built, as injected by `here!`.
+    pub location: String,
+    /// The operation in generic form, followed by its parents up to the module.
+    pub snippet: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)?;
+        if let Some(snippet) = &self.snippet {
+            for line in snippet.lines() {
+                write!(f, "\n    {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/* Run `f`, collecting the error diagnostics reported through `ctx` meanwhile as (message,
+   location) pairs instead of printing them. */
+pub(crate) fn capture<T>(ctx: &Context, f: impl FnOnce() -> T) -> (T, Vec<(String, String)>) {
+    let reported = Rc::new(RefCell::new(Vec::new()));
+    let handler = {
+        let reported = reported.clone();
+        ctx.attach_diagnostic_handler(move |diagnostic| {
+            reported.borrow_mut().push((diagnostic.to_string(), diagnostic.location().to_string()));
+            true
+        })
+    };
+    let result = f();
+    ctx.detach_diagnostic_handler(handler);
+    let reported = reported.borrow().clone();
+    (result, reported)
+}
+
+/* `operation` in generic form, then the name and location of each parent up to the module. */
+fn snippet(operation: &Operation) -> String {
+    let flags = OperationPrintingFlags::new().print_generic_op_form().enable_debug_info(true, false);
+    let mut snippet = operation.to_string_with_flags(flags).unwrap_or_else(|_| operation.to_string());
+    let mut parent = operation.parent_operation();
+    while let Some(operation) = parent {
+        let name = operation.name().as_string_ref().as_str().unwrap_or_default().to_string();
+        if name == "builtin.module" {
+            break;
+        }
+        let symbol = crate::symbol_name(&operation).map(|symbol| format!(" @{symbol}")).unwrap_or_default();
+        snippet.push_str(&format!("\nin {name}{symbol} at {}", operation.location()));
+        parent = operation.parent_operation();
+    }
+    snippet
+}
+
+/* Attach the IR at each reported location within `root`. */
+pub(crate) fn resolve(root: &Operation, reported: Vec<(String, String)>) -> Vec<Diagnostic> {
+    reported.into_iter().map(|(message, location)| {
+        let mut found = (root.location().to_string() == location).then(|| snippet(root));
+        walk(root, &mut |operation| {
+            if found.is_none() && operation.location().to_string() == location {
+                found = Some(snippet(operation));
+            }
+        });
+        Diagnostic { message, location, snippet: found }
+    }).collect()
+}
+
+/* Whether `block` is nested in a `builtin.module`, so the operations in it can be verified. */
+fn is_attached(block: &Block) -> bool {
+    let mut parent = block.parent_operation();
+    while let Some(operation) = parent {
+        if operation.name().as_string_ref().as_str() == Ok("builtin.module") {
+            return true;
+        }
+        parent = operation.block().and_then(|block| block.parent_operation());
+    }
+    false
+}
+
+/* `BlockLike::append_operation`, verifying the operation in verify each op mode. The builders in
+   this crate append through this. */
+pub(crate) trait Append<'c> {
+    fn append(&self, operation: Operation<'c>) -> OperationRef<'c, '_>;
+}
+
+impl<'c> Append<'c> for Block<'c> {
+    fn append(&self, operation: Operation<'c>) -> OperationRef<'c, '_> {
+        let appended = self.append_operation(operation);
+        if verify_each_op() && is_attached(self) {
+            let ctx = unsafe { appended.context().to_ref() };
+            let (valid, reported) = capture(ctx, || appended.verify());
+            if !valid {
+                let diagnostics = resolve(&appended, reported);
+                INVALID_OP.with(|invalid| {
+                    invalid.borrow_mut().get_or_insert(diagnostics);
+                });
+            }
+        }
+        appended
+    }
+}
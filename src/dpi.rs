@@ -0,0 +1,53 @@
+//! DPI-C imports, so generated testbench code can call into C models.
+//!
+//! ```ignore
+//! dpi::import(&design, "model_step", "model_step", &[("in", Input, i32), ("out", Output, i32)], here!(ctx));
+//! always::always(&ctx, &body, &[(Posedge, clk)], |always| {
+//!     let out = dpi::call(&ctx, always, "model_step", &[value], &[i32], here!(ctx));
+//! }, here!(ctx));
+//! ```
+
+use melior::ir::attribute::{FlatSymbolRefAttribute, StringAttribute, TypeAttribute};
+use melior::ir::operation::OperationBuilder;
+use melior::ir::{Block, Identifier, Location, Region, Type, Value};
+use melior::Context;
+
+use crate::diagnostics::Append;
+use crate::ports::{module_type, PortDirection};
+use crate::Design;
+
+/// Append a private `sv.func @name` declaration with `ports` to the top level of `design`, and
+/// `sv.func.dpi.import` of it from the C function `c_name`, emitted as
+/// `import "DPI-C" function void c_name(...)`, with output ports as `output` arguments.
+pub fn import<'c>(design: &Design<'c>, name: &str, c_name: &str, ports: &[(&str, PortDirection, Type<'c>)],
+                  location: Location<'c>) {
+    let ctx = design.context();
+    let function = OperationBuilder::new("sv.func", location)
+        .add_attributes(&[(Identifier::new(ctx, "sym_name"), StringAttribute::new(ctx, name).into()),
+                          (Identifier::new(ctx, "sym_visibility"), StringAttribute::new(ctx, "private").into()),
+                          (Identifier::new(ctx, "module_type"), TypeAttribute::new(module_type(ctx, ports)).into())])
+        .add_regions([Region::new()])
+        .build()
+        .expect("valid operation");
+    design.body().append(function);
+    let import = OperationBuilder::new("sv.func.dpi.import", location)
+        .add_attributes(&[(Identifier::new(ctx, "linkage_name"), StringAttribute::new(ctx, c_name).into()),
+                          (Identifier::new(ctx, "callee"), FlatSymbolRefAttribute::new(ctx, name).into())])
+        .build()
+        .expect("valid operation");
+    design.body().append(import);
+}
+
+/// Append `sv.func.call.procedural @name(arguments)` to the procedural `block`, returning the
+/// values of the function's outputs, which have types `results`.
+pub fn call<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, name: &str, arguments: &[Value<'c, '_>],
+                    results: &[Type<'c>], location: Location<'c>) -> Vec<Value<'c, 'a>> {
+    let call = OperationBuilder::new("sv.func.call.procedural", location)
+        .add_attributes(&[(Identifier::new(ctx, "callee"), FlatSymbolRefAttribute::new(ctx, name).into())])
+        .add_operands(arguments)
+        .add_results(results)
+        .build()
+        .expect("valid operation");
+    let call = block.append(call);
+    (0..results.len()).map(|index| call.result(index).unwrap().into()).collect()
+}
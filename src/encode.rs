@@ -0,0 +1,88 @@
+//! Binary to one-hot decoders and priority encoders, built inline or as reusable modules.
+//!
+//! ```ignore
+//! let select = encode::decoder(&ctx, &body, address, here!(ctx))?;
+//! let (valid, index) = encode::priority_encoder(&ctx, &body, requests, here!(ctx))?;
+//! encode::priority_encoder_module(&design, "PriorityEncoder8", 8, here!(ctx))?;
+//! ```
+
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, BlockLike, Location, Value, ValueLike};
+use melior::Context;
+
+use crate::constant::{const_int, Signedness};
+use crate::diagnostics::Append;
+use crate::mux::priority_mux;
+use crate::ports::{self, PortDirection};
+use crate::reduce::reduce_or;
+use crate::width::{extract, zext};
+use crate::{bit_width, Design, Error};
+
+/// The width of an index selecting one of `count` things, at least 1.
+pub fn index_width(count: u64) -> u64 {
+    u64::from(count.next_power_of_two().trailing_zeros()).max(1)
+}
+
+/// `1 << index`: a one-hot value with a bit for each of the `2^w` values of the `w` bit `index`.
+pub fn decoder<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, index: Value<'c, 'a>,
+                       location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let index_width = bit_width(index.r#type()).ok_or(Error::UnknownWidth(index.r#type().to_string()))?;
+    let width = 1u64.checked_shl(index_width as u32).filter(|width| *width <= u64::from(u32::MAX))
+        .ok_or(Error::InvalidResize { operation: "decode", from: index_width, to: u64::MAX })?;
+    let one = const_int(ctx, block, width as u32, 1, Signedness::Unsigned, location)?;
+    let amount = zext(ctx, block, index, width, location)?;
+    let shift = OperationBuilder::new("comb.shl", location)
+        .add_operands(&[one, amount])
+        .add_results(&[IntegerType::new(ctx, width as u32).into()])
+        .build()
+        .expect("valid operation");
+    Ok(block.append(shift).result(0).unwrap().into())
+}
+
+/// The index of the lowest set bit of `requests`, and an `i1` set when any bit is set. The index
+/// is [`index_width`] bits wide and 0 when no bit is set.
+pub fn priority_encoder<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, requests: Value<'c, 'a>,
+                                location: Location<'c>) -> Result<(Value<'c, 'a>, Value<'c, 'a>), Error> {
+    let count = bit_width(requests.r#type()).ok_or(Error::UnknownWidth(requests.r#type().to_string()))?;
+    let width = index_width(count) as u32;
+    let mut cases = Vec::new();
+    for bit in 0..count {
+        let request = extract(ctx, block, requests, bit, 1, location);
+        cases.push((request, const_int(ctx, block, width, bit as i128, Signedness::Unsigned, location)?));
+    }
+    let zero = const_int(ctx, block, width, 0, Signedness::Unsigned, location)?;
+    let index = priority_mux(block, &cases, zero, location)?;
+    Ok((reduce_or(ctx, block, requests, location)?, index))
+}
+
+/// Append a module `name` decoding an `index_width` bit `index` input to a `select` output with
+/// a bit per index value, see [`decoder`].
+pub fn decoder_module<'c>(design: &Design<'c>, name: &str, index_width: u64,
+                          location: Location<'c>) -> Result<(), Error> {
+    let ctx = design.context();
+    let index_type = IntegerType::new(ctx, index_width as u32).into();
+    let select_width = 1u32.checked_shl(index_width as u32)
+        .ok_or(Error::InvalidResize { operation: "decode", from: index_width, to: u64::MAX })?;
+    let select_type = IntegerType::new(ctx, select_width).into();
+    ports::module(design, name, &[("index", PortDirection::Input, index_type),
+                                  ("select", PortDirection::Output, select_type)], |body| {
+        let index = body.argument(0).unwrap().into();
+        Ok(vec![decoder(ctx, body, index, location)?])
+    }, location)
+}
+
+/// Append a module `name` with a `width` bit `requests` input and `valid` and `index` outputs,
+/// see [`priority_encoder`].
+pub fn priority_encoder_module<'c>(design: &Design<'c>, name: &str, width: u64,
+                                   location: Location<'c>) -> Result<(), Error> {
+    let ctx = design.context();
+    let ports = [("requests", PortDirection::Input, IntegerType::new(ctx, width as u32).into()),
+                 ("valid", PortDirection::Output, IntegerType::new(ctx, 1).into()),
+                 ("index", PortDirection::Output, IntegerType::new(ctx, index_width(width) as u32).into())];
+    ports::module(design, name, &ports, |body| {
+        let requests = body.argument(0).unwrap().into();
+        let (valid, index) = priority_encoder(ctx, body, requests, location)?;
+        Ok(vec![valid, index])
+    }, location)
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+
+/// Errors reported by the generators before they produce invalid IR.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A macro was referenced before `sv.macro.decl` was emitted for it.
+    UndeclaredMacro(String),
+    /// A reference to a macro with a different number of arguments than it was declared with.
+    MacroArguments { name: String, expected: usize, found: usize },
+    /// An integer literal that isn't valid or doesn't fit in the requested width.
+    InvalidConstant { literal: String, width: u32 },
+    /// `const_int` was given a value outside the range of its width and signedness.
+    ConstantOutOfRange { value: i128, width: u32, signed: bool },
+    /// A type with no fixed bit width where one is required.
+    UnknownWidth(String),
+    /// A zero bit type where a value needs at least one bit.
+    ZeroWidth(String),
+    /// Operand widths that must agree don't.
+    WidthMismatch { expected: u64, found: u64 },
+    /// A value of a type the operation can't take, with what it needs and the type as printed.
+    TypeMismatch { expected: String, found: String },
+    /// Fewer operands than an operation needs.
+    TooFewOperands { operation: &'static str, minimum: usize, found: usize },
+    /// A [`crate::width`] helper asked to extend a value to fewer bits, truncate it to more, or
+    /// resize it to zero bits.
+    InvalidResize { operation: &'static str, from: u64, to: u64 },
+    /// A name used twice where names must be unique, like the fields of an `om.class`.
+    DuplicateName(String),
+    /// A textual pass pipeline that MLIR couldn't parse.
+    InvalidPipeline(String),
+    /// A pass pipeline that failed, with the diagnostics it reported.
+    PassFailed { pipeline: String, diagnostics: Vec<Diagnostic> },
+    /// A design that failed verification, with the diagnostics the verifier reported.
+    VerificationFailed(Vec<Diagnostic>),
+    /// IR text or bytecode handed to [`crate::Design::parse`] and co. that doesn't parse.
+    ParseFailed(Vec<Diagnostic>),
+    /// A design whose printed form doesn't parse back, verify, or print the same again.
+    RoundtripFailed(Vec<Diagnostic>),
+    /// A file system error while exporting.
+    Io(String),
+    /// An external CIRCT tool couldn't be run or failed.
+    Tool(String),
+    /// A module name that isn't defined in the design.
+    UnknownModule(String),
+    /// A modport port naming a signal its interface doesn't declare, as `Interface::signal`, or a
+    /// cross module reference to a signal its module doesn't declare, as `Module::signal`.
+    UnknownSignal(String),
+    /// A port a module doesn't have, as `Module::port`.
+    UnknownPort(String),
+    /// A cycle of combinational logic, with the signals around it.
+    CombinationalLoop { module: String, path: Vec<String> },
+    /// A signal, as `Module::signal`, assigned from more than one place, with the drivers' locations.
+    MultipleDrivers { signal: String, drivers: Vec<String> },
+    /// A signal used before it is declared, like one given a default at the top of the block
+    /// declaring it.
+    UseBeforeDeclaration(String),
+    /// A module spec that couldn't be parsed.
+    InvalidSpec(String),
+    /// A parameter table that couldn't be parsed, or a row missing a parameter.
+    InvalidTable(String),
+    /// A generator parameter out of range, like an LFSR tap beyond its width.
+    InvalidParameter(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UndeclaredMacro(name) => write!(f, "macro `{name}` is referenced but not declared"),
+            Error::MacroArguments { name, expected, found } => {
+                write!(f, "macro `{name}` takes {expected} arguments, found {found}")
+            }
+            Error::InvalidConstant { literal, width } =>
+                write!(f, "`{literal}` is not a valid {width} bit integer constant"),
+            Error::ConstantOutOfRange { value, width, signed } =>
+                write!(f, "{value} does not fit in {width} {} bits", if *signed { "signed" } else { "unsigned" }),
+            Error::UnknownWidth(r#type) => write!(f, "`{type}` does not have a known bit width"),
+            Error::ZeroWidth(r#type) => write!(f, "`{type}` has no bits"),
+            Error::WidthMismatch { expected, found } => write!(f, "expected a {expected} bit value, found {found} bits"),
+            Error::TypeMismatch { expected, found } => write!(f, "expected {expected}, found a `{found}`"),
+            Error::TooFewOperands { operation, minimum, found } => {
+                write!(f, "`{operation}` takes at least {minimum} operands, found {found}")
+            Error::InvalidResize { operation, from, to } => {
+                write!(f, "cannot {operation} a {from} bit value to {to} bits")
+            }
+            Error::DuplicateName(name) => write!(f, "`{name}` is defined more than once"),
+            Error::InvalidPipeline(pipeline) => write!(f, "invalid pass pipeline `{pipeline}`"),
+            Error::PassFailed { pipeline, diagnostics } => {
+                write!(f, "pass pipeline `{pipeline}` failed")?;
+                diagnostics.iter().try_for_each(|diagnostic| write!(f, "\n{diagnostic}"))
+            }
+            Error::VerificationFailed(diagnostics) => {
+                write!(f, "design failed verification")?;
+                diagnostics.iter().try_for_each(|diagnostic| write!(f, "\n{diagnostic}"))
+            }
+            Error::Io(message) => write!(f, "{message}"),
+            Error::Tool(message) => write!(f, "{message}"),
+            Error::ParseFailed(diagnostics) => {
+                write!(f, "failed to parse IR")?;
+                diagnostics.iter().try_for_each(|diagnostic| write!(f, "\n{diagnostic}"))
+            }
+            Error::RoundtripFailed(diagnostics) => {
+                write!(f, "design failed the print/parse roundtrip")?;
+                diagnostics.iter().try_for_each(|diagnostic| write!(f, "\n{diagnostic}"))
+            }
+            Error::UnknownModule(name) => write!(f, "no module named `{name}`"),
+            Error::UnknownSignal(name) => write!(f, "no signal named `{name}`"),
+            Error::UnknownPort(name) => write!(f, "no port named `{name}`"),
+            Error::CombinationalLoop { module, path } => {
+                write!(f, "combinational loop in `{module}`: {}", path.join(" -> "))
+            }
+            Error::MultipleDrivers { signal, drivers } => {
+                write!(f, "`{signal}` has more than one driver")?;
+                drivers.iter().try_for_each(|driver| write!(f, "\n  driven at {driver}"))
+            }
+            Error::UseBeforeDeclaration(signal) => write!(f, "`{signal}` is used before it is declared"),
+            Error::InvalidSpec(message) => write!(f, "invalid module spec: {message}"),
+            Error::InvalidTable(message) => write!(f, "invalid parameter table: {message}"),
+            Error::InvalidParameter(message) => write!(f, "invalid generator parameter: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,47 @@
+//! ESI channels, latency insensitive connections between generated modules. Channel typed
+//! ports and values are lowered to valid/ready signals by
+//! [`PassPipeline::lower_esi`](crate::pipeline::PassPipeline::lower_esi).
+//!
+//! ```ignore
+//! let (channel, ready) = esi::wrap(&ctx, &body, data, valid, here!(ctx));
+//! let (data, valid) = esi::unwrap(&ctx, &body, channel, i32, ready, here!(ctx));
+//! ```
+
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, Location, Type, Value, ValueLike};
+use melior::Context;
+
+use crate::diagnostics::Append;
+
+/// `!esi.channel<inner>`, a channel carrying `inner` values, which can be a module port type.
+pub fn channel_type<'c>(ctx: &'c Context, inner: Type<'c>) -> Type<'c> {
+    let source = format!("!esi.channel<{inner}>");
+    Type::parse(ctx, &source).unwrap_or_else(|| panic!("`{source}` is a valid channel type"))
+}
+
+/// Append `esi.wrap.vr data, valid` to `block`, sending `data` into a channel while `valid` is
+/// set. Returns the channel and its `ready` signal, set when the receiver accepts the data.
+pub fn wrap<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, data: Value<'c, '_>, valid: Value<'c, '_>,
+                    location: Location<'c>) -> (Value<'c, 'a>, Value<'c, 'a>) {
+    let wrap = OperationBuilder::new("esi.wrap.vr", location)
+        .add_operands(&[data, valid])
+        .add_results(&[channel_type(ctx, data.r#type()), IntegerType::new(ctx, 1).into()])
+        .build()
+        .expect("valid operation");
+    let wrap = block.append(wrap);
+    (wrap.result(0).unwrap().into(), wrap.result(1).unwrap().into())
+}
+
+/// Append `esi.unwrap.vr channel, ready` to `block`, receiving from a channel of `inner` values
+/// when `ready` is set. Returns the data and its `valid` signal.
+pub fn unwrap<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, channel: Value<'c, '_>, inner: Type<'c>,
+                      ready: Value<'c, '_>, location: Location<'c>) -> (Value<'c, 'a>, Value<'c, 'a>) {
+    let unwrap = OperationBuilder::new("esi.unwrap.vr", location)
+        .add_operands(&[channel, ready])
+        .add_results(&[inner, IntegerType::new(ctx, 1).into()])
+        .build()
+        .expect("valid operation");
+    let unwrap = block.append(unwrap);
+    (unwrap.result(0).unwrap().into(), unwrap.result(1).unwrap().into())
+}
@@ -0,0 +1,366 @@
+//! Exporting a [`Design`] as SystemVerilog, optionally with HGLDD debug info, or as other
+//! formats CIRCT can lower it to.
+
+use std::ffi::c_void;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use melior::ir::operation::{OperationLike, OperationPrintingFlags};
+use melior::ir::{Attribute, Operation};
+use melior::{Context, LogicalResult, StringRef};
+use mlir_sys::MlirStringRef;
+
+use crate::hierarchy::{remove_unreachable, InstanceGraph};
+use crate::macros::route_to_header;
+use crate::pipeline::{PassBackend, PassPipeline};
+use crate::ports::find_module;
+use crate::verif::guard_assertions;
+use crate::{diagnostics, Design, Error};
+
+/// Options for [`export_split_verilog`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Also emit HGLDD (`.dd`) debug info files mapping the Verilog back to the locations the
+    /// design was built with, for source level waveform viewers.
+    pub hgldd: bool,
+    /// When not empty, modules not reachable from these tops are removed from the design before
+    /// it is exported.
+    pub tops: Vec<String>,
+    /// Lower struct and array typed ports to scalar ports before export, for downstream tools
+    /// that can't handle packed aggregates. Struct ports are split into a port per field and
+    /// packed arrays are emitted as flattened integers.
+    pub flatten_aggregates: bool,
+    /// Also write the design's [`placement`](crate::placement)s under its tops to a Tcl script of
+    /// this name, for the FPGA vendor tools.
+    pub placement_tcl: Option<String>,
+    /// Collect the macro definitions into a header of this name, `defines.svh` for example, which
+    /// every other file includes, rather than repeating them in each file. See
+    /// [`macros::route_to_header`](crate::macros::route_to_header).
+    pub defines_header: Option<String>,
+}
+
+/// Where ExportVerilog emits a module or other top level op, its `#hw.output_file` attribute.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputFile {
+    /// The file, relative to the export directory. A path ending in `/` names a directory the op
+    /// is emitted to in its default file name.
+    pub path: String,
+    /// Leave the file out of the file list, for headers that other files include.
+    pub exclude_from_file_list: bool,
+    /// Also emit the ops split export replicates into every file (macro definitions, ...).
+    pub include_replicated_ops: bool,
+}
+
+impl OutputFile {
+    pub fn new(path: &str) -> Self {
+        Self { path: path.to_string(), exclude_from_file_list: false, include_replicated_ops: false }
+    }
+
+    pub fn attribute<'c>(&self, ctx: &'c Context) -> Attribute<'c> {
+        let mut text = format!("#hw.output_file<{:?}", self.path);
+        if self.exclude_from_file_list {
+            text.push_str(", excludeFromFileList");
+        }
+        if self.include_replicated_ops {
+            text.push_str(", includeReplicatedOps");
+        }
+        text.push('>');
+        Attribute::parse(ctx, &text).expect("valid output file")
+    }
+
+    /// Emit `operation`, a module, `sv.bind`, `sv.ifdef`, ... at the top level, to this file.
+    pub fn set(&self, operation: &Operation) {
+        let ctx = unsafe { operation.context().to_ref() };
+        unsafe {
+            mlir_sys::mlirOperationSetAttributeByName(operation.to_raw(), StringRef::new("output_file").to_raw(),
+                                                      self.attribute(ctx).to_raw());
+        }
+    }
+}
+
+/// Emit the module `module` of `design` to `file`.
+pub fn set_output_file(design: &Design, module: &str, file: &OutputFile) -> Result<(), Error> {
+    let module = find_module(design, module).ok_or_else(|| Error::UnknownModule(module.to_string()))?;
+    file.set(&module);
+    Ok(())
+}
+
+/* The file list ExportSplitVerilog writes alongside the files it emits. */
+pub(crate) const FILE_LIST: &str = "filelist.f";
+
+/* Run a CIRCT tool for functionality that isn't in the C API. `$<ENV>` overrides the tool found
+   on the `PATH`, e.g. `CIRCT_TRANSLATE=/opt/circt/bin/circt-translate`. What it prints to stderr
+   is passed on if it succeeds, for its warnings and reports, and is the error if it fails. */
+pub(crate) fn run_tool(tool: &str, env: &str, args: &[String]) -> Result<(), Error> {
+    let program = std::env::var(env).unwrap_or_else(|_| tool.to_string());
+    tracing::debug!(%program, ?args, "running");
+    let output = Command::new(&program)
+        .args(args)
+        .output()
+        .map_err(|err| Error::Tool(format!("{program}: {err}")))?;
+    if !output.status.success() {
+        return Err(Error::Tool(format!("{program}: {}", String::from_utf8_lossy(&output.stderr))));
+    }
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    Ok(())
+}
+
+/* Print `design` with locations to `path`, as input for a CIRCT tool. */
+pub(crate) fn write_design(design: &Design, path: &Path) -> Result<(), Error> {
+    let flags = OperationPrintingFlags::new().enable_debug_info(true, false);
+    let text = design.module().as_operation().to_string_with_flags(flags)
+        .map_err(|err| Error::Io(err.to_string()))?;
+    fs::write(path, text).map_err(|err| Error::Io(format!("{}: {err}", path.display())))
+}
+
+pub(crate) fn temp_dir(purpose: &str) -> Result<PathBuf, Error> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!("circt-sv-basic-{purpose}-{}-{}", std::process::id(),
+                                                COUNT.fetch_add(1, Ordering::Relaxed)));
+    fs::create_dir_all(&dir).map_err(|err| Error::Io(format!("{}: {err}", dir.display())))?;
+    Ok(dir)
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|err| Error::Io(format!("{}: {err}", path.display())))
+}
+
+/// Export each module of `design` to its own file in `dir` with ExportSplitVerilog, returning the
+/// files written in file list order. The design's emission style is applied to each file, and its
+/// assertions are put behind its [`AssertionGuard`](crate::verif::AssertionGuard).
+#[tracing::instrument(skip_all, fields(dir = %dir.display()))]
+pub fn export_split_verilog(design: &Design, dir: &Path, options: &ExportOptions) -> Result<Vec<PathBuf>, Error> {
+    guard_assertions(design);
+    if let Some(header) = &options.defines_header {
+        route_to_header(design, header);
+    }
+    if !options.tops.is_empty() {
+        let tops: Vec<&str> = options.tops.iter().map(String::as_str).collect();
+        remove_unreachable(design, &tops)?;
+    }
+    if options.hgldd || options.flatten_aggregates {
+        let mut lowering = design.lowering_options();
+        lowering.emit_verilog_locations |= options.hgldd;
+        lowering.disallow_packed_arrays |= options.flatten_aggregates;
+        design.set_lowering_options(lowering);
+    }
+    let mut pipeline = PassPipeline::new();
+    if options.flatten_aggregates {
+        pipeline = pipeline.flatten_struct_ports();
+    }
+    if let Some(tcl) = &options.placement_tcl {
+        let graph = InstanceGraph::new(design);
+        let tops: Vec<&str> = if options.tops.is_empty() {
+            graph.tops()
+        } else {
+            options.tops.iter().map(String::as_str).collect()
+        };
+        pipeline = pipeline.export_placement_tcl(&tops, tcl);
+    }
+    pipeline
+        .add(&format!("export-split-verilog{{dir-name={}}}", dir.display()))
+        .run(design)?;
+
+    let style = design.emission_style();
+    let mut files = Vec::new();
+    for name in read(&dir.join(FILE_LIST))?.lines().filter(|line| !line.is_empty()) {
+        let file = dir.join(name);
+        let verilog = style.reindent(&read(&file)?);
+        fs::write(&file, verilog).map_err(|err| Error::Io(format!("{}: {err}", file.display())))?;
+        tracing::debug!(file = %file.display(), "exported");
+        files.push(file);
+    }
+
+    let header = options.defines_header.as_ref().map(|header| dir.join(header));
+    if let Some(header) = header.filter(|header| header.exists()) {
+        files.insert(0, header);
+    }
+    if let Some(tcl) = options.placement_tcl.as_ref().map(|tcl| dir.join(tcl)).filter(|tcl| tcl.exists()) {
+        files.push(tcl);
+    }
+    if options.hgldd {
+        files.extend(emit_hgldd(design, dir)?);
+    }
+    Ok(files)
+}
+
+/* How ExportVerilog starts the text of an op with an output file when it emits a single stream. */
+const FILE_SEPARATOR: (&str, &str) = ("// ----- 8< ----- FILE \"", "\" ----- 8< -----");
+
+/// Export `design` as a single SystemVerilog string, calling ExportVerilog through the C API
+/// rather than running a pass pipeline. The text of ops with an [`OutputFile`] follows the ops
+/// without one, each file's after a `` // ----- 8< ----- FILE "name" ----- 8< ----- `` line; see
+/// [`export_verilog_files`] to take them apart. With [`PassBackend::CirctOpt`] the design is
+/// exported with [`export_split_verilog`] instead, and each file follows such a line.
+pub fn export_verilog(design: &Design) -> Result<String, Error> {
+    unsafe extern "C" fn append(data: MlirStringRef, verilog: *mut c_void) {
+        let verilog = unsafe { &mut *(verilog as *mut Vec<u8>) };
+        verilog.extend_from_slice(unsafe { slice::from_raw_parts(data.data as *const u8, data.length) });
+    }
+    if design.pass_backend() == PassBackend::CirctOpt {
+        return export_through_pipeline(design);
+    }
+    guard_assertions(design);
+    let mut verilog = Vec::new();
+    let (result, reported) = diagnostics::capture(design.context(), || unsafe {
+        LogicalResult::from_raw(mlir_sys::mlirExportVerilog(design.module().to_raw(), Some(append),
+                                                            &mut verilog as *mut Vec<u8> as *mut c_void))
+    });
+    if !result.is_success() {
+        let diagnostics = diagnostics::resolve(&design.module().as_operation(), reported);
+        return Err(Error::PassFailed { pipeline: "export-verilog".to_string(), diagnostics });
+    }
+    let verilog = String::from_utf8(verilog).map_err(|err| Error::Io(err.to_string()))?;
+    Ok(design.emission_style().reindent(&verilog))
+}
+
+/* export_verilog through the pass pipeline, so the circt-opt backend, reproducers and
+   instrumentation apply: export-split-verilog into a temporary directory, then read it back. */
+fn export_through_pipeline(design: &Design) -> Result<String, Error> {
+    let dir = temp_dir("export")?;
+    let (prefix, suffix) = FILE_SEPARATOR;
+    let verilog = export_split_verilog(design, &dir, &ExportOptions::default()).and_then(|files| {
+        files.iter()
+            .map(|file| {
+                let name = file.strip_prefix(&dir).unwrap_or(file).display();
+                Ok(format!("{prefix}{name}{suffix}\n{}", read(file)?))
+            })
+            .collect::<Result<Vec<_>, Error>>()
+    });
+    let _ = fs::remove_dir_all(&dir);
+    Ok(verilog?.concat())
+}
+
+/// Export `design` with [`export_verilog`], calling `on_file` with the name and text of each
+/// file, in the order they are emitted, for split output without writing to a directory. The
+/// text of ops without an [`OutputFile`] comes first, named `None`.
+pub fn export_verilog_files(design: &Design, mut on_file: impl FnMut(Option<&str>, &str)) -> Result<(), Error> {
+    let verilog = export_verilog(design)?;
+    let (prefix, suffix) = FILE_SEPARATOR;
+    let (mut name, mut text) = (None, String::new());
+    for line in verilog.split_inclusive('\n') {
+        match line.trim_end().strip_prefix(prefix).and_then(|rest| rest.strip_suffix(suffix)) {
+            Some(file) => {
+                if name.is_some() || !text.trim().is_empty() {
+                    on_file(name, &text);
+                }
+                name = Some(file);
+                text.clear();
+            }
+            None => text.push_str(line),
+        }
+    }
+    if name.is_some() || !text.trim().is_empty() {
+        on_file(name, &text);
+    }
+    Ok(())
+}
+
+/* Run circt-translate's HGLDD emitter over the exported design, which ExportVerilog has annotated
+   with the locations of the Verilog it emitted. */
+fn emit_hgldd(design: &Design, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let input = dir.join("design.mlir");
+    write_design(design, &input)?;
+    let before: Vec<PathBuf> = hgldd_files(dir)?;
+    let result = run_tool("circt-translate", "CIRCT_TRANSLATE",
+                          &["--emit-split-hgldd".to_string(),
+                            format!("--hgldd-output-dir={}", dir.display()),
+                            input.display().to_string()]);
+    let _ = fs::remove_file(&input);
+    result?;
+    Ok(hgldd_files(dir)?.into_iter().filter(|file| !before.contains(file)).collect())
+}
+
+fn hgldd_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let entries = fs::read_dir(dir).map_err(|err| Error::Io(format!("{}: {err}", dir.display())))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "dd"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Lower `design` through the arc dialect with arcilator, for fast simulation without a Verilog
+/// simulator. Writes `<name>.ll`, the LLVM IR model, and `<name>.json`, the state layout
+/// `arcilator-header-cpp.py` turns into a C++ header, to `dir` and returns their paths.
+#[tracing::instrument(skip_all, fields(dir = %dir.display()))]
+pub fn export_arc(design: &Design, dir: &Path, name: &str) -> Result<Vec<PathBuf>, Error> {
+    let input = dir.join(format!("{name}.mlir"));
+    let model = dir.join(format!("{name}.ll"));
+    let state = dir.join(format!("{name}.json"));
+    write_design(design, &input)?;
+    let result = run_tool("arcilator", "ARCILATOR",
+                          &[input.display().to_string(),
+                            format!("--state-file={}", state.display()),
+                            "-o".to_string(),
+                            model.display().to_string()]);
+    let _ = fs::remove_file(&input);
+    result?;
+    Ok(vec![model, state])
+}
+
+/// Translate `design` to BTOR2 for hardware model checkers, writing it to `path`. Its `verif`
+/// assertions become `bad` properties. State must be held in `seq` registers, not `sv` procedural
+/// blocks, which have no BTOR2 translation.
+#[tracing::instrument(skip_all, fields(path = %path.display()))]
+pub fn export_btor2(design: &Design, path: &Path) -> Result<(), Error> {
+    let dir = temp_dir("btor2")?;
+    let input = dir.join("design.mlir");
+    let result = write_design(design, &input).and_then(|()| {
+        run_tool("circt-translate", "CIRCT_TRANSLATE",
+                 &["--export-btor2".to_string(),
+                   input.display().to_string(),
+                   "-o".to_string(),
+                   path.display().to_string()])
+    });
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Unroll `top` for `bound` cycles with CIRCT's bounded model checking lowering (`lower-to-bmc`,
+/// then the hw, comb, seq, and verif to SMT conversions) and write the resulting SMT-LIB query to
+/// `path`, for a solver to check the design's `verif` assertions against.
+#[tracing::instrument(skip_all, fields(path = %path.display()))]
+pub fn export_smtlib(design: &Design, path: &Path, top: &str, bound: u32) -> Result<(), Error> {
+    let dir = temp_dir("smtlib")?;
+    let input = dir.join("design.mlir");
+    let result = write_design(design, &input).and_then(|()| {
+        run_tool("circt-bmc", "CIRCT_BMC",
+                 &[input.display().to_string(),
+                   format!("--module={top}"),
+                   format!("-b={bound}"),
+                   "--emit-smtlib".to_string(),
+                   "-o".to_string(),
+                   path.display().to_string()])
+    });
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Convert `design`'s modules to SystemC with `convert-hw-to-systemc` and write them as a C++
+/// header to `path`, for SystemC virtual platforms. Modules must be built from `hw` and `comb`;
+/// `sv` operations have no SystemC lowering. The conversion runs with circt-opt on a written
+/// copy, leaving `design` as it was.
+#[tracing::instrument(skip_all, fields(path = %path.display()))]
+pub fn export_systemc(design: &Design, path: &Path) -> Result<(), Error> {
+    let dir = temp_dir("systemc")?;
+    let input = dir.join("design.mlir");
+    let converted = dir.join("systemc.mlir");
+    let result = write_design(design, &input)
+        .and_then(|()| run_tool("circt-opt", "CIRCT_OPT",
+                                &["--pass-pipeline=builtin.module(convert-hw-to-systemc)".to_string(),
+                                  input.display().to_string(),
+                                  "-o".to_string(),
+                                  converted.display().to_string()]))
+        .and_then(|()| run_tool("circt-translate", "CIRCT_TRANSLATE",
+                                &["--export-systemc".to_string(),
+                                  converted.display().to_string(),
+                                  "-o".to_string(),
+                                  path.display().to_string()]));
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
@@ -0,0 +1,26 @@
+//! `sv.force` and `sv.release`, for testbench utilities that override a design's signals from
+//! procedural code.
+//!
+//! ```ignore
+//! always::always(&ctx, &body, &[(Posedge, clk)], |always| {
+//!     force::force(&ctx, always, stuck_at, zero, here!(ctx));
+//! }, here!(ctx));
+//! ```
+
+use melior::dialect::ods::sv;
+use melior::ir::{Block, Location, Value};
+use melior::Context;
+
+use crate::diagnostics::Append;
+
+/// Append `sv.force dest, value` to the procedural `block`, holding the inout `dest` at `value`
+/// until it is released.
+pub fn force<'c>(ctx: &'c Context, block: &Block<'c>, dest: Value<'c, '_>, value: Value<'c, '_>,
+                 location: Location<'c>) {
+    block.append(sv::force(ctx, dest, value, location).into());
+}
+
+/// Append `sv.release dest` to the procedural `block`, returning `dest` to its drivers.
+pub fn release<'c>(ctx: &'c Context, block: &Block<'c>, dest: Value<'c, '_>, location: Location<'c>) {
+    block.append(sv::release(ctx, dest, location).into());
+}
@@ -0,0 +1,172 @@
+//! The module hierarchy of a [`Design`], for generators that make decisions based on how modules
+//! are instantiated (deduplicating identical submodules, dropping unreachable ones, ...).
+//!
+//! ```ignore
+//! let graph = InstanceGraph::new(&design);
+//! for module in graph.unreachable(&["Top"]) {
+//!     eprintln!("{module} is never instantiated");
+//! }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use melior::ir::attribute::{FlatSymbolRefAttribute, StringAttribute};
+use melior::ir::operation::OperationLike;
+use melior::ir::{Attribute, Operation};
+use melior::StringRef;
+
+use crate::ports::find_module;
+use crate::{operations, symbol_name, walk, Design, Error};
+
+/// An `hw.instance` of a module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instance {
+    /// The instance name.
+    pub name: String,
+    /// The module instantiated.
+    pub module: String,
+}
+
+/* The ops that define a module an instance can refer to. */
+const MODULE_OPS: &[&str] = &["hw.module", "hw.module.extern", "hw.module.generated"];
+
+/// The modules of a design and the instances in each, as of when it was built. It isn't updated
+/// as the design changes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstanceGraph {
+    /// Module name -> the instances in its body, in order. Every module has an entry.
+    children: BTreeMap<String, Vec<Instance>>,
+}
+
+impl InstanceGraph {
+    pub fn new(design: &Design) -> Self {
+        let mut children = BTreeMap::new();
+        for operation in operations(&design.body()) {
+            if !MODULE_OPS.contains(&operation.name().as_string_ref().as_str().unwrap_or_default()) {
+                continue;
+            }
+            let Some(name) = symbol_name(&operation) else { continue };
+            let mut instances = Vec::new();
+            walk(&operation, &mut |nested| {
+                if let Some(instance) = instance(nested) {
+                    instances.push(instance);
+                }
+            });
+            children.insert(name, instances);
+        }
+        Self { children }
+    }
+
+    /// The names of all modules, sorted.
+    pub fn modules(&self) -> impl Iterator<Item = &str> {
+        self.children.keys().map(String::as_str)
+    }
+
+    pub fn contains(&self, module: &str) -> bool {
+        self.children.contains_key(module)
+    }
+
+    /// The instances in `module`'s body, empty for external or unknown modules.
+    pub fn children(&self, module: &str) -> &[Instance] {
+        self.children.get(module).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The modules that instantiate `module`, sorted.
+    pub fn parents(&self, module: &str) -> Vec<&str> {
+        self.children.iter()
+            .filter(|(_, instances)| instances.iter().any(|instance| instance.module == module))
+            .map(|(parent, _)| parent.as_str())
+            .collect()
+    }
+
+    /// The modules no other module instantiates, the candidate tops, sorted.
+    pub fn tops(&self) -> Vec<&str> {
+        self.modules().filter(|module| self.parents(module).is_empty()).collect()
+    }
+
+    /// `tops` and every module instantiated, directly or indirectly, under them.
+    pub fn reachable(&self, tops: &[&str]) -> BTreeSet<String> {
+        let mut reachable = BTreeSet::new();
+        let mut pending: Vec<&str> = tops.to_vec();
+        while let Some(module) = pending.pop() {
+            if reachable.insert(module.to_string()) {
+                pending.extend(self.children(module).iter().map(|instance| instance.module.as_str()));
+            }
+        }
+        reachable
+    }
+
+    /// The modules that aren't reachable from `tops`, sorted.
+    pub fn unreachable(&self, tops: &[&str]) -> Vec<&str> {
+        let reachable = self.reachable(tops);
+        self.modules().filter(|module| !reachable.contains(*module)).collect()
+    }
+
+    /// How many times `module` is instantiated in the hierarchy under `top`, counting every path
+    /// to it. `top` itself counts once.
+    pub fn instance_count(&self, top: &str, module: &str) -> usize {
+        if top == module {
+            return 1;
+        }
+        self.children(top).iter().map(|instance| self.instance_count(&instance.module, module)).sum()
+    }
+}
+
+fn instance(operation: &Operation) -> Option<Instance> {
+    if operation.name().as_string_ref().as_str().ok()? != "hw.instance" {
+        return None;
+    }
+    let module = FlatSymbolRefAttribute::try_from(operation.attribute("moduleName").ok()?).ok()?;
+    let name = StringAttribute::try_from(operation.attribute("instanceName").ok()?).ok()?;
+    Some(Instance { name: name.value().to_string(), module: module.value().to_string() })
+}
+
+/// Remove the modules not reachable from `tops` from `design`, returning their names. For spec
+/// driven generators that produce helper modules whether or not they end up used. The tops are
+/// the names given to the module builders, and must all be modules of `design`, so a misspelled
+/// one can't remove them all.
+pub fn remove_unreachable(design: &Design, tops: &[&str]) -> Result<Vec<String>, Error> {
+    let graph = InstanceGraph::new(design);
+    let tops: Vec<String> = tops.iter().map(|top| design.module_name(top)).collect();
+    if let Some(top) = tops.iter().find(|top| !graph.contains(top)) {
+        return Err(Error::UnknownModule(top.clone()));
+    }
+    let tops: Vec<&str> = tops.iter().map(String::as_str).collect();
+    let unreachable: Vec<String> = graph.unreachable(&tops).into_iter().map(str::to_string).collect();
+    for operation in operations(&design.body()) {
+        let is_module = MODULE_OPS.contains(&operation.name().as_string_ref().as_str().unwrap_or_default());
+        if is_module && symbol_name(&operation).is_some_and(|name| unreachable.contains(&name)) {
+            tracing::debug!(module = ?symbol_name(&operation), "removing unreachable module");
+            unsafe { mlir_sys::mlirOperationDestroy(operation.to_raw()) };
+        }
+    }
+    Ok(unreachable)
+}
+
+/* The `hw.instance` named `name` in `module`, giving it an inner symbol so placements and
+   hierarchical paths can refer to it. Returns the module it instantiates. */
+pub(crate) fn instance_symbol(design: &Design, module: &str, name: &str) -> Result<String, Error> {
+    let ctx = design.context();
+    let module_op = find_module(design, module).ok_or_else(|| Error::UnknownModule(module.to_string()))?;
+    let mut found = None;
+    walk(&module_op, &mut |operation| {
+        if found.is_some() || operation.name().as_string_ref().as_str() != Ok("hw.instance") {
+            return;
+        }
+        let attribute = |name: &str| operation.attribute(name).ok();
+        if attribute("instanceName").and_then(|name| StringAttribute::try_from(name).ok())
+            .is_none_or(|instance| instance.value() != name) {
+            return;
+        }
+        if attribute("inner_sym").is_none() {
+            let symbol = Attribute::parse(ctx, &format!("#hw<innerSym@{name}>")).expect("valid inner symbol");
+            unsafe {
+                mlir_sys::mlirOperationSetAttributeByName(operation.to_raw(), StringRef::new("inner_sym").to_raw(),
+                                                          symbol.to_raw());
+            }
+        }
+        found = attribute("moduleName").and_then(|module| FlatSymbolRefAttribute::try_from(module).ok())
+            .map(|module| module.value().to_string());
+    });
+    found.ok_or_else(|| Error::UnknownModule(format!("{module}/{name}")))
+}
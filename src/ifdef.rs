@@ -0,0 +1,140 @@
+//! `sv.ifdef` and `sv.ifdef.procedural` builders.
+//!
+//! ```ignore
+//! ifdef::ifdef_procedural(&design, &always_block, "SYNTHESIS",
+//!                         |then| { ... },
+//!                         Some(|else_: &Block| { ... }),
+//!                         here!(ctx))?;
+//! ```
+
+use melior::dialect::ods::sv;
+use melior::ir::{Block, Location, Operation};
+use melior::Context;
+
+use crate::diagnostics::Append;
+use crate::macros::ident;
+use crate::{region, Design, Error};
+
+/* Build the op without checking that `cond` is declared. */
+pub(crate) fn build<'c, T, E>(ctx: &'c Context, cond: &str, then: T, else_: Option<E>, procedural: bool,
+                              location: Location<'c>) -> Operation<'c>
+where
+    T: FnOnce(&Block<'c>),
+    E: FnOnce(&Block<'c>),
+{
+    let then_region = region(then);
+    let else_region = region(|block| if let Some(else_) = else_ { else_(block) });
+    if procedural {
+        sv::ifdef_procedural(ctx, then_region, else_region, ident(ctx, cond), location).into()
+    } else {
+        sv::ifdef(ctx, then_region, else_region, ident(ctx, cond), location).into()
+    }
+}
+
+fn append<'c, T, E>(design: &Design<'c>, block: &Block<'c>, cond: &str, then: T, else_: Option<E>,
+                    procedural: bool, location: Location<'c>) -> Result<(), Error>
+where
+    T: FnOnce(&Block<'c>),
+    E: FnOnce(&Block<'c>),
+{
+    if !design.is_macro_declared(cond) {
+        return Err(Error::UndeclaredMacro(cond.to_string()));
+    }
+    block.append(build(design.context(), &design.macro_name(cond), then, else_, procedural, location));
+    Ok(())
+}
+
+/// Append `sv.ifdef @cond` to `block`, a module body or the top level block. `then` and `else_`
+/// fill in the branches; the else branch is left empty when `else_` is `None`.
+pub fn ifdef<'c, T, E>(design: &Design<'c>, block: &Block<'c>, cond: &str, then: T, else_: Option<E>,
+                       location: Location<'c>) -> Result<(), Error>
+where
+    T: FnOnce(&Block<'c>),
+    E: FnOnce(&Block<'c>),
+{
+    append(design, block, cond, then, else_, false, location)
+}
+
+/// Like [`ifdef`], but appends `sv.ifdef.procedural` for use inside `sv.always`, `sv.initial`
+/// and other procedural regions.
+pub fn ifdef_procedural<'c, T, E>(design: &Design<'c>, block: &Block<'c>, cond: &str, then: T, else_: Option<E>,
+                                  location: Location<'c>) -> Result<(), Error>
+where
+    T: FnOnce(&Block<'c>),
+    E: FnOnce(&Block<'c>),
+{
+    append(design, block, cond, then, else_, true, location)
+}
+
+/// Append `` `ifndef cond `` to `block`: an `sv.ifdef @cond` whose then branch is empty.
+pub fn ifndef<'c, T>(design: &Design<'c>, block: &Block<'c>, cond: &str, then: T,
+                     location: Location<'c>) -> Result<(), Error>
+where
+    T: FnOnce(&Block<'c>),
+{
+    append(design, block, cond, |_| {}, Some(then), false, location)
+}
+
+/// Like [`ifndef`], but for procedural regions.
+pub fn ifndef_procedural<'c, T>(design: &Design<'c>, block: &Block<'c>, cond: &str, then: T,
+                                location: Location<'c>) -> Result<(), Error>
+where
+    T: FnOnce(&Block<'c>),
+{
+    append(design, block, cond, |_| {}, Some(then), true, location)
+}
+
+type Arm<'c, 'f> = Box<dyn FnOnce(&Block<'c>) + 'f>;
+
+/// A `` `ifdef A ... `elsif B ... `else ... `endif `` chain, built as `sv.ifdef` ops nested in
+/// each other's else branch, which ExportVerilog prints as `` `elsif ``.
+///
+/// ```ignore
+/// IfdefChain::new("VERILATOR", |then| { ... })
+///     .elsif("VCS", |then| { ... })
+///     .else_(|else_| { ... })
+///     .append(&design, &body, here!(ctx))?;
+/// ```
+pub struct IfdefChain<'c, 'f> {
+    arms: Vec<(String, Arm<'c, 'f>)>,
+    otherwise: Option<Arm<'c, 'f>>,
+    procedural: bool,
+}
+
+impl<'c: 'f, 'f> IfdefChain<'c, 'f> {
+    pub fn new(cond: &str, then: impl FnOnce(&Block<'c>) + 'f) -> Self {
+        Self { arms: vec![(cond.to_string(), Box::new(then))], otherwise: None, procedural: false }
+    }
+
+    /// A chain of `sv.ifdef.procedural` ops, for procedural regions.
+    pub fn procedural(cond: &str, then: impl FnOnce(&Block<'c>) + 'f) -> Self {
+        Self { procedural: true, ..Self::new(cond, then) }
+    }
+
+    pub fn elsif(mut self, cond: &str, then: impl FnOnce(&Block<'c>) + 'f) -> Self {
+        self.arms.push((cond.to_string(), Box::new(then)));
+        self
+    }
+
+    pub fn else_(mut self, otherwise: impl FnOnce(&Block<'c>) + 'f) -> Self {
+        self.otherwise = Some(Box::new(otherwise));
+        self
+    }
+
+    /// Check every condition is declared, then append the chain to `block`.
+    pub fn append(self, design: &Design<'c>, block: &Block<'c>, location: Location<'c>) -> Result<(), Error> {
+        if let Some((cond, _)) = self.arms.iter().find(|(cond, _)| !design.is_macro_declared(cond)) {
+            return Err(Error::UndeclaredMacro(cond.clone()));
+        }
+        let ctx = design.context();
+        let mut nested = self.otherwise;
+        for (cond, then) in self.arms.into_iter().rev() {
+            let op = build(ctx, &design.macro_name(&cond), then, nested, self.procedural, location);
+            nested = Some(Box::new(move |else_: &Block<'c>| { else_.append(op); }));
+        }
+        if let Some(outermost) = nested {
+            outermost(block);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! SystemVerilog interfaces: `sv.interface` declarations, instances inside a module, and access
+//! to their signals directly or through a modport.
+//!
+//! ```ignore
+//! interface::declare(&design, "Bus", &[("data", i8), ("valid", i1)],
+//!                    &[("source", &[(Output, "data"), (Output, "valid")])], here!(ctx));
+//! let bus = interface::instance(&ctx, &body, "bus", "Bus", here!(ctx));
+//! interface::assign(&ctx, &body, bus, "Bus", "data", data, here!(ctx));
+//! let source = interface::modport(&ctx, &body, bus, "Bus", "source", here!(ctx));
+//! ```
+
+use melior::ir::attribute::{ArrayAttribute, StringAttribute, TypeAttribute};
+use melior::ir::operation::OperationBuilder;
+use melior::ir::{Attribute, Block, Identifier, Location, Region, RegionLike, Type, Value};
+use melior::Context;
+
+use crate::diagnostics::Append;
+use crate::ports::PortDirection;
+use crate::{Design, Error};
+
+/// `!sv.interface<@name>`, the type of instances of interface `name`.
+pub fn interface_type<'c>(ctx: &'c Context, name: &str) -> Type<'c> {
+    let source = format!("!sv.interface<@{name}>");
+    Type::parse(ctx, &source).unwrap_or_else(|| panic!("`{source}` is a valid interface type"))
+}
+
+/// `!sv.modport<@interface::@modport>`.
+pub fn modport_type<'c>(ctx: &'c Context, interface: &str, modport: &str) -> Type<'c> {
+    let source = format!("!sv.modport<@{interface}::@{modport}>");
+    Type::parse(ctx, &source).unwrap_or_else(|| panic!("`{source}` is a valid modport type"))
+}
+
+fn symbol<'c>(ctx: &'c Context, name: &str) -> Attribute<'c> {
+    StringAttribute::new(ctx, name).into()
+}
+
+/* `@interface::@member`, how signals and modports are referred to from outside the interface. */
+fn member<'c>(ctx: &'c Context, interface: &str, member: &str) -> Attribute<'c> {
+    Attribute::parse(ctx, &format!("@{interface}::@{member}")).expect("valid symbol reference")
+}
+
+/// Append `sv.interface @name` to the top level of `design`, with an `sv.interface.signal` for
+/// each of `signals` and an `sv.interface.modport` for each of `modports`, whose ports give the
+/// direction of each signal as seen through the modport.
+pub fn declare<'c>(design: &Design<'c>, name: &str, signals: &[(&str, Type<'c>)],
+                   modports: &[(&str, &[(PortDirection, &str)])], location: Location<'c>) -> Result<(), Error> {
+    let ctx = design.context();
+    let body = Block::new(&[]);
+    for (signal, r#type) in signals {
+        let signal = OperationBuilder::new("sv.interface.signal", location)
+            .add_attributes(&[(Identifier::new(ctx, "sym_name"), symbol(ctx, signal)),
+                              (Identifier::new(ctx, "type"), TypeAttribute::new(*r#type).into())])
+            .build()
+            .expect("valid operation");
+        body.append(signal);
+    }
+    for (modport, ports) in modports {
+        let mut attributes = Vec::new();
+        for (direction, signal) in *ports {
+            if !signals.iter().any(|(name, _)| name == signal) {
+                return Err(Error::UnknownSignal(format!("{name}::{signal}")));
+            }
+            let source = format!("#sv.mod_port<{} @{signal}>", direction.keyword());
+            attributes.push(Attribute::parse(ctx, &source).expect("valid modport port"));
+        }
+        let modport = OperationBuilder::new("sv.interface.modport", location)
+            .add_attributes(&[(Identifier::new(ctx, "sym_name"), symbol(ctx, modport)),
+                              (Identifier::new(ctx, "ports"), ArrayAttribute::new(ctx, &attributes).into())])
+            .build()
+            .expect("valid operation");
+        body.append(modport);
+    }
+    let region = Region::new();
+    region.append_block(body);
+    let interface = OperationBuilder::new("sv.interface", location)
+        .add_attributes(&[(Identifier::new(ctx, "sym_name"), symbol(ctx, name))])
+        .add_regions([region])
+        .build()
+        .expect("valid operation");
+    design.body().append(interface);
+    Ok(())
+}
+
+/// Append `sv.interface.instance` of `interface`, named `name`, to the module `block`.
+pub fn instance<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, name: &str, interface: &str,
+                        location: Location<'c>) -> Value<'c, 'a> {
+    let instance = OperationBuilder::new("sv.interface.instance", location)
+        .add_attributes(&[(Identifier::new(ctx, "name"), symbol(ctx, name))])
+        .add_results(&[interface_type(ctx, interface)])
+        .build()
+        .expect("valid operation");
+    block.append(instance).result(0).unwrap().into()
+}
+
+/// Append `sv.modport.get` to `block`: the `modport` view of the `interface` instance `instance`,
+/// to connect to a child instance's modport port.
+pub fn modport<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, instance: Value<'c, '_>, interface: &str,
+                       modport: &str, location: Location<'c>) -> Value<'c, 'a> {
+    let get = OperationBuilder::new("sv.modport.get", location)
+        .add_operands(&[instance])
+        .add_attributes(&[(Identifier::new(ctx, "field"),
+                           Attribute::parse(ctx, &format!("@{modport}")).expect("valid symbol reference"))])
+        .add_results(&[modport_type(ctx, interface, modport)])
+        .build()
+        .expect("valid operation");
+    block.append(get).result(0).unwrap().into()
+}
+
+/// Append `sv.interface.signal.read` of `signal`, of type `r#type`, of the `interface` instance
+/// `instance` to `block`.
+pub fn read<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, instance: Value<'c, '_>, interface: &str, signal: &str,
+                    r#type: Type<'c>, location: Location<'c>) -> Value<'c, 'a> {
+    let read = OperationBuilder::new("sv.interface.signal.read", location)
+        .add_operands(&[instance])
+        .add_attributes(&[(Identifier::new(ctx, "signalName"), member(ctx, interface, signal))])
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    block.append(read).result(0).unwrap().into()
+}
+
+/// Append `sv.interface.signal.assign` to `block`, continuously driving `signal` of the
+/// `interface` instance `instance` with `value`.
+pub fn assign<'c>(ctx: &'c Context, block: &Block<'c>, instance: Value<'c, '_>, interface: &str, signal: &str,
+                  value: Value<'c, '_>, location: Location<'c>) {
+    let assign = OperationBuilder::new("sv.interface.signal.assign", location)
+        .add_operands(&[instance, value])
+        .add_attributes(&[(Identifier::new(ctx, "signalName"), member(ctx, interface, signal))])
+        .build()
+        .expect("valid operation");
+    block.append(assign);
+}
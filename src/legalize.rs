@@ -0,0 +1,160 @@
+//! Legalization of user provided names (from specs etc.) into SystemVerilog identifiers before
+//! they are used for ports, modules, and macros.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+static SIMPLE_IDENTIFIER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_$]*$").unwrap());
+static ILLEGAL_CHARACTERS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[^A-Za-z0-9_$]").unwrap());
+
+/// IEEE 1800-2017 reserved keywords.
+const KEYWORDS: &[&str] = &[
+    "accept_on", "alias", "always", "always_comb", "always_ff", "always_latch", "and", "assert",
+    "assign", "assume", "automatic", "before", "begin", "bind", "bins", "binsof", "bit", "break",
+    "buf", "bufif0", "bufif1", "byte", "case", "casex", "casez", "cell", "chandle", "checker",
+    "class", "clocking", "cmos", "config", "const", "constraint", "context", "continue", "cover",
+    "covergroup", "coverpoint", "cross", "deassign", "default", "defparam", "design", "disable",
+    "dist", "do", "edge", "else", "end", "endcase", "endchecker", "endclass", "endclocking",
+    "endconfig", "endfunction", "endgenerate", "endgroup", "endinterface", "endmodule",
+    "endpackage", "endprimitive", "endprogram", "endproperty", "endspecify", "endsequence",
+    "endtable", "endtask", "enum", "event", "eventually", "expect", "export", "extends", "extern",
+    "final", "first_match", "for", "force", "foreach", "forever", "fork", "forkjoin", "function",
+    "generate", "genvar", "global", "highz0", "highz1", "if", "iff", "ifnone", "ignore_bins",
+    "illegal_bins", "implements", "implies", "import", "incdir", "include", "initial", "inout",
+    "input", "inside", "instance", "int", "integer", "interconnect", "interface", "intersect",
+    "join", "join_any", "join_none", "large", "let", "liblist", "library", "local", "localparam",
+    "logic", "longint", "macromodule", "matches", "medium", "modport", "module", "nand",
+    "negedge", "nettype", "new", "nexttime", "nmos", "nor", "noshowcancelled", "not", "notif0",
+    "notif1", "null", "or", "output", "package", "packed", "parameter", "pmos", "posedge",
+    "primitive", "priority", "program", "property", "protected", "pull0", "pull1", "pulldown",
+    "pullup", "pulsestyle_ondetect", "pulsestyle_onevent", "pure", "rand", "randc", "randcase",
+    "randsequence", "rcmos", "real", "realtime", "ref", "reg", "reject_on", "release", "repeat",
+    "restrict", "return", "rnmos", "rpmos", "rtran", "rtranif0", "rtranif1", "s_always",
+    "s_eventually", "s_nexttime", "s_until", "s_until_with", "scalared", "sequence", "shortint",
+    "shortreal", "showcancelled", "signed", "small", "soft", "solve", "specify", "specparam",
+    "static", "string", "strong", "strong0", "strong1", "struct", "super", "supply0", "supply1",
+    "sync_accept_on", "sync_reject_on", "table", "tagged", "task", "this", "throughout", "time",
+    "timeprecision", "timeunit", "tran", "tranif0", "tranif1", "tri", "tri0", "tri1", "triand",
+    "trior", "trireg", "type", "typedef", "union", "unique", "unique0", "unsigned", "until",
+    "until_with", "untyped", "use", "uwire", "var", "vectored", "virtual", "void", "wait",
+    "wait_order", "wand", "weak", "weak0", "weak1", "while", "wildcard", "wire", "with", "within",
+    "wor", "xnor", "xor",
+];
+
+pub fn is_keyword(name: &str) -> bool {
+    KEYWORDS.contains(&name)
+}
+
+pub fn is_legal(name: &str) -> bool {
+    SIMPLE_IDENTIFIER.is_match(name) && !is_keyword(name)
+}
+
+/* Replace illegal characters with `_`, prefix names that don't start with a letter or `_`, and
+   suffix keywords with `_`. */
+fn escape(name: &str) -> String {
+    let mut legal = ILLEGAL_CHARACTERS.replace_all(name, "_").into_owned();
+    if !legal.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        legal.insert(0, '_');
+    }
+    if is_keyword(&legal) {
+        legal.push('_');
+    }
+    legal
+}
+
+/// A name [`Legalizer::legalize`] had to change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rename {
+    pub original: String,
+    pub legalized: String,
+}
+
+impl fmt::Display for Rename {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` -> `{}`", self.original, self.legalized)
+    }
+}
+
+/// Legalizes names within one namespace (a design's modules, a module's ports, ...), keeping
+/// escaped names from colliding with each other and recording every rename it applied.
+#[derive(Clone, Debug, Default)]
+pub struct Legalizer {
+    /// Legal name -> the original it was produced from.
+    names: HashMap<String, String>,
+    renames: Vec<Rename>,
+}
+
+impl Legalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The legal identifier for `name`. Legalizing the same name again gives the same result.
+    pub fn legalize(&mut self, name: &str) -> String {
+        if let Some(rename) = self.renames.iter().find(|rename| rename.original == name) {
+            return rename.legalized.clone();
+        }
+        let base = if is_legal(name) { name.to_string() } else { escape(name) };
+        let mut legal = base.clone();
+        let mut suffix = 0;
+        while self.names.get(&legal).is_some_and(|original| original != name) {
+            suffix += 1;
+            legal = format!("{base}_{suffix}");
+        }
+        self.names.insert(legal.clone(), name.to_string());
+        if legal != name {
+            self.renames.push(Rename { original: name.to_string(), legalized: legal.clone() });
+        }
+        legal
+    }
+
+    /// Whether `name` was handed out, or reserved, in this namespace.
+    pub fn is_taken(&self, name: &str) -> bool {
+        self.names.contains_key(name)
+    }
+
+    /// Take `name`, already legal and in use, so it isn't handed out for another name.
+    pub fn reserve(&mut self, name: &str) {
+        self.names.entry(name.to_string()).or_insert_with(|| name.to_string());
+    }
+
+    /// The renames applied so far, in the order they were made.
+    pub fn renames(&self) -> &[Rename] {
+        &self.renames
+    }
+}
+
+/// A team's naming conventions, applied by the builders that name modules, macros, and
+/// instances (see [`crate::Design::set_naming_policy`]). The default leaves names as given.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NamingPolicy {
+    /// Prepended to every module name, e.g. `acme_`.
+    pub module_prefix: String,
+    /// Appended to every module name.
+    pub module_suffix: String,
+    /// Uppercase macro names, the usual convention for `` `define``s.
+    pub uppercase_macros: bool,
+    /// Names for generated instances, with `{module}` replaced by the instantiated module and
+    /// `{n}` by a count of its instances so far, e.g. `u_{module}_{n}`. `{module}_{n}` when unset.
+    pub instance_template: Option<String>,
+}
+
+impl NamingPolicy {
+    pub fn module_name(&self, name: &str) -> String {
+        format!("{}{name}{}", self.module_prefix, self.module_suffix)
+    }
+
+    pub fn macro_name(&self, name: &str) -> String {
+        if self.uppercase_macros { name.to_uppercase() } else { name.to_string() }
+    }
+
+    /// The name of instance `n`, counting from 0, of `module`.
+    pub fn instance_name(&self, module: &str, n: usize) -> String {
+        self.instance_template.as_deref().unwrap_or("{module}_{n}")
+            .replace("{module}", module)
+            .replace("{n}", &n.to_string())
+    }
+}
@@ -0,0 +1,212 @@
+//! Generators for CIRCT `hw` and `sv` dialect operations built on melior.
+//!
+//! The demo in `main.rs` uses these to build a small design; they can also be used from other
+//! crates that want to emit SV dialect IR.
+
+// Lets the code `#[derive(Ports)]` generates name this crate from within it.
+extern crate self as circt_sv_basic;
+
+pub mod always;
+pub mod arith;
+pub mod array;
+pub mod attributes;
+pub mod batch;
+pub mod bits;
+pub mod builder;
+pub mod cache;
+pub mod capi;
+pub mod cast;
+pub mod checker;
+pub mod compare;
+pub mod components;
+pub mod constant;
+pub mod constraints;
+pub mod dataflow;
+pub mod decl;
+pub mod design;
+pub mod diagnostics;
+pub mod dpi;
+pub mod encode;
+pub mod error;
+pub mod esi;
+pub mod export;
+pub mod force;
+pub mod hierarchy;
+pub mod ifdef;
+pub mod interface;
+pub mod legalize;
+pub mod lint;
+pub mod locations;
+pub mod lowering;
+pub mod macros;
+pub mod mux;
+pub mod observe;
+pub mod om;
+pub mod param;
+pub mod pipeline;
+pub mod placement;
+pub mod plusargs;
+pub mod ports;
+pub mod preamble;
+pub mod prelude;
+pub mod reduce;
+pub mod register;
+pub mod rust_api;
+pub mod scheduled;
+pub mod spec;
+pub mod stats;
+pub mod tristate;
+pub mod verif;
+pub mod verilator;
+pub mod version;
+pub mod width;
+pub mod xmr;
+
+pub use design::{Design, PrintOptions, TopLevelOrder};
+/// `#[derive(Ports)]`, implementing [`ports::PortBundle`] for a struct of ports.
+pub use circt_sv_basic_derive::Ports;
+pub use error::Error;
+pub use version::version;
+
+/// The melior and mlir-sys this crate is built against, for the APIs [`prelude`] doesn't cover.
+pub use melior;
+pub use mlir_sys;
+
+use melior::dialect::DialectHandle;
+use melior::ir::attribute::StringAttribute;
+use melior::ir::operation::{OperationLike, OperationRef};
+use melior::ir::{Block, BlockLike, Operation, Region, RegionLike, Type, TypeLike};
+use melior::Context;
+
+/// A `Location` for the Rust source line that invoked the macro. `here!(ctx, fused other)` fuses
+/// it with `other`, the input line the operation is generated from, say, and `here!(ctx, named
+/// "name")` puts it under a name; see [`locations`].
+#[macro_export]
+macro_rules! here {
+    ($c:ident) => {
+        $crate::prelude::Location::new(&$c, file!(), line!() as usize, column!() as usize)
+    };
+    ($c:ident, fused $other:expr) => {
+        $crate::locations::fused(&$c, &[$other, $crate::here!($c)])
+    };
+    ($c:ident, named $name:expr) => {
+        $crate::locations::named(&$c, $name, $crate::here!($c))
+    };
+}
+
+/// Append a module of integer ports to a [`Design`], for small modules. Inputs are bound to
+/// variables of their names, and `$m` is an [`IrBuilder`](builder::IrBuilder) for the body,
+/// which returns `Ok` of the output values in order:
+///
+/// ```ignore
+/// module! {
+///     design: &design,
+///     name: test1,
+///     inputs: { clk: 1, d: 8 },
+///     outputs: { q: 8 },
+///     body: |m| {
+///         let q = register::register(&design, m.block(), "q", i8, clk, None, None, |_| Ok(d), m.location())?;
+///         Ok(vec![q])
+///     }
+/// }?;
+/// ```
+#[macro_export]
+macro_rules! module {
+    {
+        design: $design:expr,
+        name: $name:ident,
+        inputs: { $($input:ident: $input_width:expr),* $(,)? },
+        outputs: { $($output:ident: $output_width:expr),* $(,)? },
+        body: |$m:ident| $body:block $(,)?
+    } => {{
+        let design: &$crate::Design = $design;
+        let ctx = design.context();
+        let location = $crate::prelude::Location::new(ctx, file!(), line!() as usize, column!() as usize);
+        let integer = |width: u32| -> $crate::prelude::Type { $crate::prelude::IntegerType::new(ctx, width).into() };
+        let ports = [
+            $((stringify!($input), $crate::ports::PortDirection::Input, integer($input_width)),)*
+            $((stringify!($output), $crate::ports::PortDirection::Output, integer($output_width)),)*
+        ];
+        $crate::ports::module(design, stringify!($name), &ports, |block| {
+            let $m = $crate::builder::IrBuilder::new(ctx, block, location);
+            let mut arguments = (0..).map(|index| -> $crate::prelude::Value {
+                $crate::prelude::BlockLike::argument(block, index).unwrap().into()
+            });
+            $(let $input = arguments.next().unwrap();)*
+            let _ = &mut arguments;
+            $body
+        }, location)
+    }};
+}
+
+/* The dialects `load_dialects` loads, each with one of its operations, by which `version` tells
+   whether a context has the dialect without loading it. */
+pub(crate) const DIALECTS: &[(fn() -> DialectHandle, &str)] = &[
+    (DialectHandle::hw, "hw.module"),
+    (DialectHandle::sv, "sv.wire"),
+    (DialectHandle::comb, "comb.add"),
+    (DialectHandle::om, "om.class"),
+    (DialectHandle::verif, "verif.assert"),
+    (DialectHandle::seq, "seq.compreg"),
+    (DialectHandle::pipeline, "pipeline.scheduled"),
+    (DialectHandle::handshake, "handshake.func"),
+    (DialectHandle::dc, "dc.buffer"),
+    (DialectHandle::esi, "esi.null"),
+    (DialectHandle::msft, "msft.instance.hierarchy"),
+    (DialectHandle::debug, "dbg.variable"),
+];
+
+/// Load the dialects the generators in this crate emit.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn load_dialects(ctx: &Context) {
+    for (handle, _) in DIALECTS {
+        handle().load_dialect(ctx);
+    }
+}
+
+/// The bit width of an integer, struct, or array type, or `None` if it isn't fixed.
+pub fn bit_width(r#type: Type) -> Option<u64> {
+    let width = unsafe { mlir_sys::hwGetBitWidth(r#type.to_raw()) };
+    u64::try_from(width).ok()
+}
+
+/* A single block region, filled in by `build`. */
+pub(crate) fn region<'c>(build: impl FnOnce(&Block<'c>)) -> Region<'c> {
+    let block = Block::new(&[]);
+    build(&block);
+    let region = Region::new();
+    region.append_block(block);
+    region
+}
+
+/* The operations in `block`, in order. */
+pub(crate) fn operations<'c, 'a>(block: &'a Block<'c>) -> Vec<OperationRef<'c, 'a>> {
+    let mut operations = Vec::new();
+    let mut next = block.first_operation();
+    while let Some(operation) = next {
+        next = operation.next_in_block();
+        operations.push(operation);
+    }
+    operations
+}
+
+/* Call `visit` on every operation nested in `operation`'s regions, outer operations first. */
+pub(crate) fn walk(operation: &Operation, visit: &mut impl FnMut(&Operation)) {
+    for index in 0..operation.region_count() {
+        let Ok(region) = operation.region(index) else { continue };
+        let mut next = region.first_block();
+        while let Some(block) = next {
+            for nested in operations(&block) {
+                visit(&nested);
+                walk(&nested, visit);
+            }
+            next = block.next_in_region();
+        }
+    }
+}
+
+/* The `sym_name` of a symbol operation like `hw.module` or `sv.macro.decl`. */
+pub(crate) fn symbol_name(operation: &Operation) -> Option<String> {
+    let name = operation.attribute("sym_name").ok()?;
+    StringAttribute::try_from(name).ok().map(|name| name.value().to_string())
+}
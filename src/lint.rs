@@ -0,0 +1,369 @@
+//! Structural checks of a built [`Design`] for mistakes the verifier accepts but downstream tools
+//! reject or mis-simulate, reported with signal names before the design is emitted.
+//!
+//! ```ignore
+//! design.check()?;
+//! lint::check_combinational_loops(&design)?;
+//! lint::check_multiple_drivers(&design)?;
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+
+use melior::ir::attribute::{ArrayAttribute, FlatSymbolRefAttribute, StringAttribute};
+use melior::ir::operation::{OperationLike, OperationRef};
+use melior::ir::value::{BlockArgument, OperationResult};
+use melior::ir::{Operation, Value, ValueLike};
+
+use crate::ports::{find_module, module_ports, PortDirection};
+use crate::{operations, symbol_name, walk, Design, Error};
+
+/// A wire or inout driven from more than one place.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultipleDrivers {
+    pub module: String,
+    pub signal: String,
+    /// The location of each driver: a continuous assignment, or the first assignment to the signal
+    /// in each procedural block.
+    pub drivers: Vec<String>,
+}
+
+/// A cycle of combinational logic in a module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CombinationalLoop {
+    pub module: String,
+    /// The signals around the loop, ending with the first.
+    pub path: Vec<String>,
+}
+
+/* Ops other than the `comb` and `hw` dialects' whose results follow their operands combinationally. */
+const COMBINATIONAL_OPS: &[&str] = &[
+    "sv.read_inout", "sv.array_index_inout", "sv.struct_field_inout", "sv.indexed_part_select_inout",
+    "seq.to_clock", "seq.from_clock",
+];
+
+/* The ops whose procedural assignments are combinational. */
+const COMBINATIONAL_BLOCKS: &[&str] = &["sv.alwayscomb"];
+
+/* The ops whose procedural assignments only take effect on an event. */
+const SEQUENTIAL_BLOCKS: &[&str] = &["sv.always", "sv.alwaysff", "sv.initial"];
+
+fn name_of(operation: &Operation) -> &str {
+    operation.name().as_string_ref().as_str().unwrap_or_default()
+}
+
+/* The module an `hw.instance` instantiates. */
+fn instantiated(operation: &Operation) -> Option<String> {
+    let module = operation.attribute("moduleName").ok()?;
+    FlatSymbolRefAttribute::try_from(module).ok().map(|module| module.value().to_string())
+}
+
+fn key(value: Value) -> usize {
+    value.to_raw().ptr as usize
+}
+
+/* The `always`, `always_comb`, ... block the procedural assignment `operation` is in. */
+fn procedural_block<'c, 'a>(operation: &'a Operation<'c>) -> Option<OperationRef<'c, 'a>> {
+    let mut parent = operation.parent_operation();
+    while let Some(operation) = parent {
+        let name = name_of(&operation);
+        if COMBINATIONAL_BLOCKS.contains(&name) || SEQUENTIAL_BLOCKS.contains(&name) {
+            return Some(operation);
+        }
+        if name == "hw.module" {
+            return None;
+        }
+        parent = operation.parent_operation();
+    }
+    None
+}
+
+/* Whether the procedural assignment `operation` is in an `always_comb` rather than an edge
+   triggered block. */
+fn in_combinational_block(operation: &Operation) -> bool {
+    procedural_block(operation).is_some_and(|block| COMBINATIONAL_BLOCKS.contains(&name_of(&block)))
+}
+
+/* Whether `value` is `'z` or a mux selecting `'z`, one of several drivers of a tristate net. */
+fn is_high_impedance(value: Value) -> bool {
+    let Ok(result) = OperationResult::try_from(value) else { return false };
+    let operation = result.owner();
+    match name_of(&operation) {
+        "sv.constantZ" => true,
+        "comb.mux" => (1..operation.operand_count()).filter_map(|index| operation.operand(index).ok())
+            .any(is_high_impedance),
+        _ => false,
+    }
+}
+
+/* The combinational dependencies between the values of a module: value -> the values it follows. */
+#[derive(Default)]
+struct Graph {
+    edges: HashMap<usize, Vec<usize>>,
+    names: HashMap<usize, String>,
+}
+
+impl Graph {
+    fn edge(&mut self, from: Value, to: Value) {
+        self.edges.entry(key(from)).or_default().push(key(to));
+    }
+
+    /* The values reachable from `start`, including it. */
+    fn reachable(&self, start: usize) -> BTreeSet<usize> {
+        let mut reachable = BTreeSet::new();
+        let mut pending = vec![start];
+        while let Some(value) = pending.pop() {
+            if reachable.insert(value) {
+                pending.extend(self.edges.get(&value).into_iter().flatten());
+            }
+        }
+        reachable
+    }
+}
+
+/* A name for `value` in a report: the name of the port, wire, or instance output it is, the name
+   of the wire a `sv.read_inout` reads, or the op and location that computes it. */
+fn signal_name(value: Value, inputs: &[String]) -> String {
+    if let Ok(argument) = BlockArgument::try_from(value) {
+        return inputs.get(argument.argument_number()).cloned().unwrap_or_else(|| "<argument>".to_string());
+    }
+    let Ok(result) = OperationResult::try_from(value) else { return "<value>".to_string() };
+    let operation = result.owner();
+    let string = |name: &str| operation.attribute(name).ok()
+        .and_then(|attribute| StringAttribute::try_from(attribute).ok())
+        .map(|name| name.value().to_string());
+    if name_of(&operation) == "hw.instance" {
+        let port = operation.attribute("resultNames").ok()
+            .and_then(|names| ArrayAttribute::try_from(names).ok())
+            .and_then(|names| names.element(result.result_number()).ok())
+            .and_then(|name| StringAttribute::try_from(name).ok())
+            .map(|name| name.value().to_string())
+            .unwrap_or_else(|| result.result_number().to_string());
+        return format!("{}.{port}", string("instanceName").unwrap_or_default());
+    }
+    if name_of(&operation) == "sv.read_inout" {
+        if let Ok(inout) = operation.operand(0) {
+            return signal_name(inout, inputs);
+        }
+    }
+    string("name").unwrap_or_else(|| format!("{} at {}", name_of(&operation), operation.location()))
+}
+
+/* The combinational graph of `module`. `summaries` gives, for each module instantiated, the
+   inputs each output follows combinationally. */
+fn graph(module: &Operation, summaries: &HashMap<String, Vec<Vec<usize>>>) -> Graph {
+    let inputs: Vec<String> = module_ports(module).into_iter()
+        .filter(|port| port.direction != PortDirection::Output)
+        .map(|port| port.name)
+        .collect();
+    let mut graph = Graph::default();
+    let name = |graph: &mut Graph, value: Value| {
+        graph.names.entry(key(value)).or_insert_with(|| signal_name(value, &inputs));
+    };
+    walk(module, &mut |operation| {
+        let op = name_of(operation);
+        let operands: Vec<Value> = (0..operation.operand_count()).filter_map(|index| operation.operand(index).ok())
+            .collect();
+        let results: Vec<Value> = (0..operation.result_count())
+            .filter_map(|index| operation.result(index).ok().map(Into::into))
+            .collect();
+        operands.iter().chain(&results).for_each(|value| name(&mut graph, *value));
+        match op {
+            "hw.instance" => {
+                let module = instantiated(operation).unwrap_or_default();
+                for (output, inputs) in summaries.get(&module).into_iter().flatten().enumerate() {
+                    for input in inputs {
+                        if let (Some(result), Some(operand)) = (results.get(output), operands.get(*input)) {
+                            graph.edge(*result, *operand);
+                        }
+                    }
+                }
+            }
+            "sv.assign" => graph.edge(operands[0], operands[1]),
+            "sv.bpassign" | "sv.passign" if in_combinational_block(operation) => graph.edge(operands[0], operands[1]),
+            "hw.output" => {}
+            _ if op.starts_with("comb.") || op.starts_with("hw.") || COMBINATIONAL_OPS.contains(&op) => {
+                for result in &results {
+                    for operand in &operands {
+                        graph.edge(*result, *operand);
+                    }
+                }
+            }
+            _ => {}
+        }
+    });
+    graph
+}
+
+/* For each output of `name`, the inputs it follows combinationally, computing the summaries of the
+   modules it instantiates first. External modules are assumed to register their outputs. */
+fn summarize(design: &Design, name: &str, summaries: &mut HashMap<String, Vec<Vec<usize>>>) {
+    if summaries.contains_key(name) {
+        return;
+    }
+    summaries.insert(name.to_string(), Vec::new());
+    let Some(module) = find_module(design, name).filter(|module| name_of(module) == "hw.module") else { return };
+    walk(&module, &mut |operation| {
+        if name_of(operation) == "hw.instance" {
+            if let Some(child) = instantiated(operation) {
+                summarize(design, &child, summaries);
+            }
+        }
+    });
+    let graph = graph(&module, summaries);
+    let body = module.region(0).ok().and_then(|region| region.first_block());
+    let Some(body) = body else { return };
+    let arguments: Vec<usize> = (0..body.argument_count())
+        .filter_map(|index| body.argument(index).ok().map(|argument| key(argument.into())))
+        .collect();
+    let output = operations(&body).into_iter().find(|operation| name_of(operation) == "hw.output");
+    let summary = output.map(|output| {
+        (0..output.operand_count()).filter_map(|index| output.operand(index).ok()).map(|value| {
+            let reachable = graph.reachable(key(value));
+            arguments.iter().enumerate()
+                .filter(|(_, argument)| reachable.contains(argument))
+                .map(|(index, _)| index)
+                .collect()
+        }).collect()
+    }).unwrap_or_default();
+    summaries.insert(name.to_string(), summary);
+}
+
+/* The cycles in `graph`, each reported once from the value the search reached it at. */
+fn cycles(graph: &Graph) -> Vec<Vec<String>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        OnStack,
+        Done,
+    }
+    let mut state: HashMap<usize, State> = HashMap::new();
+    let mut cycles = Vec::new();
+    let mut starts: Vec<usize> = graph.edges.keys().copied().collect();
+    starts.sort_unstable();
+    for start in starts {
+        if state.contains_key(&start) {
+            continue;
+        }
+        // Iterative depth first search: the path so far and the next edge to follow from each
+        let mut stack: Vec<(usize, usize)> = vec![(start, 0)];
+        state.insert(start, State::OnStack);
+        while let Some((value, edge)) = stack.last_mut() {
+            let value = *value;
+            let Some(&next) = graph.edges.get(&value).and_then(|edges| edges.get(*edge)) else {
+                state.insert(value, State::Done);
+                stack.pop();
+                continue;
+            };
+            *edge += 1;
+            match state.get(&next) {
+                Some(State::OnStack) => {
+                    let from = stack.iter().position(|(value, _)| *value == next).unwrap_or_default();
+                    let mut path: Vec<String> = stack[from..].iter().rev()
+                        .map(|(value, _)| graph.names.get(value).cloned().unwrap_or_default())
+                        .collect();
+                    path.push(path[0].clone());
+                    path.dedup();
+                    cycles.push(path);
+                }
+                Some(State::Done) => {}
+                None => {
+                    state.insert(next, State::OnStack);
+                    stack.push((next, 0));
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// The combinational loops in the modules of `design`, following combinational paths through the
+/// ports of the modules it instantiates: `comb` and `hw` ops, continuous assignments, and
+/// assignments in `always_comb` blocks. Registers and edge triggered blocks break a loop.
+pub fn combinational_loops(design: &Design) -> Vec<CombinationalLoop> {
+    let mut summaries = HashMap::new();
+    let mut loops = Vec::new();
+    for operation in operations(&design.body()) {
+        if name_of(&operation) != "hw.module" {
+            continue;
+        }
+        let Some(module) = symbol_name(&operation) else { continue };
+        summarize(design, &module, &mut summaries);
+        for path in cycles(&graph(&operation, &summaries)) {
+            loops.push(CombinationalLoop { module: module.clone(), path });
+        }
+    }
+    loops
+}
+
+/// [`combinational_loops`], failing with the first loop found.
+pub fn check_combinational_loops(design: &Design) -> Result<(), Error> {
+    match combinational_loops(design).into_iter().next() {
+        Some(found) => Err(Error::CombinationalLoop { module: found.module, path: found.path }),
+        None => Ok(()),
+    }
+}
+
+/* The drivers of each signal in `module` driven more than once. */
+fn module_multiple_drivers(module: &Operation) -> Vec<(String, Vec<String>)> {
+    let inputs: Vec<String> = module_ports(module).into_iter()
+        .filter(|port| port.direction != PortDirection::Output)
+        .map(|port| port.name)
+        .collect();
+    // Signal -> (the assignment or procedural block driving it, the driver's location), in order
+    let mut drivers: Vec<(usize, Value, Vec<(usize, String)>)> = Vec::new();
+    walk(module, &mut |operation| {
+        let driver = match name_of(operation) {
+            "sv.assign" => Some(operation.to_raw().ptr as usize),
+            "sv.bpassign" | "sv.passign" => procedural_block(operation).map(|block| block.to_raw().ptr as usize),
+            _ => None,
+        };
+        let (Some(driver), Ok(dest), Ok(source)) = (driver, operation.operand(0), operation.operand(1)) else {
+            return;
+        };
+        if is_high_impedance(source) {
+            return;
+        }
+        let index = match drivers.iter().position(|(signal, _, _)| *signal == key(dest)) {
+            Some(index) => index,
+            None => {
+                drivers.push((key(dest), dest, Vec::new()));
+                drivers.len() - 1
+            }
+        };
+        let found = &mut drivers[index].2;
+        if !found.iter().any(|(existing, _)| *existing == driver) {
+            found.push((driver, operation.location().to_string()));
+        }
+    });
+    drivers.into_iter()
+        .filter(|(_, _, found)| found.len() > 1)
+        .map(|(_, signal, found)| {
+            (signal_name(signal, &inputs), found.into_iter().map(|(_, location)| location).collect())
+        })
+        .collect()
+}
+
+/// The wires, logic, registers, and inout ports in `design` assigned from more than one
+/// continuous assignment or procedural block. Assignments of `'z`, or of a mux that can select it,
+/// are tristate drivers and don't count; nor do assignments to elements of an array or struct.
+pub fn multiple_drivers(design: &Design) -> Vec<MultipleDrivers> {
+    let mut found = Vec::new();
+    for operation in operations(&design.body()) {
+        if name_of(&operation) != "hw.module" {
+            continue;
+        }
+        let Some(module) = symbol_name(&operation) else { continue };
+        for (signal, drivers) in module_multiple_drivers(&operation) {
+            found.push(MultipleDrivers { module: module.clone(), signal, drivers });
+        }
+    }
+    found
+}
+
+/// [`multiple_drivers`], failing with the first signal found.
+pub fn check_multiple_drivers(design: &Design) -> Result<(), Error> {
+    match multiple_drivers(design).into_iter().next() {
+        Some(found) => Err(Error::MultipleDrivers { signal: format!("{}::{}", found.module, found.signal),
+                                                    drivers: found.drivers }),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,28 @@
+//! Locations giving an operation more than one provenance: the line of a spec or other input it
+//! was generated from, fused with the Rust line that built it, or a name for the signal or step
+//! it belongs to. Diagnostics and `--print-locations` output show every part.
+//!
+//! ```ignore
+//! let from_spec = Location::new(&ctx, "adder.json", 3, 5);
+//! let location = here!(ctx, fused from_spec); // loc(fused["adder.json":3:5, "gen.rs":42:20])
+//! let location = here!(ctx, named "sum");      // loc("sum"("gen.rs":43:20))
+//! ```
+
+use melior::ir::Location;
+use melior::Context;
+
+/// One location for `locations` together, `loc(fused[a, b])`, such as the spec line an
+/// operation comes from and the builder line that made it.
+pub fn fused<'c>(ctx: &'c Context, locations: &[Location<'c>]) -> Location<'c> {
+    let locations: Vec<_> = locations.iter().map(|location| location.to_raw()).collect();
+    let no_metadata = mlir_sys::MlirAttribute { ptr: std::ptr::null() };
+    unsafe {
+        Location::from_raw(mlir_sys::mlirLocationFusedGet(ctx.to_raw(), locations.len() as isize, locations.as_ptr(),
+                                                          no_metadata))
+    }
+}
+
+/// `location` under `name`, `loc("name"(location))`.
+pub fn named<'c>(ctx: &'c Context, name: &str, location: Location<'c>) -> Location<'c> {
+    Location::name(ctx, name, location)
+}
@@ -0,0 +1,200 @@
+//! ExportVerilog lowering options, set through the `circt.loweringOptions` attribute on the top
+//! level module.
+//!
+//! ```ignore
+//! let options = LoweringOptions { disallow_packed_arrays: true, emitted_line_length: Some(100), ..Default::default() };
+//! options.apply(&design);
+//! ```
+
+use std::fmt;
+
+use melior::ir::attribute::StringAttribute;
+use melior::ir::Attribute;
+use melior::Context;
+
+use crate::Design;
+
+/// The attribute ExportVerilog reads its options from.
+pub const LOWERING_OPTIONS_ATTRIBUTE: &str = "circt.loweringOptions";
+
+/// The language generated code should be compatible with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LanguageStandard {
+    #[default]
+    SystemVerilog,
+    /// Verilog-2001: `always` instead of `always_ff`/`always_comb`, `reg` instead of `logic`, and
+    /// no packed arrays, packed struct assignments, or local variables.
+    Verilog2001,
+}
+
+/// How ExportVerilog decides to spill expressions into wires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireSpillingHeuristic {
+    /// Spill large terms that have a name hint into named wires.
+    SpillLargeTermsWithNamehints,
+}
+
+/// How source locations are printed in the emitted Verilog.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocationInfoStyle {
+    Plain,
+    WrapInAtSquareBracket,
+    None,
+}
+
+/// The options in CIRCT's `LoweringOptions`. The defaults match ExportVerilog's defaults, so
+/// only options that are changed appear in the attribute.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoweringOptions {
+    /// Emit `always @(*)` instead of `always_comb`.
+    pub no_always_comb: bool,
+    /// Allow expressions in event controls, e.g. `always @(posedge a & b)`.
+    pub expr_in_event_control: bool,
+    /// Lower packed arrays to flattened integers.
+    pub disallow_packed_arrays: bool,
+    pub disallow_packed_struct_assignments: bool,
+    /// Don't declare `automatic logic` temporaries inside procedural blocks.
+    pub disallow_local_variables: bool,
+    /// Emit one port declaration per line.
+    pub disallow_port_decl_sharing: bool,
+    pub disallow_expression_inlining_in_ports: bool,
+    pub disallow_mux_inlining: bool,
+    /// Add labels to assertions.
+    pub verif_labels: bool,
+    pub explicit_bitcast: bool,
+    pub emit_replicated_ops_to_header: bool,
+    pub print_debug_info: bool,
+    pub emit_wire_in_ports: bool,
+    pub emit_bind_comments: bool,
+    pub omit_version_comment: bool,
+    pub case_insensitive_keywords: bool,
+    pub fix_up_empty_modules: bool,
+    pub mitigate_vivado_array_index_const_prop_bug: bool,
+    /// Attach the locations of the emitted Verilog to the IR, needed for HGLDD debug info.
+    pub emit_verilog_locations: bool,
+    /// Target line length, 90 when unset.
+    pub emitted_line_length: Option<u32>,
+    pub maximum_number_of_terms_per_expression: Option<u32>,
+    pub location_info_style: Option<LocationInfoStyle>,
+    pub wire_spilling_heuristic: Option<WireSpillingHeuristic>,
+    pub wire_spilling_namehint_term_limit: Option<u32>,
+}
+
+impl LoweringOptions {
+    /// The options ExportVerilog needs to stay within `standard`.
+    pub fn for_standard(standard: LanguageStandard) -> Self {
+        match standard {
+            LanguageStandard::SystemVerilog => Self::default(),
+            LanguageStandard::Verilog2001 => Self {
+                no_always_comb: true,
+                disallow_packed_arrays: true,
+                disallow_packed_struct_assignments: true,
+                disallow_local_variables: true,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Set the `circt.loweringOptions` attribute on `design`'s top level module.
+    pub fn apply(&self, design: &Design) {
+        design.set_lowering_options(self.clone());
+    }
+
+    pub(crate) fn attribute<'c>(&self, ctx: &'c Context) -> Attribute<'c> {
+        StringAttribute::new(ctx, &self.to_string()).into()
+    }
+}
+
+/// Formatting of the emitted Verilog, for code review policies on generated files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmissionStyle {
+    /// Lines ExportVerilog should try to stay within, 90 when unset.
+    pub max_line_length: Option<u32>,
+    /// Spaces per indentation level. ExportVerilog indents by 2; other widths are applied to its
+    /// output by [`EmissionStyle::reindent`].
+    pub indent_width: usize,
+    /// Share one declaration between ports of the same direction and type (`input a, b;`).
+    pub group_declarations: bool,
+}
+
+impl Default for EmissionStyle {
+    fn default() -> Self {
+        Self { max_line_length: None, indent_width: EXPORT_VERILOG_INDENT, group_declarations: true }
+    }
+}
+
+const EXPORT_VERILOG_INDENT: usize = 2;
+
+impl EmissionStyle {
+    /// Update `options` with the parts of this style ExportVerilog implements itself.
+    pub fn configure(&self, options: &mut LoweringOptions) {
+        options.emitted_line_length = self.max_line_length;
+        options.disallow_port_decl_sharing = !self.group_declarations;
+    }
+
+    /// Rewrite the leading indentation of ExportVerilog output to `indent_width` spaces per level.
+    pub fn reindent(&self, verilog: &str) -> String {
+        if self.indent_width == EXPORT_VERILOG_INDENT {
+            return verilog.to_string();
+        }
+        verilog.lines().map(|line| {
+            let text = line.trim_start_matches(' ');
+            let leading = line.len() - text.len();
+            let levels = leading / EXPORT_VERILOG_INDENT;
+            let extra = leading % EXPORT_VERILOG_INDENT;
+            format!("{}{}\n", " ".repeat(levels * self.indent_width + extra), text)
+        }).collect()
+    }
+}
+
+/// The comma separated form ExportVerilog parses, e.g. `disallowPackedArrays,emittedLineLength=100`.
+impl fmt::Display for LoweringOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (self.no_always_comb, "noAlwaysComb"),
+            (self.expr_in_event_control, "exprInEventControl"),
+            (self.disallow_packed_arrays, "disallowPackedArrays"),
+            (self.disallow_packed_struct_assignments, "disallowPackedStructAssignments"),
+            (self.disallow_local_variables, "disallowLocalVariables"),
+            (self.disallow_port_decl_sharing, "disallowPortDeclSharing"),
+            (self.disallow_expression_inlining_in_ports, "disallowExpressionInliningInPorts"),
+            (self.disallow_mux_inlining, "disallowMuxInlining"),
+            (self.verif_labels, "verifLabels"),
+            (self.explicit_bitcast, "explicitBitcast"),
+            (self.emit_replicated_ops_to_header, "emitReplicatedOpsToHeader"),
+            (self.print_debug_info, "printDebugInfo"),
+            (self.emit_wire_in_ports, "emitWireInPorts"),
+            (self.emit_bind_comments, "emitBindComments"),
+            (self.omit_version_comment, "omitVersionComment"),
+            (self.case_insensitive_keywords, "caseInsensitiveKeywords"),
+            (self.fix_up_empty_modules, "fixUpEmptyModules"),
+            (self.mitigate_vivado_array_index_const_prop_bug, "mitigateVivadoArrayIndexConstPropBug"),
+            (self.emit_verilog_locations, "emitVerilogLocations"),
+        ];
+        let mut options: Vec<String> = flags.iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| name.to_string())
+            .collect();
+        if let Some(length) = self.emitted_line_length {
+            options.push(format!("emittedLineLength={length}"));
+        }
+        if let Some(terms) = self.maximum_number_of_terms_per_expression {
+            options.push(format!("maximumNumberOfTermsPerExpression={terms}"));
+        }
+        if let Some(style) = self.location_info_style {
+            let style = match style {
+                LocationInfoStyle::Plain => "plain",
+                LocationInfoStyle::WrapInAtSquareBracket => "wrapInAtSquareBracket",
+                LocationInfoStyle::None => "none",
+            };
+            options.push(format!("locationInfoStyle={style}"));
+        }
+        if let Some(WireSpillingHeuristic::SpillLargeTermsWithNamehints) = self.wire_spilling_heuristic {
+            options.push("wireSpillingHeuristic=spillLargeTermsWithNamehints".to_string());
+        }
+        if let Some(limit) = self.wire_spilling_namehint_term_limit {
+            options.push(format!("wireSpillingNamehintTermLimit={limit}"));
+        }
+        write!(f, "{}", options.join(","))
+    }
+}
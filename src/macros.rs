@@ -0,0 +1,288 @@
+//! Well known macros referenced by firtool generated Verilog, and the prelude that declares and
+//! defines them, and references to macros, with arguments for function-like ones.
+
+use std::fmt;
+
+use melior::dialect::ods::sv;
+use melior::ir::attribute::{FlatSymbolRefAttribute, StringAttribute};
+use melior::ir::operation::{OperationBuilder, OperationLike};
+use melior::ir::{
+    Attribute, AttributeLike, Block, BlockLike, Identifier, Location, Operation, RegionLike, Type, Value,
+};
+use melior::Context;
+
+use circt_sv_attrs::sv::svMacroIdentAttrGetAlt2;
+
+use crate::diagnostics::Append;
+use crate::export::OutputFile;
+use crate::{ifdef, operations, walk, Design, Error};
+
+/// Macros firtool output relies on, to name them where a macro identifier is needed without
+/// spelling it out: [`KnownMacro::declared`] declares the macro on first use and returns its name.
+///
+/// The `*Cond` variants are the `_` suffixed gates generated code actually tests, which the
+/// prelude defines in terms of the user settable `User*Cond` macros.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KnownMacro {
+    /// `SYNTHESIS`, defined by synthesis tools to exclude simulation only code.
+    Synthesis,
+    /// `VERILATOR`, defined by Verilator.
+    Verilator,
+    /// `RANDOM`, an expression producing a 32-bit random value.
+    Random,
+    /// `PRINTF_COND_`, gates `$fwrite` calls.
+    PrintfCond,
+    /// `PRINTF_COND`, the user's extra printf gate.
+    UserPrintfCond,
+    /// `STOP_COND_`, gates `$fatal`/`$finish` calls.
+    StopCond,
+    /// `STOP_COND`, the user's extra stop gate.
+    UserStopCond,
+    /// `ASSERT_VERBOSE_COND_`, gates assertion failure messages.
+    AssertVerboseCond,
+    /// `ASSERT_VERBOSE_COND`, the user's extra assertion message gate.
+    UserAssertVerboseCond,
+    /// `ASSERT_ON`, enables guarded assertions, see [`crate::verif::guard_assertions`].
+    AssertOn,
+    /// `RANDOMIZE`, defined when any register or memory randomization is enabled.
+    Randomize,
+    /// `RANDOMIZE_REG_INIT`, randomizes register initial values.
+    RandomizeRegInit,
+    /// `RANDOMIZE_DELAY`, the delay before randomizing initial values.
+    RandomizeDelay,
+    /// `INIT_RANDOM`, user code injected into initializer blocks.
+    InitRandom,
+    /// `INIT_RANDOM_PROLOG_`, the prologue of register initializer blocks.
+    InitRandomProlog,
+    /// `ENABLE_INITIAL_REG_`, includes register initializers outside synthesis.
+    EnableInitialReg,
+    /// `FIRRTL_BEFORE_INITIAL`, user code before register initializers.
+    FirrtlBeforeInitial,
+    /// `FIRRTL_AFTER_INITIAL`, user code after register initializers.
+    FirrtlAfterInitial,
+}
+
+impl KnownMacro {
+    /// Every macro declared by [`declare_prelude`], in declaration order.
+    pub const PRELUDE: [KnownMacro; 9] = [
+        KnownMacro::Random,
+        KnownMacro::UserPrintfCond,
+        KnownMacro::PrintfCond,
+        KnownMacro::UserStopCond,
+        KnownMacro::StopCond,
+        KnownMacro::UserAssertVerboseCond,
+        KnownMacro::AssertVerboseCond,
+        KnownMacro::Synthesis,
+        KnownMacro::Verilator,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KnownMacro::Synthesis => "SYNTHESIS",
+            KnownMacro::Verilator => "VERILATOR",
+            KnownMacro::Random => "RANDOM",
+            KnownMacro::PrintfCond => "PRINTF_COND_",
+            KnownMacro::UserPrintfCond => "PRINTF_COND",
+            KnownMacro::StopCond => "STOP_COND_",
+            KnownMacro::UserStopCond => "STOP_COND",
+            KnownMacro::AssertVerboseCond => "ASSERT_VERBOSE_COND_",
+            KnownMacro::UserAssertVerboseCond => "ASSERT_VERBOSE_COND",
+            KnownMacro::AssertOn => "ASSERT_ON",
+            KnownMacro::Randomize => "RANDOMIZE",
+            KnownMacro::RandomizeRegInit => "RANDOMIZE_REG_INIT",
+            KnownMacro::RandomizeDelay => "RANDOMIZE_DELAY",
+            KnownMacro::InitRandom => "INIT_RANDOM",
+            KnownMacro::InitRandomProlog => "INIT_RANDOM_PROLOG_",
+            KnownMacro::EnableInitialReg => "ENABLE_INITIAL_REG_",
+            KnownMacro::FirrtlBeforeInitial => "FIRRTL_BEFORE_INITIAL",
+            KnownMacro::FirrtlAfterInitial => "FIRRTL_AFTER_INITIAL",
+        }
+    }
+
+    /// Declare the macro in `design` unless it already is, and return its name, for the functions
+    /// taking a macro by name:
+    ///
+    /// ```ignore
+    /// ifdef::ifdef(&design, &body, KnownMacro::Synthesis.declared(&design, here!(ctx)), ...)?;
+    /// ```
+    pub fn declared<'c>(self, design: &Design<'c>, location: Location<'c>) -> &'static str {
+        design.declare_macro(self.name(), location);
+        self.name()
+    }
+
+    /// For the gate macros, the user macro they defer to when it is defined.
+    fn user_gate(self) -> Option<KnownMacro> {
+        match self {
+            KnownMacro::PrintfCond => Some(KnownMacro::UserPrintfCond),
+            KnownMacro::StopCond => Some(KnownMacro::UserStopCond),
+            KnownMacro::AssertVerboseCond => Some(KnownMacro::UserAssertVerboseCond),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KnownMacro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An `sv::MacroIdentAttr` naming `name`, for `sv.ifdef` conditions.
+pub fn ident<'c>(ctx: &'c Context, name: &str) -> Attribute<'c> {
+    let ident = StringAttribute::new(ctx, name);
+    unsafe { Attribute::from_raw(svMacroIdentAttrGetAlt2(ident.to_raw())) }
+}
+
+/// Append `sv.macro.def @name "value"` to `block`.
+pub fn define<'c>(ctx: &'c Context, block: &Block<'c>, name: &str, value: &str, location: Location<'c>) {
+    let macro_def = sv::macro_def(ctx,
+                                  FlatSymbolRefAttribute::new(ctx, name),
+                                  StringAttribute::new(ctx, value),
+                                  location);
+    block.append(macro_def.into());
+}
+
+/* Check `name` is declared and takes `args` arguments. */
+fn check_reference(design: &Design, name: &str, args: usize) -> Result<(), Error> {
+    if !design.is_macro_declared(name) {
+        return Err(Error::UndeclaredMacro(name.to_string()));
+    }
+    let expected = design.macro_arity(name);
+    if args != expected {
+        return Err(Error::MacroArguments { name: name.to_string(), expected, found: args });
+    }
+    Ok(())
+}
+
+/// Append `` `name(args...) ``, an `sv.macro.ref.expr` of type `r#type`, to `block`. The macro must
+/// be declared with as many arguments as `args` has, see [`Design::declare_macro_with_args`].
+pub fn reference_expr<'c, 'a>(design: &Design<'c>, block: &'a Block<'c>, name: &str, args: &[Value<'c, '_>],
+                              r#type: Type<'c>, location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    check_reference(design, name, args.len())?;
+    let ctx = design.context();
+    let reference = sv::macro_ref_expr(ctx, r#type, args, FlatSymbolRefAttribute::new(ctx, &design.macro_name(name)),
+                                       location);
+    Ok(block.append(reference.into()).result(0).unwrap().into())
+}
+
+/// Append `` `name(args...); ``, an `sv.macro.ref` statement, to the procedural `block`, for macros
+/// expanding to statements like `` `PRINTF_COND_(clock, cond) ``.
+pub fn reference<'c>(design: &Design<'c>, block: &Block<'c>, name: &str, args: &[Value<'c, '_>],
+                     location: Location<'c>) -> Result<(), Error> {
+    check_reference(design, name, args.len())?;
+    let ctx = design.context();
+    let reference = OperationBuilder::new("sv.macro.ref", location)
+        .add_attributes(&[(Identifier::new(ctx, "macroName"),
+                           FlatSymbolRefAttribute::new(ctx, &design.macro_name(name)).into())])
+        .add_operands(args)
+        .build()
+        .expect("valid operation");
+    block.append(reference);
+    Ok(())
+}
+
+/// Declare every [`KnownMacro`] and define the gate macros the way firtool does:
+///
+/// ```verilog
+/// `ifndef PRINTF_COND_
+///   `ifdef PRINTF_COND
+///     `define PRINTF_COND_ (`PRINTF_COND)
+///   `else  // PRINTF_COND
+///     `define PRINTF_COND_ 1
+///   `endif // PRINTF_COND
+/// `endif // not def PRINTF_COND_
+/// ```
+pub fn declare_prelude<'c>(design: &Design<'c>, location: Location<'c>) {
+    let ctx = design.context();
+    for known in KnownMacro::PRELUDE {
+        design.declare_macro(known.name(), location);
+    }
+    let top = design.body();
+    for known in KnownMacro::PRELUDE {
+        let Some(user) = known.user_gate() else { continue };
+        let gate = ifdef::build(ctx, known.name(), |_| {}, Some(|else_: &Block<'c>| {
+            let user_defined = ifdef::build(ctx, user.name(),
+                                            |then| define(ctx, then, known.name(), &format!("(`{})", user.name()), location),
+                                            Some(|else_: &Block<'c>| define(ctx, else_, known.name(), "1", location)),
+                                            false, location);
+            else_.append(user_defined);
+        }), false, location);
+        top.append(gate);
+    }
+}
+
+/* Whether `operation` defines a macro, itself or in its regions. */
+fn defines_macro(operation: &Operation) -> bool {
+    let is_definition = |operation: &Operation| operation.name().as_string_ref().as_str() == Ok("sv.macro.def");
+    let mut found = is_definition(operation);
+    walk(operation, &mut |nested| found |= is_definition(nested));
+    found
+}
+
+/// The macro guarding the header `header` against being included twice, `DEFINES_SVH_` for
+/// `defines.svh`.
+pub fn include_guard(header: &str) -> String {
+    let file = header.rsplit('/').next().unwrap_or(header);
+    let mut guard: String = file.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    guard.push('_');
+    guard
+}
+
+/// Move the macro definitions at the top level of `design`, and the `` `ifdef `` guards around
+/// them, to the header `header`, and include it at the top of every other file. Without this
+/// split export repeats the definitions in each module's file. The header is left out of the file
+/// list, since the files including it pull it in, and its contents are wrapped in an include
+/// guard named by [`include_guard`]:
+///
+/// ```verilog
+/// `ifndef DEFINES_SVH_
+///   `define DEFINES_SVH_
+///   ...
+/// `endif // not def DEFINES_SVH_
+/// ```
+///
+/// Definitions added since an earlier call go in the same guard. Returns whether any definitions
+/// were moved.
+pub fn route_to_header(design: &Design, header: &str) -> bool {
+    let ctx = design.context();
+    let output_file = OutputFile { exclude_from_file_list: true, ..OutputFile::new(header) };
+    let body = design.body();
+    let moved: Vec<_> = operations(&body).into_iter().filter(|operation| {
+        let name = operation.name().as_string_ref().as_str().unwrap_or_default().to_string();
+        matches!(name.as_str(), "sv.macro.def" | "sv.ifdef") && defines_macro(operation)
+            && operation.attribute("output_file").is_err()
+    }).collect();
+    let Some(first) = moved.first() else { return false };
+
+    let attribute = output_file.attribute(ctx);
+    let existing = operations(&body).into_iter()
+        .find(|operation| operation.attribute("output_file").is_ok_and(|file| file == attribute));
+    let guarded = match existing {
+        Some(guarded) => guarded,
+        None => {
+            let location = design.module().as_operation().location();
+            let guard = include_guard(header);
+            design.declare_macro(&guard, location);
+            let guard = design.macro_name(&guard);
+            let define_guard = |else_: &Block| define(ctx, else_, &guard, "", location);
+            let ifdef = ifdef::build(ctx, &guard, |_| {}, Some(define_guard), false, location);
+            let guarded = body.insert_operation_before(*first, ifdef);
+            output_file.set(&guarded);
+            let include = sv::verbatim(ctx, &[], StringAttribute::new(ctx, &format!("`include \"{header}\"")),
+                                       location);
+            let top = body.first_operation().expect("the guard is in the body");
+            body.insert_operation_before(top, include.into());
+            guarded
+        }
+    };
+    let else_ = guarded.region(1).ok().and_then(|region| region.first_block()).expect("sv.ifdef has an else block");
+    for operation in moved {
+        unsafe {
+            mlir_sys::mlirOperationRemoveFromParent(operation.to_raw());
+            mlir_sys::mlirBlockAppendOwnedOperation(else_.to_raw(), operation.to_raw());
+        }
+    }
+    true
+}
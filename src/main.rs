@@ -1,44 +1,249 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
 use melior::ir::attribute::{ArrayAttribute, IntegerAttribute, StringAttribute, TypeAttribute};
-use melior::ir::operation::{OperationLike, OperationPrintingFlags};
 use melior::ir::r#type::IntegerType;
-use melior::ir::{Attribute, AttributeLike, Block, BlockLike, Location, Region, RegionLike, Type, TypeLike};
+use melior::ir::{AttributeLike, Block, BlockLike, Region, RegionLike, Type, TypeLike};
 use melior::Context;
-use melior::dialect::ods::{builtin, hw, sv};
+use melior::dialect::ods::{hw, sv};
+use tracing::Level;
+
+use circt_sv_basic::always::{self, Posedge};
+use circt_sv_basic::hierarchy;
+use circt_sv_basic::macros::KnownMacro;
+use circt_sv_basic::diagnostics;
+use circt_sv_basic::export::ExportOptions;
+use circt_sv_basic::pipeline::{PassBackend, PassInstrumentation, PassPipeline};
+use circt_sv_basic::rust_api;
+use circt_sv_basic::stats::{self, Stats};
+use circt_sv_basic::verilator::{self, HarnessOptions};
+use circt_sv_basic::{export, here, ifdef, lint, macros, Design, Error, PrintOptions};
+
+/* What to do with the design: print its IR (the default), `lower-llhd` to print it lowered to
+   LLHD, `export-arc <dir>` to write an arcilator model of it, `export-systemc <file>` to write
+   it as SystemC, `export-btor2 <file>` or `export-smtlib <file>` for model checking, or
+   `verilator-harness <dir>` to export its Verilog with a Verilator C++ harness for the top module.
+   `--version` reports library versions instead. */
+enum Mode {
+    Version,
+    Print,
+    LowerLlhd,
+    ExportArc(PathBuf),
+    ExportSystemc(PathBuf),
+    ExportBtor2(PathBuf),
+    ExportSmtlib(PathBuf),
+    VerilatorHarness(PathBuf),
+}
 
-use circt_sv_attrs::sv::svMacroIdentAttrGetAlt2;
+const USAGE: &str = "usage: circt-sv-basic [lower-llhd | export-arc <dir> | export-systemc <file> | export-btor2 <file>
+                      | export-smtlib <file> | verilator-harness <dir> [--clock <port>] [--reset <port>]]
+                     [--top <module>] [--stats] [--log-level <level>] [--verify-each] [--roundtrip]
+                     [--emit-rust-api <file>] [--print-generic] [--print-locations] [--print-pretty-locations]
+                     [--elide-elements-above <n>] [--emit-bytecode <file>] [--input <file>]
+                     [--single-thread] [--pass-timing] [--pass-statistics]
+                     [--pass-pipeline <pipeline>] [--print-ir-before-all] [--print-ir-after-all]
+                     [--print-ir-dir <dir>] [--reproducer <file>] [--pass-backend capi|circt-opt]
+       circt-sv-basic --version";
 
-macro_rules! here {
-    ($c:ident) => {
-        Location::new(&$c, file!(), line!() as usize, column!() as usize)
+/* Cycles the `export-smtlib` query unrolls the design for. */
+const BMC_BOUND: u32 = 20;
+
+/* The command line: a mode, `--top <module>` to verify and export only that module's hierarchy,
+   `--stats` to report metrics about the run, `--log-level` for the tracing output,
+   `--verify-each` to verify operations as they are built, `--roundtrip` to check the printed IR
+   parses back the same, and `--emit-rust-api <file>` to also write Rust constants describing the
+   modules' ports. `--clock` and `--reset` name the top's clock and reset for `verilator-harness`.
+   `--print-generic`, `--print-locations`, `--print-pretty-locations`, and `--elide-elements-above`
+   set how the IR is printed, and `--emit-bytecode <file>` writes it to `file` as MLIR bytecode
+   instead. `--input <file>` reads the design from a `.mlir` or `.mlirbc` file rather than building
+   the demo design. `--single-thread` runs passes on one thread, for debugging.
+   `--pass-timing` and `--pass-statistics` print MLIR's pass timing report and pass statistics,
+   the latter by running passes with `circt-opt`. `--pass-pipeline "builtin.module(...)"` runs
+   passes over the design before it is printed or exported. `--print-ir-before-all` and
+   `--print-ir-after-all` dump the IR around every pass run, to stderr or to files in
+   `--print-ir-dir <dir>`. `--reproducer <file>` keeps the input and pipeline of a pass pipeline
+   that fails or crashes in `file`. `--pass-backend circt-opt` runs passes with a `circt-opt`
+   subprocess rather than the linked C API. */
+struct Args {
+    mode: Mode,
+    top: Option<String>,
+    stats: bool,
+    log_level: Level,
+    verify_each: bool,
+    roundtrip: bool,
+    rust_api: Option<PathBuf>,
+    harness: HarnessOptions,
+    print: PrintOptions,
+    bytecode: Option<PathBuf>,
+    input: Option<PathBuf>,
+    single_thread: bool,
+    instrumentation: PassInstrumentation,
+    pipeline: Option<PassPipeline>,
+    reproducer: Option<PathBuf>,
+    backend: PassBackend,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut mode = None;
+    let mut top = None;
+    let mut stats = false;
+    let mut log_level = Level::WARN;
+    let mut verify_each = false;
+    let mut roundtrip = false;
+    let mut rust_api = None;
+    let mut harness = HarnessOptions::default();
+    let mut print = PrintOptions::default();
+    let mut bytecode = None;
+    let mut input = None;
+    let mut single_thread = false;
+    let mut instrumentation = PassInstrumentation::default();
+    let mut pipeline = None;
+    let mut reproducer = None;
+    let mut backend = PassBackend::default();
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| args.next().ok_or(format!("{arg} needs {what}"));
+        let next = match arg.as_str() {
+            "--top" => {
+                top = Some(value("a module name")?);
+                continue;
+            }
+            "--log-level" => {
+                let level = value("a level")?;
+                log_level = level.parse().map_err(|_| format!("unknown log level `{level}`"))?;
+                continue;
+            }
+            "--clock" => {
+                harness.clock = Some(value("a port")?);
+                continue;
+            }
+            "--reset" => {
+                harness.reset = Some(value("a port")?);
+                continue;
+            }
+            "--emit-rust-api" => {
+                rust_api = Some(PathBuf::from(value("a file")?));
+                continue;
+            }
+            "--print-generic" => {
+                print.generic = true;
+                continue;
+            }
+            "--print-locations" => {
+                print.locations = true;
+                continue;
+            }
+            "--print-pretty-locations" => {
+                print.pretty_locations = true;
+                continue;
+            }
+            "--elide-elements-above" => {
+                let limit = value("an element count")?;
+                let limit = limit.parse().map_err(|_| format!("invalid element count `{limit}`"))?;
+                print.elide_elements_above = Some(limit);
+                continue;
+            }
+            "--input" => {
+                input = Some(PathBuf::from(value("a file")?));
+                continue;
+            }
+            "--emit-bytecode" => {
+                bytecode = Some(PathBuf::from(value("a file")?));
+                continue;
+            }
+            "--pass-backend" => {
+                backend = match value("a backend")?.as_str() {
+                    "capi" => PassBackend::CApi,
+                    "circt-opt" => PassBackend::CirctOpt,
+                    other => return Err(format!("unknown pass backend `{other}`")),
+                };
+                continue;
+            }
+            "--reproducer" => {
+                reproducer = Some(PathBuf::from(value("a file")?));
+                continue;
+            }
+            "--pass-pipeline" => {
+                let text = value("a pipeline")?;
+                pipeline = Some(PassPipeline::parse(&text).map_err(|err| err.to_string())?);
+                continue;
+            }
+            "--print-ir-before-all" => {
+                instrumentation.print_before = true;
+                continue;
+            }
+            "--print-ir-after-all" => {
+                instrumentation.print_after = true;
+                continue;
+            }
+            "--print-ir-dir" => {
+                instrumentation.print_dir = Some(PathBuf::from(value("a directory")?));
+                continue;
+            }
+            "--pass-timing" => {
+                instrumentation.timing = true;
+                continue;
+            }
+            "--pass-statistics" => {
+                instrumentation.statistics = true;
+                continue;
+            }
+            "--single-thread" => {
+                single_thread = true;
+                continue;
+            }
+            "--roundtrip" => {
+                roundtrip = true;
+                continue;
+            }
+            "--verify-each" => {
+                verify_each = true;
+                continue;
+            }
+            "--stats" => {
+                stats = true;
+                continue;
+            }
+            "--version" => Mode::Version,
+            "lower-llhd" => Mode::LowerLlhd,
+            "export-arc" => Mode::ExportArc(PathBuf::from(value("a directory")?)),
+            "export-systemc" => Mode::ExportSystemc(PathBuf::from(value("a file")?)),
+            "export-btor2" => Mode::ExportBtor2(PathBuf::from(value("a file")?)),
+            "export-smtlib" => Mode::ExportSmtlib(PathBuf::from(value("a file")?)),
+            "verilator-harness" => Mode::VerilatorHarness(PathBuf::from(value("a directory")?)),
+            _ if mode.is_none() && !arg.starts_with('-') => return Err(format!("unknown mode `{arg}`")),
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        };
+        if mode.replace(next).is_some() {
+            return Err(format!("unexpected argument `{arg}`"));
+        }
     }
+    Ok(Args { mode: mode.unwrap_or(Mode::Print), top, stats, log_level, verify_each, roundtrip, rust_api,
+              harness, print, bytecode, input, single_thread,
+              instrumentation, pipeline, reproducer, backend })
 }
 
-fn create_hw_module() -> String 
+fn create_hw_module(ctx: &Context) -> Result<Design<'_>, Error>
 {
-    let ctx = Context::new();
-    let hw_handle = melior::dialect::DialectHandle::hw();
-    hw_handle.load_dialect(&ctx);    
-    let sv_handle = melior::dialect::DialectHandle::sv();
-    sv_handle.load_dialect(&ctx);
-
     // Build top block
-    let top_block = Block::new(&[]);
+    let design = Design::new(ctx, here!(ctx));
 
     /*
     sv.macro.decl @RANDOM
+    sv.macro.decl @PRINTF_COND
     sv.macro.decl @PRINTF_COND_
+    ...
     sv.macro.decl @SYNTHESIS
+    sv.macro.decl @VERILATOR
+    sv.ifdef @PRINTF_COND_ {
+    } else {
+      ...
+    }
      */
-    let macro_decl = sv::macro_decl(&ctx, StringAttribute::new(&ctx, "RANDOM"), here!(ctx));
-    top_block.append_operation(macro_decl.as_operation().clone());
-    let macro_decl = sv::macro_decl(&ctx, StringAttribute::new(&ctx, "PRINTF_COND_"), here!(ctx));
-    top_block.append_operation(macro_decl.as_operation().clone());
-    let macro_decl = sv::macro_decl(&ctx, StringAttribute::new(&ctx, "SYNTHESIS"), here!(ctx));
-    top_block.append_operation(macro_decl.as_operation().clone());
+    macros::declare_prelude(&design, here!(ctx));
 
     // Now the body block
-    let i1_type = IntegerType::new(&ctx, 1);
-    let i8_type = IntegerType::new(&ctx, 8);
+    let i1_type = IntegerType::new(ctx, 1);
+    let i8_type = IntegerType::new(ctx, 8);
 
     let body_block = Block::new(&[]);
     // Body blocks have the same args as the module's ports
@@ -47,14 +252,14 @@ fn create_hw_module() -> String
     let _arg8 = body_block.add_argument(i8_type.clone().into(), here!(ctx));    
     
     /* %fd = hw.constant 0x80000002 : i32 */
-    let i32_type = IntegerType::new(&ctx,32);
-    let arith_constant = hw::constant(&ctx,
+    let i32_type = IntegerType::new(ctx,32);
+    let arith_constant = hw::constant(ctx,
                                 i32_type.clone().into(),
                                 IntegerAttribute::new(i32_type.clone().into(), 0x80000002).into(), 
                                 here!(ctx)); 
     /* Equivalent low level code:
     let arith_constant = melior::ir::operation::OperationBuilder::new("hw.constant", here!(ctx))
-        .add_attributes(&[(melior::ir::Identifier::new(&ctx, "value"),
+        .add_attributes(&[(melior::ir::Identifier::new(ctx, "value"),
                             IntegerAttribute::new(i32_type.clone().into(), 0x80000002).into())])
         .add_results(&[i32_type.into()])
         .build()
@@ -62,66 +267,51 @@ fn create_hw_module() -> String
     body_block.append_operation(arith_constant.into());
 
     /* %param_x = sv.localparam {value = 11 : i42} : i42 */
-    let i42_type = IntegerType::new(&ctx, 42);
-    let param = sv::localparam(&ctx, i42_type.into(),
+    let i42_type = IntegerType::new(ctx, 42);
+    let param = sv::localparam(ctx, i42_type.into(),
                                 IntegerAttribute::new(i42_type.into(), 11).into(), 
-                                StringAttribute::new(&ctx, "x"), here!(ctx));
+                                StringAttribute::new(ctx, "x"), here!(ctx));
     /* Equivalent low level code:
     let param = melior::ir::operation::OperationBuilder::new("sv.localparam", here!(ctx))
-        .add_attributes(&[(melior::ir::Identifier::new(&ctx, "value"),
+        .add_attributes(&[(melior::ir::Identifier::new(ctx, "value"),
                             IntegerAttribute::new(i42_type.clone().into(), 11).into()),
-                            (melior::ir::Identifier::new(&ctx, "name"),
-                            StringAttribute::new(&ctx, "param_x").into())])
+                            (melior::ir::Identifier::new(ctx, "name"),
+                            StringAttribute::new(ctx, "param_x").into())])
         .add_results(&[i42_type.into()])
         .build()
         .expect("valid operation");*/
 
     body_block.append_operation(param.into());
 
-    let always_region = Region::new();
-    let always_block = Block::new(&[]);
-    let if_block = Block::new(&[]);    
-    let if_region = Region::new(); // Block::new(&[]);
-    if_region.append_block(if_block);
-    let else_block = Block::new(&[]);
-    let else_region = Region::new();
-    else_region.append_block(else_block);
-
-    let macro_ident = StringAttribute::new(&ctx, "SYNTHESIS");
-    let macro_ref = unsafe { Attribute::from_raw(svMacroIdentAttrGetAlt2(macro_ident.to_raw())) };
-    let ifdef_op = sv::ifdef_procedural(&ctx, if_region, else_region, macro_ref.into(), here!(ctx));
-
-    always_block.append_operation(ifdef_op.into());
-
-    // sv.always posedge %arg0
-    always_region.append_block(always_block);
-    // posedge = 0
-    let posedge = IntegerAttribute::new(IntegerType::new(&ctx, 32).into(), 0 as i64);
-    let events = ArrayAttribute::new(&ctx, &[posedge.into()]);
-    let sv_always = sv::always(&ctx, &[arg0], always_region, events, here!(ctx));
-    body_block.append_operation(sv_always.into());
-
-    let hw_output = hw::output(&ctx, &[], here!(ctx));
+    /* sv.always posedge %arg0 {
+         sv.ifdef.procedural @SYNTHESIS { } else { }
+       } */
+    always::always(ctx, &body_block, &[(Posedge, arg0)], |always_block| {
+        let synthesis = KnownMacro::Synthesis.declared(&design, here!(ctx));
+        ifdef::ifdef_procedural(&design, always_block, synthesis, |_| {}, Some(|_: &Block| {}), here!(ctx))
+    }, here!(ctx))?;
+
+    let hw_output = hw::output(ctx, &[], here!(ctx));
     body_block.append_operation(hw_output.into());
 
     let body_region = Region::new();
     body_region.append_block(body_block);
 
     // Create the module
-    let sym_name = StringAttribute::new(&ctx, "test1");
+    let sym_name = StringAttribute::new(ctx, "test1");
     let mod_ports = [
         mlir_sys::HWModulePort {
-            name: StringAttribute::new(&ctx, "arg0").to_raw(),
+            name: StringAttribute::new(ctx, "arg0").to_raw(),
             type_: i1_type.clone().to_raw(),
             dir: mlir_sys::HWModulePortDirection_Input
         },
         mlir_sys::HWModulePort {
-            name: StringAttribute::new(&ctx, "arg1").to_raw(),
+            name: StringAttribute::new(ctx, "arg1").to_raw(),
             type_: i1_type.to_raw(),
             dir: mlir_sys::HWModulePortDirection_Input
         },        
         mlir_sys::HWModulePort {
-            name: StringAttribute::new(&ctx, "arg8").to_raw(),
+            name: StringAttribute::new(ctx, "arg8").to_raw(),
             type_: i8_type.to_raw(),
             dir: mlir_sys::HWModulePortDirection_Input
         }
@@ -131,33 +321,142 @@ fn create_hw_module() -> String
                                                     mod_ports.len() as isize, 
                                                     std::mem::transmute(&mod_ports))) 
     });
-    let parameters = ArrayAttribute::new(&ctx, &[]); 
+    let parameters = ArrayAttribute::new(ctx, &[]); 
 
-    let module = hw::module(&ctx,
+    let module = hw::module(ctx,
                             body_region,
                             sym_name,
                             module_type,
                             parameters,
                             here!(ctx));
 
-    top_block.append_operation(module.into());
+    design.body().append_operation(module.into());
+    Ok(design)
+}
+
+/* Report metrics about the design, with the sizes of the files it exports to. */
+fn report_stats(design: &Design, generation: Duration, verification: Duration) -> Result<(), Error> {
+    let modules = stats::module_stats(design);
+    let dir = std::env::temp_dir().join(format!("circt-sv-basic-stats-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).map_err(|err| Error::Io(format!("{}: {err}", dir.display())))?;
+    let files = export::export_split_verilog(design, &dir, &ExportOptions::default()).map(|files| {
+        files.into_iter()
+            .map(|file| {
+                let size = std::fs::metadata(&file).map_or(0, |metadata| metadata.len());
+                (file.strip_prefix(&dir).unwrap_or(&file).to_path_buf(), size)
+            })
+            .collect()
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+    eprint!("{}", Stats { modules, generation, verification, files: files? });
+    Ok(())
+}
 
-    let top_region = Region::new();
-    top_region.append_block(top_block);
-    let top = builtin::module(&ctx, top_region, here!(ctx));
+fn run(args: &Args) -> Result<(), Error> {
+    diagnostics::set_verify_each_op(args.verify_each);
+    let ctx = Context::new();
+    circt_sv_basic::load_dialects(&ctx);
+    if let Mode::Version = args.mode {
+        println!("{}", circt_sv_basic::version(&ctx));
+        return Ok(());
+    }
+    let start = Instant::now();
+    let design = match &args.input {
+        Some(file) => Design::read(&ctx, file)?,
+        None => tracing::info_span!("module construction").in_scope(|| create_hw_module(&ctx))?,
+    };
+    let generation = start.elapsed();
+    design.set_multithreading(!args.single_thread);
+    design.set_pass_instrumentation(args.instrumentation.clone());
+    design.set_crash_reproducer(args.reproducer.clone());
+    design.set_pass_backend(args.backend);
+
+    // Skip the modules outside the selected hierarchy
+    let top = match &args.top {
+        Some(top) => {
+            for module in hierarchy::remove_unreachable(&design, &[top.as_str()])? {
+                eprintln!("skipping {module}");
+            }
+            top.as_str()
+        }
+        None => "test1",
+    };
 
-    unsafe {
-        if mlir_sys::mlirOperationVerify(top.as_operation().to_raw()) {
-                eprintln!("Verification passed!");
-            } else {
-                eprintln!("Verification failed :-(");
+    let start = Instant::now();
+    design.check()?;
+    lint::check_combinational_loops(&design)?;
+    lint::check_multiple_drivers(&design)?;
+    if args.roundtrip {
+        design.check_roundtrip()?;
+    }
+    eprintln!("Verification passed!");
+    let verification = start.elapsed();
+    if let Some(pipeline) = &args.pipeline {
+        pipeline.run(&design)?;
+    }
+    if let Some(file) = &args.rust_api {
+        std::fs::write(file, rust_api::rust_api(&design))
+            .map_err(|err| Error::Io(format!("{}: {err}", file.display())))?;
+    }
+    match &args.mode {
+        Mode::Version => unreachable!("handled before the design is built"),
+        Mode::Print | Mode::LowerLlhd => {
+            if let Mode::LowerLlhd = args.mode {
+                PassPipeline::new().lower_to_llhd().run(&design)?;
+            }
+            match &args.bytecode {
+                Some(file) => {
+                    design.write_bytecode(file)?;
+                    eprintln!("wrote {}", file.display());
+                }
+                None => println!("{}", design.print(&args.print)?),
+            }
+        }
+        Mode::ExportArc(dir) => {
+            std::fs::create_dir_all(dir).map_err(|err| Error::Io(format!("{}: {err}", dir.display())))?;
+            for file in export::export_arc(&design, dir, top)? {
+                eprintln!("wrote {}", file.display());
             }
+        }
+        Mode::ExportSystemc(file) => export::export_systemc(&design, file)?,
+        Mode::ExportBtor2(file) => export::export_btor2(&design, file)?,
+        Mode::ExportSmtlib(file) => export::export_smtlib(&design, file, top, BMC_BOUND)?,
+        Mode::VerilatorHarness(dir) => {
+            // The harness is built from the ports before export lowers the module
+            let harness = verilator::harness(&design, top, &args.harness)?;
+            let options = ExportOptions { tops: vec![top.to_string()], ..ExportOptions::default() };
+            std::fs::create_dir_all(dir).map_err(|err| Error::Io(format!("{}: {err}", dir.display())))?;
+            let mut files = export::export_split_verilog(&design, dir, &options)?;
+            let file = dir.join(format!("{top}_harness.cpp"));
+            std::fs::write(&file, harness).map_err(|err| Error::Io(format!("{}: {err}", file.display())))?;
+            files.push(file);
+            for file in files {
+                eprintln!("wrote {}", file.display());
+            }
+        }
+    }
+    // Last, since exporting for the file sizes prepares the IR for emission
+    if args.stats {
+        report_stats(&design, generation, verification)?;
     }
-    let flags = OperationPrintingFlags::default();
-    let text = top.as_operation().to_string_with_flags(flags).unwrap();
-    text    
+    Ok(())
 }
 
 fn main() {
-    println!("{}", create_hw_module());
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {err}");
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    tracing_subscriber::fmt().with_max_level(args.log_level).with_writer(std::io::stderr).init();
+    match run(&args) {
+        Ok(()) => {}
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,89 @@
+//! Priority and one-hot multiplexers expanded to `comb` logic.
+//!
+//! ```ignore
+//! let next = mux::priority_mux(&body, &[(flush, zero), (load, data)], count, here!(ctx))?;
+//! let grant_data = mux::onehot_mux(&ctx, &body, grant, &[a, b, c], true, here!(ctx))?;
+//! ```
+
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, Location, Type, Value, ValueLike};
+use melior::Context;
+
+use crate::compare::{icmp, Predicate};
+use crate::constant::{const_int, Signedness};
+use crate::diagnostics::Append;
+use crate::width::extract;
+use crate::{bit_width, verif, Error};
+
+fn width(value: Value) -> Result<u64, Error> {
+    bit_width(value.r#type()).ok_or(Error::UnknownWidth(value.r#type().to_string()))
+}
+
+/* The width of `value`, which must be an integer. */
+fn integer_width(value: Value) -> Result<u64, Error> {
+    IntegerType::try_from(value.r#type())
+        .map(|integer| u64::from(integer.width()))
+        .map_err(|_| Error::TypeMismatch { expected: "an integer".to_string(), found: value.r#type().to_string() })
+}
+
+/* Append the `comb` operation `name` over `operands`, with a result of type `r#type`. */
+fn comb<'c, 'a>(block: &'a Block<'c>, name: &str, operands: &[Value<'c, '_>], r#type: Type<'c>,
+                location: Location<'c>) -> Value<'c, 'a> {
+    let operation = OperationBuilder::new(name, location)
+        .add_operands(operands)
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    block.append(operation).result(0).unwrap().into()
+}
+
+/// `cases[0].1` if `cases[0].0` is set, else `cases[1].1` if `cases[1].0` is set, ..., else
+/// `default`: a chain of `comb.mux`, first case highest priority. The conditions are `i1`s and
+/// the values must all have `default`'s type.
+pub fn priority_mux<'c, 'a>(block: &'a Block<'c>, cases: &[(Value<'c, '_>, Value<'c, 'a>)], default: Value<'c, 'a>,
+                            location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let mut result = default;
+    for (condition, value) in cases.iter().rev() {
+        if value.r#type() != default.r#type() {
+            return Err(Error::WidthMismatch { expected: width(default)?, found: width(*value)? });
+        }
+        result = comb(block, "comb.mux", &[*condition, *value, result], default.r#type(), location);
+    }
+    Ok(result)
+}
+
+/// `inputs[i]` where bit `i` of `select` is the only bit set, as an AND-OR tree, which is what
+/// synthesis wants for selects that are one-hot by construction. `select` must be an integer
+/// with a bit per input, and the inputs integers of the same width. When `assert_one_hot` is set,
+/// a `verif.assert` checks exactly one bit of `select` is set.
+pub fn onehot_mux<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, select: Value<'c, '_>, inputs: &[Value<'c, '_>],
+                          assert_one_hot: bool, location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let Some(first) = inputs.first() else { return Err(Error::WidthMismatch { expected: 1, found: 0 }) };
+    let select_width = integer_width(select)?;
+    if select_width != inputs.len() as u64 {
+        return Err(Error::WidthMismatch { expected: inputs.len() as u64, found: select_width });
+    }
+    let r#type = first.r#type();
+    let input_width = integer_width(*first)?;
+    let mut terms = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        if input.r#type() != r#type {
+            return Err(Error::WidthMismatch { expected: input_width, found: integer_width(*input)? });
+        }
+        let bit = extract(ctx, block, select, index as u64, 1, location);
+        let mask = comb(block, "comb.replicate", &[bit], r#type, location);
+        terms.push(comb(block, "comb.and", &[mask, *input], r#type, location));
+    }
+    if assert_one_hot {
+        let one = const_int(ctx, block, select_width as u32, 1, Signedness::Unsigned, location)?;
+        let zero = const_int(ctx, block, select_width as u32, 0, Signedness::Unsigned, location)?;
+        let below = comb(block, "comb.sub", &[select, one], select.r#type(), location);
+        let overlap = comb(block, "comb.and", &[select, below], select.r#type(), location);
+        let at_most_one = icmp(ctx, block, Predicate::Eq, overlap, zero, location);
+        let any = icmp(ctx, block, Predicate::Ne, select, zero, location);
+        let one_hot = comb(block, "comb.and", &[at_most_one, any], IntegerType::new(ctx, 1).into(), location);
+        verif::assert(ctx, block, one_hot, Some("onehot_mux_select_one_hot"), location);
+    }
+    Ok(comb(block, "comb.or", &terms, r#type, location))
+}
@@ -0,0 +1,48 @@
+//! Marking internal values as observable, so they survive optimization with stable names in the
+//! Verilog and appear as source level variables in waveform viewers reading HGLDD debug info.
+//!
+//! ```ignore
+//! let sum = observe::observe(&ctx, &body, "partial_sum", sum, here!(ctx));
+//! ```
+
+use melior::ir::attribute::StringAttribute;
+use melior::ir::operation::OperationBuilder;
+use melior::ir::{Attribute, Block, Identifier, Location, Value, ValueLike};
+use melior::Context;
+
+use crate::diagnostics::Append;
+
+/// Append `hw.wire value sym @name` to `block`: a wire the inner symbol keeps from being
+/// optimized away or renamed, emitted as `wire name`. Use the returned value in its place.
+pub fn probe<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, name: &str, value: Value<'c, '_>,
+                     location: Location<'c>) -> Value<'c, 'a> {
+    let symbol = Attribute::parse(ctx, &format!("#hw<innerSym@{name}>")).expect("valid inner symbol");
+    let wire = OperationBuilder::new("hw.wire", location)
+        .add_operands(&[value])
+        .add_attributes(&[(Identifier::new(ctx, "name"), StringAttribute::new(ctx, name).into()),
+                          (Identifier::new(ctx, "inner_sym"), symbol)])
+        .add_results(&[value.r#type()])
+        .build()
+        .expect("valid operation");
+    block.append(wire).result(0).unwrap().into()
+}
+
+/// Append `dbg.variable "name", value` to `block`, recording `value` as the source variable
+/// `name` in the debug info, without affecting the emitted Verilog.
+pub fn debug_variable<'c>(ctx: &'c Context, block: &Block<'c>, name: &str, value: Value<'c, '_>,
+                          location: Location<'c>) {
+    let variable = OperationBuilder::new("dbg.variable", location)
+        .add_operands(&[value])
+        .add_attributes(&[(Identifier::new(ctx, "name"), StringAttribute::new(ctx, name).into())])
+        .build()
+        .expect("valid operation");
+    block.append(variable);
+}
+
+/// [`probe`] `value` and record the probe as the debug variable `name`, returning the probe.
+pub fn observe<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, name: &str, value: Value<'c, '_>,
+                       location: Location<'c>) -> Value<'c, 'a> {
+    let probe = probe(ctx, block, name, value, location);
+    debug_variable(ctx, block, name, probe, location);
+    probe
+}
@@ -0,0 +1,187 @@
+//! `om` dialect builders, for structured metadata about generated modules (a CSR map, ...) that
+//! travels with the IR for downstream CIRCT tools to evaluate.
+//!
+//! ```ignore
+//! om::class(&design, "Register", &[("address", om::integer_type(&ctx))], |body| {
+//!     let name = om::string(&ctx, body, "ctrl", here!(ctx));
+//!     vec![("name", name), ("address", body.argument(0).unwrap().into())]
+//! }, here!(ctx))?;
+//! ```
+
+use std::collections::HashSet;
+
+use melior::ir::attribute::{ArrayAttribute, FlatSymbolRefAttribute, StringAttribute};
+use melior::ir::operation::OperationBuilder;
+use melior::ir::{Attribute, Block, Identifier, Location, Region, RegionLike, Type, TypeLike, Value, ValueLike};
+use melior::{Context, StringRef};
+
+use crate::diagnostics::Append;
+use crate::{Design, Error};
+
+/// How a path refers to its target, `om::TargetKind`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetKind {
+    /// A path to a hardware object which doesn't exist.
+    DontTouch,
+    /// The hardware object itself, e.g. a register or instance.
+    Reference,
+    /// A module the path instantiates.
+    Instance,
+    /// A value inside a module.
+    MemberReference,
+    /// An instance inside a module.
+    MemberInstance,
+}
+
+impl TargetKind {
+    fn keyword(self) -> &'static str {
+        match self {
+            TargetKind::DontTouch => "dont_touch",
+            TargetKind::Reference => "reference",
+            TargetKind::Instance => "instance",
+            TargetKind::MemberReference => "member_reference",
+            TargetKind::MemberInstance => "member_instance",
+        }
+    }
+}
+
+fn parse_type<'c>(ctx: &'c Context, source: &str) -> Type<'c> {
+    Type::parse(ctx, source).unwrap_or_else(|| panic!("`{source}` is a valid om type"))
+}
+
+pub fn integer_type(ctx: &Context) -> Type<'_> {
+    parse_type(ctx, "!om.integer")
+}
+
+pub fn string_type(ctx: &Context) -> Type<'_> {
+    parse_type(ctx, "!om.string")
+}
+
+pub fn frozen_path_type(ctx: &Context) -> Type<'_> {
+    parse_type(ctx, "!om.frozenpath")
+}
+
+/// `!om.class.type<@name>`, the type of objects of class `name`.
+pub fn class_type<'c>(ctx: &'c Context, name: &str) -> Type<'c> {
+    parse_type(ctx, &format!("!om.class.type<@{name}>"))
+}
+
+/// `!om.list<element>`.
+pub fn list_type<'c>(ctx: &'c Context, element: Type<'c>) -> Type<'c> {
+    parse_type(ctx, &format!("!om.list<{element}>"))
+}
+
+fn constant<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Attribute<'c>, r#type: Type<'c>,
+                    location: Location<'c>) -> Value<'c, 'a> {
+    let constant = OperationBuilder::new("om.constant", location)
+        .add_attributes(&[(Identifier::new(ctx, "value"), value)])
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    block.append(constant).result(0).unwrap().into()
+}
+
+/// Append `om.constant #om.integer<value : si64> : !om.integer` to `block`.
+pub fn integer<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: i64, location: Location<'c>) -> Value<'c, 'a> {
+    let attribute = Attribute::parse(ctx, &format!("#om.integer<{value} : si64>")).expect("valid om integer");
+    constant(ctx, block, attribute, integer_type(ctx), location)
+}
+
+/// Append `om.constant "value" : !om.string` to `block`.
+pub fn string<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: &str, location: Location<'c>) -> Value<'c, 'a> {
+    let attribute = unsafe {
+        Attribute::from_raw(mlir_sys::mlirStringAttrTypedGet(string_type(ctx).to_raw(), StringRef::new(value).to_raw()))
+    };
+    constant(ctx, block, attribute, string_type(ctx), location)
+}
+
+/// Append `om.list_create` of `elements`, all of type `element`, to `block`.
+pub fn list<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, element: Type<'c>, elements: &[Value<'c, '_>],
+                    location: Location<'c>) -> Value<'c, 'a> {
+    let list = OperationBuilder::new("om.list_create", location)
+        .add_operands(elements)
+        .add_results(&[list_type(ctx, element)])
+        .build()
+        .expect("valid operation");
+    block.append(list).result(0).unwrap().into()
+}
+
+/// Append `om.object @class(arguments)` to `block`, instantiating an `om.class`.
+pub fn object<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, class: &str, arguments: &[Value<'c, '_>],
+                      location: Location<'c>) -> Value<'c, 'a> {
+    let object = OperationBuilder::new("om.object", location)
+        .add_attributes(&[(Identifier::new(ctx, "className"), FlatSymbolRefAttribute::new(ctx, class).into())])
+        .add_operands(arguments)
+        .add_results(&[class_type(ctx, class)])
+        .build()
+        .expect("valid operation");
+    block.append(object).result(0).unwrap().into()
+}
+
+/// Append an `om.frozenpath_create` to `block`: a path to hardware, relative to `base_path`
+/// (an `!om.frozenbasepath`), of the form `Top/inst:Module>signal`.
+pub fn frozen_path<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, kind: TargetKind, base_path: Value<'c, '_>,
+                           path: &str, module: &str, reference: &str, location: Location<'c>) -> Value<'c, 'a> {
+    let target_kind = Attribute::parse(ctx, &format!("#om<target_kind {}>", kind.keyword())).expect("valid target kind");
+    let string = |value: &str| -> Attribute<'c> { StringAttribute::new(ctx, value).into() };
+    let path = OperationBuilder::new("om.frozenpath_create", location)
+        .add_attributes(&[(Identifier::new(ctx, "targetKind"), target_kind),
+                          (Identifier::new(ctx, "path"), string(path)),
+                          (Identifier::new(ctx, "module"), string(module)),
+                          (Identifier::new(ctx, "ref"), string(reference)),
+                          (Identifier::new(ctx, "field"), string(""))])
+        .add_operands(&[base_path])
+        .add_results(&[frozen_path_type(ctx)])
+        .build()
+        .expect("valid operation");
+    block.append(path).result(0).unwrap().into()
+}
+
+/// Append `om.class @name` with formal parameters `parameters` to the top level of `design`.
+/// `body` builds the class body, where the parameters are the block arguments, and returns the
+/// class's fields, which become its `om.class.fields` terminator.
+pub fn class<'c, F>(design: &Design<'c>, name: &str, parameters: &[(&str, Type<'c>)], body: F,
+                    location: Location<'c>) -> Result<(), Error>
+where
+    F: FnOnce(&Block<'c>) -> Vec<(&str, Value<'c, '_>)>,
+{
+    let ctx = design.context();
+    let arguments: Vec<(Type, Location)> = parameters.iter().map(|(_, r#type)| (*r#type, location)).collect();
+    let class_block = Block::new(&arguments);
+    let fields = body(&class_block);
+
+    let mut seen = HashSet::new();
+    let mut field_names: Vec<Attribute> = Vec::new();
+    let mut field_types = Vec::new();
+    for (field, value) in &fields {
+        if !seen.insert(*field) {
+            return Err(Error::DuplicateName(field.to_string()));
+        }
+        field_names.push(StringAttribute::new(ctx, field).into());
+        field_types.push(format!("{field} = {}", value.r#type()));
+    }
+    let field_dictionary = format!("{{{}}}", field_types.join(", "));
+    let field_values: Vec<Value> = fields.iter().map(|(_, value)| *value).collect();
+    let fields_op = OperationBuilder::new("om.class.fields", location)
+        .add_operands(&field_values)
+        .build()
+        .expect("valid operation");
+    class_block.append(fields_op);
+
+    let parameter_names: Vec<Attribute> = parameters.iter()
+        .map(|(parameter, _)| StringAttribute::new(ctx, parameter).into())
+        .collect();
+    let class_region = Region::new();
+    class_region.append_block(class_block);
+    let class = OperationBuilder::new("om.class", location)
+        .add_attributes(&[(Identifier::new(ctx, "sym_name"), StringAttribute::new(ctx, name).into()),
+                          (Identifier::new(ctx, "formalParamNames"), ArrayAttribute::new(ctx, &parameter_names).into()),
+                          (Identifier::new(ctx, "fieldNames"), ArrayAttribute::new(ctx, &field_names).into()),
+                          (Identifier::new(ctx, "fieldTypes"),
+                           Attribute::parse(ctx, &field_dictionary).expect("valid field types"))])
+        .add_regions([class_region])
+        .build()
+        .expect("valid operation");
+    design.body().append(class);
+    Ok(())
+}
@@ -0,0 +1,334 @@
+//! Module parameters and widths computed from them, so one module can be width-generic in the
+//! emitted Verilog instead of being generated once per width.
+//!
+//! ```ignore
+//! let width = ParamExpr::reference("WIDTH");
+//! let data = param::int_type(&ctx, &width);
+//! let wide = param::int_type(&ctx, &(width.clone() * ParamExpr::from(2)));
+//! let bits = param::localparam(&ctx, &body, "INDEX_BITS", &ParamExpr::reference("DEPTH").clog2(), i32,
+//!                              here!(ctx));
+//! param::module(&design, "Fifo", &[Parameter::new("WIDTH", 8)],
+//!               &[("in", PortDirection::Input, data), ("out", PortDirection::Output, data)],
+//!               |body| Ok(vec![body.argument(0)?.into()]), here!(ctx))?;
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+use melior::dialect::ods::{hw, sv};
+use melior::ir::attribute::{
+    ArrayAttribute, FlatSymbolRefAttribute, IntegerAttribute, StringAttribute, TypeAttribute,
+};
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Attribute, Block, Identifier, Location, Region, Type, Value};
+use melior::Context;
+
+use crate::decl::inout_type;
+use crate::diagnostics::Append;
+use crate::ports::{module_type, PortDirection};
+use crate::{Design, Error};
+
+/// An integer parameter expression, `#hw.param.expr` in CIRCT, which ExportVerilog prints as a
+/// Verilog expression over the module's parameters.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ParamExpr {
+    Value(i64),
+    /// The value of the enclosing module's parameter of this name.
+    Reference(String),
+    Add(Vec<ParamExpr>),
+    Mul(Vec<ParamExpr>),
+    Sub(Box<ParamExpr>, Box<ParamExpr>),
+    DivU(Box<ParamExpr>, Box<ParamExpr>),
+    Shl(Box<ParamExpr>, Box<ParamExpr>),
+    /// `$clog2`, the bits to index this many elements.
+    Clog2(Box<ParamExpr>),
+}
+
+impl ParamExpr {
+    pub fn reference(name: &str) -> Self {
+        ParamExpr::Reference(name.to_string())
+    }
+
+    pub fn clog2(self) -> Self {
+        ParamExpr::Clog2(Box::new(self)).simplify()
+    }
+
+    /// The value when it doesn't depend on a parameter.
+    pub fn constant(&self) -> Option<i64> {
+        match self.simplify() {
+            ParamExpr::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Fold what doesn't depend on a parameter: constant operands are combined, nested sums and
+    /// products flattened, and identities like `x + 0` and `x * 1` dropped, so an expression only
+    /// mentions parameters where they matter.
+    pub fn simplify(&self) -> ParamExpr {
+        let binary = |left: &ParamExpr, right: &ParamExpr| (left.simplify(), right.simplify());
+        match self {
+            ParamExpr::Value(_) | ParamExpr::Reference(_) => self.clone(),
+            ParamExpr::Add(operands) => associative(operands, true),
+            ParamExpr::Mul(operands) => associative(operands, false),
+            ParamExpr::Sub(left, right) => match binary(left, right) {
+                (ParamExpr::Value(left), ParamExpr::Value(right)) => ParamExpr::Value(left.wrapping_sub(right)),
+                (left, ParamExpr::Value(0)) => left,
+                (left, right) if left == right => ParamExpr::Value(0),
+                (left, right) => ParamExpr::Sub(Box::new(left), Box::new(right)),
+            },
+            ParamExpr::DivU(left, right) => match binary(left, right) {
+                (ParamExpr::Value(left), ParamExpr::Value(right)) if right != 0 => {
+                    ParamExpr::Value(((left as u64) / (right as u64)) as i64)
+                }
+                (left, ParamExpr::Value(1)) => left,
+                (left, right) => ParamExpr::DivU(Box::new(left), Box::new(right)),
+            },
+            ParamExpr::Shl(left, right) => match binary(left, right) {
+                (ParamExpr::Value(left), ParamExpr::Value(right)) if (0..64).contains(&right) => {
+                    ParamExpr::Value(left << right)
+                }
+                (left, ParamExpr::Value(0)) => left,
+                (left, right) => ParamExpr::Shl(Box::new(left), Box::new(right)),
+            },
+            ParamExpr::Clog2(operand) => match operand.simplify() {
+                ParamExpr::Value(value) if value <= 1 => ParamExpr::Value(0),
+                ParamExpr::Value(value) => ParamExpr::Value(i64::from(64 - (value - 1).leading_zeros())),
+                operand => ParamExpr::Clog2(Box::new(operand)),
+            },
+        }
+    }
+
+    /// The value with the parameters given `values`, or `None` if it refers to one that isn't.
+    pub fn evaluate(&self, values: &HashMap<String, i64>) -> Option<i64> {
+        self.substitute(values).constant()
+    }
+
+    /// This expression with the parameters in `values` replaced by their values, simplified.
+    pub fn substitute(&self, values: &HashMap<String, i64>) -> ParamExpr {
+        let replace = |operand: &ParamExpr| Box::new(operand.substitute(values));
+        let each = |operands: &[ParamExpr]| -> Vec<ParamExpr> {
+            operands.iter().map(|operand| operand.substitute(values)).collect()
+        };
+        let substituted = match self {
+            ParamExpr::Value(_) => self.clone(),
+            ParamExpr::Reference(name) => values.get(name).copied().map_or_else(|| self.clone(), ParamExpr::Value),
+            ParamExpr::Add(operands) => ParamExpr::Add(each(operands)),
+            ParamExpr::Mul(operands) => ParamExpr::Mul(each(operands)),
+            ParamExpr::Sub(left, right) => ParamExpr::Sub(replace(left), replace(right)),
+            ParamExpr::DivU(left, right) => ParamExpr::DivU(replace(left), replace(right)),
+            ParamExpr::Shl(left, right) => ParamExpr::Shl(replace(left), replace(right)),
+            ParamExpr::Clog2(operand) => ParamExpr::Clog2(replace(operand)),
+        };
+        substituted.simplify()
+    }
+}
+
+/* `operands` summed, or multiplied unless `add`, simplified. */
+fn associative(operands: &[ParamExpr], add: bool) -> ParamExpr {
+    fn collect(operand: ParamExpr, add: bool, constant: &mut i64, symbolic: &mut Vec<ParamExpr>) {
+        match operand {
+            ParamExpr::Value(value) if add => *constant = constant.wrapping_add(value),
+            ParamExpr::Value(value) => *constant = constant.wrapping_mul(value),
+            ParamExpr::Add(nested) if add => {
+                nested.into_iter().for_each(|operand| collect(operand, add, constant, symbolic));
+            }
+            ParamExpr::Mul(nested) if !add => {
+                nested.into_iter().for_each(|operand| collect(operand, add, constant, symbolic));
+            }
+            operand => symbolic.push(operand),
+        }
+    }
+    let identity = i64::from(!add);
+    let (mut constant, mut symbolic) = (identity, Vec::new());
+    for operand in operands {
+        collect(operand.simplify(), add, &mut constant, &mut symbolic);
+    }
+    if !add && constant == 0 {
+        return ParamExpr::Value(0);
+    }
+    // CIRCT's canonical form keeps the constant operand last
+    if constant != identity || symbolic.is_empty() {
+        symbolic.push(ParamExpr::Value(constant));
+    }
+    match symbolic.len() {
+        1 => symbolic.pop().unwrap(),
+        _ if add => ParamExpr::Add(symbolic),
+        _ => ParamExpr::Mul(symbolic),
+    }
+}
+
+impl From<i64> for ParamExpr {
+    fn from(value: i64) -> Self {
+        ParamExpr::Value(value)
+    }
+}
+
+impl Add for ParamExpr {
+    type Output = ParamExpr;
+
+    fn add(self, other: ParamExpr) -> ParamExpr {
+        ParamExpr::Add(vec![self, other]).simplify()
+    }
+}
+
+impl Sub for ParamExpr {
+    type Output = ParamExpr;
+
+    fn sub(self, other: ParamExpr) -> ParamExpr {
+        ParamExpr::Sub(Box::new(self), Box::new(other)).simplify()
+    }
+}
+
+impl Mul for ParamExpr {
+    type Output = ParamExpr;
+
+    fn mul(self, other: ParamExpr) -> ParamExpr {
+        ParamExpr::Mul(vec![self, other]).simplify()
+    }
+}
+
+/* `operands` separated by commas. */
+fn join<'e>(operands: impl IntoIterator<Item = &'e ParamExpr>) -> String {
+    operands.into_iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+}
+
+/// The attribute syntax, with operands typed by the `i32` context they are parsed in.
+impl fmt::Display for ParamExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamExpr::Value(value) => write!(f, "{value}"),
+            ParamExpr::Reference(name) => write!(f, "#hw.param.decl.ref<\"{name}\">"),
+            ParamExpr::Add(operands) => write!(f, "#hw.param.expr.add<{}>", join(operands)),
+            ParamExpr::Mul(operands) => write!(f, "#hw.param.expr.mul<{}>", join(operands)),
+            ParamExpr::Sub(left, right) => {
+                // There is no subtraction; `a - b` is `a + b * -1`.
+                let negated = ParamExpr::Mul(vec![(**right).clone(), ParamExpr::Value(-1)]);
+                write!(f, "#hw.param.expr.add<{}>", join([&**left, &negated]))
+            }
+            ParamExpr::DivU(left, right) => write!(f, "#hw.param.expr.divu<{}>", join([&**left, &**right])),
+            ParamExpr::Shl(left, right) => write!(f, "#hw.param.expr.shl<{}>", join([&**left, &**right])),
+            ParamExpr::Clog2(operand) => write!(f, "#hw.param.expr.clog2<{operand}>"),
+        }
+    }
+}
+
+/// An `i32` module parameter with a default value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parameter {
+    pub name: String,
+    pub default: i64,
+}
+
+impl Parameter {
+    pub fn new(name: &str, default: i64) -> Self {
+        Self { name: name.to_string(), default }
+    }
+
+    /// `#hw.param.decl<"name": i32 = default>`, for a module's `parameters`.
+    pub fn attribute<'c>(&self, ctx: &'c Context) -> Attribute<'c> {
+        Attribute::parse(ctx, &format!("#hw.param.decl<\"{}\": i32 = {}>", self.name, self.default))
+            .expect("valid parameter declaration")
+    }
+}
+
+/// An integer type `width` bits wide: `iN` when `width` folds to a constant, `!hw.int<width>`
+/// otherwise.
+pub fn int_type<'c>(ctx: &'c Context, width: &ParamExpr) -> Type<'c> {
+    match width.simplify() {
+        ParamExpr::Value(width) => IntegerType::new(ctx, width as u32).into(),
+        width => Type::parse(ctx, &format!("!hw.int<{width}>")).expect("valid parametric integer type"),
+    }
+}
+
+/* `expression` simplified as an `r#type` attribute: an integer, or a parameter expression. */
+fn attribute<'c>(ctx: &'c Context, expression: &ParamExpr, r#type: Type<'c>) -> Attribute<'c> {
+    match expression.simplify() {
+        ParamExpr::Value(value) => IntegerAttribute::new(r#type, value).into(),
+        expression => Attribute::parse(ctx, &format!("{expression} : {type}")).expect("valid parameter expression"),
+    }
+}
+
+/// Append the value of `expression` as an `r#type` integer to `block`, to use a parameter in
+/// logic: `hw.param.value`, or `hw.constant` if it folds to a constant.
+pub fn value<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, expression: &ParamExpr, r#type: Type<'c>,
+                     location: Location<'c>) -> Value<'c, 'a> {
+    let value = attribute(ctx, expression, r#type);
+    let name = if expression.constant().is_some() { "hw.constant" } else { "hw.param.value" };
+    let operation = OperationBuilder::new(name, location)
+        .add_attributes(&[(Identifier::new(ctx, "value"), value)])
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    block.append(operation).result(0).unwrap().into()
+}
+
+/// Append `sv.localparam name = expression` to `block`, an `r#type` integer. ExportVerilog prints a
+/// parameter expression, like `localparam DEPTH_BITS = $clog2(DEPTH);`, unless it folds to a
+/// constant.
+pub fn localparam<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, name: &str, expression: &ParamExpr,
+                          r#type: Type<'c>, location: Location<'c>) -> Value<'c, 'a> {
+    let localparam = sv::localparam(ctx, r#type, attribute(ctx, expression, r#type), StringAttribute::new(ctx, name),
+                                    location);
+    block.append(localparam.into()).result(0).unwrap().into()
+}
+
+/// [`ports::module`](crate::ports::module) with `parameters`, which `ports` types and the body can
+/// refer to by [`ParamExpr::Reference`].
+pub fn module<'c, F>(design: &Design<'c>, name: &str, parameters: &[Parameter],
+                     ports: &[(&str, PortDirection, Type<'c>)], body: F, location: Location<'c>) -> Result<(), Error>
+where
+    F: FnOnce(&Block<'c>) -> Result<Vec<Value<'c, '_>>, Error>,
+{
+    let ctx = design.context();
+    let arguments: Vec<(Type, Location)> = ports.iter()
+        .filter(|(_, direction, _)| *direction != PortDirection::Output)
+        .map(|(_, direction, r#type)| match direction {
+            PortDirection::InOut => (inout_type(*r#type), location),
+            _ => (*r#type, location),
+        })
+        .collect();
+    let body_block = Block::new(&arguments);
+    let outputs = body(&body_block)?;
+    body_block.append(hw::output(ctx, &outputs, location).into());
+    let body_region = Region::new();
+    body_region.append_block(body_block);
+    let parameters: Vec<Attribute> = parameters.iter().map(|parameter| parameter.attribute(ctx)).collect();
+    let module = hw::module(ctx, body_region, StringAttribute::new(ctx, &design.module_name(name)),
+                            TypeAttribute::new(module_type(ctx, ports)), ArrayAttribute::new(ctx, &parameters),
+                            location);
+    design.body().append(module.into());
+    Ok(())
+}
+
+/// [`ports::instance`](crate::ports::instance) of a parameterized module, overriding its
+/// `parameters` by name. `outputs` have the types the parameters give them here.
+pub fn instance<'c, 'a>(design: &Design<'c>, block: &'a Block<'c>, name: Option<&str>, module: &str,
+                        parameters: &[(&str, i64)], inputs: &[(&str, Value<'c, '_>)], outputs: &[(&str, Type<'c>)],
+                        location: Location<'c>) -> Vec<Value<'c, 'a>> {
+    let ctx = design.context();
+    let module = design.module_name(module);
+    let name = name.map_or_else(|| design.instance_name(&module), str::to_string);
+    let names = |ports: Vec<&str>| -> Attribute<'c> {
+        let names: Vec<Attribute> = ports.into_iter().map(|port| StringAttribute::new(ctx, port).into()).collect();
+        ArrayAttribute::new(ctx, &names).into()
+    };
+    let parameters: Vec<Attribute> = parameters.iter()
+        .map(|(name, value)| Parameter::new(name, *value).attribute(ctx))
+        .collect();
+    let operands: Vec<Value> = inputs.iter().map(|(_, value)| *value).collect();
+    let results: Vec<Type> = outputs.iter().map(|(_, r#type)| *r#type).collect();
+    let instance = OperationBuilder::new("hw.instance", location)
+        .add_attributes(&[(Identifier::new(ctx, "instanceName"), StringAttribute::new(ctx, &name).into()),
+                          (Identifier::new(ctx, "moduleName"), FlatSymbolRefAttribute::new(ctx, &module).into()),
+                          (Identifier::new(ctx, "argNames"), names(inputs.iter().map(|(port, _)| *port).collect())),
+                          (Identifier::new(ctx, "resultNames"), names(outputs.iter().map(|(port, _)| *port).collect())),
+                          (Identifier::new(ctx, "parameters"), ArrayAttribute::new(ctx, &parameters).into())])
+        .add_operands(&operands)
+        .add_results(&results)
+        .build()
+        .expect("valid operation");
+    let instance = block.append(instance);
+    (0..results.len()).map(|index| instance.result(index).unwrap().into()).collect()
+}
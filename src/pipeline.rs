@@ -0,0 +1,302 @@
+//! Running CIRCT passes over a [`Design`].
+//!
+//! ```ignore
+//! PassPipeline::new().add("hw-cleanup").add("prettify-verilog").run(&design)?;
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+
+use melior::ir::operation::{OperationLike, OperationPrintingFlags};
+use melior::pass::{parse_pass_pipeline, PassManager};
+use melior::{LogicalResult, StringRef};
+
+use crate::export::{run_tool, temp_dir, write_design};
+use crate::{diagnostics, Design, Error, PrintOptions};
+
+static REGISTER_PASSES: Once = Once::new();
+
+/// Register the CIRCT passes pipelines can refer to by name. Called by [`PassPipeline::run`].
+pub fn register_passes() {
+    REGISTER_PASSES.call_once(|| unsafe {
+        mlir_sys::registerConversionPasses();
+        mlir_sys::registerHWPasses();
+        mlir_sys::registerSVPasses();
+        mlir_sys::registerCombPasses();
+        mlir_sys::registerSeqPasses();
+        mlir_sys::registerPipelinePasses();
+        mlir_sys::registerHandshakePasses();
+        mlir_sys::registerDCPasses();
+        mlir_sys::registerESIPasses();
+        mlir_sys::registerMSFTPasses();
+    });
+}
+
+/// The MLIR pass instrumentation [`PassPipeline::run`] turns on for the passes it runs on a design:
+/// reports for finding where export time goes, and IR dumps around each pass for diagnosing
+/// lowering problems. The reports are printed to stderr when the pipeline finishes, and the dumps
+/// as it runs, as `circt-opt --mlir-timing --mlir-pass-statistics --mlir-print-ir-after-all` does.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PassInstrumentation {
+    /// Time each pass.
+    pub timing: bool,
+    /// Report the statistics passes keep, like the patterns a canonicalization applied. The C API
+    /// can't turn these on, so with them the pipeline is run by `circt-opt`, `$CIRCT_OPT` if it is
+    /// set, over a copy of the design, whose contents are then replaced with the tool's output.
+    pub statistics: bool,
+    /// Dump the IR each pass runs on before it.
+    pub print_before: bool,
+    /// Dump the IR each pass runs on after it.
+    pub print_after: bool,
+    /// Write the dumps to files under this directory instead of to stderr, in a tree of
+    /// directories following the nesting of the pipeline, as `--mlir-print-ir-tree-dir` does.
+    pub print_dir: Option<PathBuf>,
+}
+
+/// How [`PassPipeline::run`] runs passes over a design.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PassBackend {
+    /// In process, through the CIRCT C API this crate is linked against.
+    #[default]
+    CApi,
+    /// With a `circt-opt` subprocess, `$CIRCT_OPT` if it is set, over the design written to a
+    /// temporary file, for passes the linked C API doesn't register. The design's contents are
+    /// replaced with the tool's output, so values and operations taken from it before are stale.
+    CirctOpt,
+}
+
+/// A sequence of passes run on the top level `builtin.module`, in `circt-opt` pass syntax.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PassPipeline {
+    passes: Vec<String>,
+}
+
+impl PassPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pipeline `text`, in the `builtin.module(pass1,pass2)` form [`to_pipeline_string`]
+    /// prints, or just its `pass1,pass2` list, for running any registered CIRCT pass. The passes
+    /// are only checked when the pipeline is run.
+    ///
+    /// [`to_pipeline_string`]: PassPipeline::to_pipeline_string
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let text = text.trim();
+        let list = match text.strip_prefix("builtin.module(") {
+            Some(rest) => rest.strip_suffix(')').ok_or_else(|| Error::InvalidPipeline(text.to_string()))?,
+            None => text,
+        };
+        let mut passes = Vec::new();
+        let (mut depth, mut start) = (0i32, 0);
+        for (index, character) in list.char_indices() {
+            match character {
+                '(' | '{' => depth += 1,
+                ')' | '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    passes.push(list[start..index].trim().to_string());
+                    start = index + 1;
+                }
+                _ => {}
+            }
+            if depth < 0 {
+                return Err(Error::InvalidPipeline(text.to_string()));
+            }
+        }
+        if depth != 0 {
+            return Err(Error::InvalidPipeline(text.to_string()));
+        }
+        passes.push(list[start..].trim().to_string());
+        passes.retain(|pass| !pass.is_empty());
+        Ok(Self { passes })
+    }
+
+    /// Append a pass, with options if it has any, e.g. `export-split-verilog{dir-name=out}`.
+    pub fn add(mut self, pass: &str) -> Self {
+        self.passes.push(pass.to_string());
+        self
+    }
+
+    /// Lower the design's `hw` modules to `llhd` entities, for event-driven simulation flows.
+    /// `sv` procedural code has no LLHD lowering, so this is for designs built from `hw`, `comb`,
+    /// and `seq` only.
+    pub fn lower_to_llhd(self) -> Self {
+        self.add("convert-hw-to-llhd").add("canonicalize")
+    }
+
+    /// Lower `pipeline.scheduled` pipelines to `seq` registers and `comb` logic in their modules,
+    /// then `seq` to `sv`, see [`crate::scheduled`].
+    pub fn lower_pipelines(self) -> Self {
+        self.add("lower-pipeline-to-hw").add("lower-seq-to-sv")
+    }
+
+    /// Lower `handshake.func`s to `hw.module`s of `dc` dataflow, materializing the forks and sinks
+    /// handshake leaves implicit and buffering every channel so the result has no combinational
+    /// cycles. Follow with [`PassPipeline::lower_dc`].
+    pub fn lower_handshake(self) -> Self {
+        self.add("handshake-materialize-forks-sinks")
+            .add("handshake-insert-buffers")
+            .add("lower-handshake-to-dc")
+    }
+
+    /// Lower `dc` dataflow to valid/ready handshaking in `hw`, `comb`, and `seq`, then to `sv`.
+    pub fn lower_dc(self) -> Self {
+        self.add("lower-dc-to-hw").lower_esi().add("lower-seq-to-sv")
+    }
+
+    /// Lower ESI channels, including channel typed module ports, to valid/ready signals, see
+    /// [`crate::esi`].
+    pub fn lower_esi(self) -> Self {
+        self.add("lower-esi-to-physical")
+            .add("lower-esi-ports")
+            .add("lower-esi-to-hw")
+    }
+
+    /// Write the `msft` placements under `tops` to a Tcl script, `file` in the export directory,
+    /// and remove the placement ops so the design can be exported. See [`crate::placement`].
+    pub fn export_placement_tcl(self, tops: &[&str], file: &str) -> Self {
+        self.add(&format!("msft-export-tcl{{tops={} tcl-file={file}}}", tops.join(",")))
+            .add("lower-msft-to-hw")
+    }
+
+    /// Lower `hw`-level constructs with no direct Verilog form, like `hw.triggered`, to `sv`.
+    pub fn lower_hw_to_sv(self) -> Self {
+        self.add("lower-hw-to-sv")
+    }
+
+    /// Remove module ports that are never read, for inputs, or never driven, for outputs, and
+    /// update the instances of those modules. See [`crate::ports::prune_unused`] for a report of
+    /// what was removed.
+    pub fn remove_unused_ports(self) -> Self {
+        self.add("hw-remove-unused-ports")
+    }
+
+    /// Split struct typed module ports into one port per field, recursively, for tools that
+    /// can't handle packed structs on ports.
+    pub fn flatten_struct_ports(self) -> Self {
+        self.add("hw-flatten-io{recursive=true flatten-extern=true}")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// The textual pipeline, `builtin.module(pass1,pass2)`.
+    pub fn to_pipeline_string(&self) -> String {
+        format!("builtin.module({})", self.passes.join(","))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(pipeline = %self.to_pipeline_string()))]
+    pub fn run(&self, design: &Design) -> Result<(), Error> {
+        register_passes();
+        run_pipeline(design, self.to_pipeline_string())
+    }
+}
+
+fn run_pipeline(design: &Design, pipeline: String) -> Result<(), Error> {
+    // Written before the run, so it survives a crash; removed again if the passes succeed
+    let reproducer = design.crash_reproducer();
+    if let Some(file) = &reproducer {
+        write_reproducer(design, &pipeline, file)?;
+    }
+    let instrumentation = design.pass_instrumentation();
+    // The C API has no switch for pass statistics, only circt-opt's command line does
+    let result = match design.pass_backend() {
+        PassBackend::CApi if !instrumentation.statistics => run_in_process(design, pipeline, &instrumentation),
+        _ => run_circt_opt(design, &pipeline, &instrumentation),
+    };
+    match (result, reproducer) {
+        (Ok(()), Some(file)) => {
+            let _ = fs::remove_file(file);
+            Ok(())
+        }
+        (Err(Error::PassFailed { pipeline, mut diagnostics }), Some(file)) => {
+            diagnostics.push(diagnostics::Diagnostic {
+                message: "reproducer written, run it with `circt-opt --run-reproducer`".to_string(),
+                location: file.display().to_string(),
+                snippet: None,
+            });
+            Err(Error::PassFailed { pipeline, diagnostics })
+        }
+        (result, _) => result,
+    }
+}
+
+fn run_in_process(design: &Design, pipeline: String, instrumentation: &PassInstrumentation) -> Result<(), Error> {
+    // Contexts from the C API start single threaded; the pass manager uses the context's threads
+    design.context().enable_multi_threading(design.multithreading());
+    let pass_manager = PassManager::new(design.context());
+    parse_pass_pipeline(pass_manager.as_operation_pass_manager(), &pipeline)
+        .map_err(|_| Error::InvalidPipeline(pipeline.clone()))?;
+    if instrumentation.timing {
+        // Reported when the pass manager is dropped
+        unsafe { mlir_sys::mlirPassManagerEnableTiming(pass_manager.to_raw()) };
+    }
+    if instrumentation.print_before || instrumentation.print_after {
+        // Printing the whole module around each pass would need a single thread; print what it runs on
+        let dir = instrumentation.print_dir.as_ref().map(|dir| dir.display().to_string()).unwrap_or_default();
+        unsafe {
+            mlir_sys::mlirPassManagerEnableIRPrinting(pass_manager.to_raw(), instrumentation.print_before,
+                                                      instrumentation.print_after, false, false, false,
+                                                      OperationPrintingFlags::new().to_raw(),
+                                                      StringRef::new(&dir).to_raw());
+        }
+    }
+    let (result, reported) = diagnostics::capture(design.context(), || unsafe {
+        LogicalResult::from_raw(mlir_sys::mlirPassManagerRunOnOp(pass_manager.to_raw(),
+                                                                 design.module().as_operation().to_raw()))
+    });
+    if result.is_success() {
+        Ok(())
+    } else {
+        let diagnostics = diagnostics::resolve(&design.module().as_operation(), reported);
+        Err(Error::PassFailed { pipeline, diagnostics })
+    }
+}
+
+/* Run `pipeline` over a copy of the design with `circt-opt`, with the pass instrumentation
+   `instrumentation` asks for, then replace the design's contents with the result. */
+fn run_circt_opt(design: &Design, pipeline: &str, instrumentation: &PassInstrumentation) -> Result<(), Error> {
+    let dir = temp_dir("passes")?;
+    let (input, output) = (dir.join("input.mlir"), dir.join("output.mlir"));
+    let result = write_design(design, &input).and_then(|()| {
+        let mut args = vec![format!("--pass-pipeline={pipeline}"), input.display().to_string(),
+                            "-o".to_string(), output.display().to_string()];
+        if !design.multithreading() {
+            args.push("--mlir-disable-threading".to_string());
+        }
+        if instrumentation.timing {
+            args.push("--mlir-timing".to_string());
+        }
+        if instrumentation.statistics {
+            args.push("--mlir-pass-statistics".to_string());
+        }
+        if instrumentation.print_before {
+            args.push("--mlir-print-ir-before-all".to_string());
+        }
+        if instrumentation.print_after {
+            args.push("--mlir-print-ir-after-all".to_string());
+        }
+        if let Some(dir) = &instrumentation.print_dir {
+            args.push(format!("--mlir-print-ir-tree-dir={}", dir.display()));
+        }
+        run_tool("circt-opt", "CIRCT_OPT", &args)
+    }).and_then(|()| {
+        let source = fs::read(&output).map_err(|err| Error::Io(format!("{}: {err}", output.display())))?;
+        design.replace_body(Design::parse_bytes(design.context(), &source)?);
+        Ok(())
+    });
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/* Write the design and `pipeline` to `file` as an MLIR reproducer: the IR, with the pipeline to
+   run over it in the `mlir_reproducer` resource `circt-opt --run-reproducer` reads. */
+fn write_reproducer(design: &Design, pipeline: &str, file: &Path) -> Result<(), Error> {
+    let ir = design.print(&PrintOptions { locations: true, ..PrintOptions::default() })?;
+    let reproducer = format!("{ir}\n{{-#\n  external_resources: {{\n    mlir_reproducer: {{\n      \
+                              pipeline: {pipeline:?},\n      disable_threading: {},\n      \
+                              verify_each: true\n    }}\n  }}\n#-}}\n", !design.multithreading());
+    fs::write(file, reproducer).map_err(|err| Error::Io(format!("{}: {err}", file.display())))
+}
@@ -0,0 +1,118 @@
+//! FPGA placement of instances with the `msft` dialect, exported as a Tcl script of location
+//! assignments for the vendor tools (see [`ExportOptions::placement_tcl`](crate::export::ExportOptions)).
+//!
+//! ```ignore
+//! placement::place(&design, "Top", &["core", "ram"], PhysLocation::new(Primitive::M20k, 15, 9, 0), here!(ctx))?;
+//! ```
+
+use std::fmt;
+
+use melior::ir::attribute::FlatSymbolRefAttribute;
+use melior::ir::operation::{OperationBuilder, OperationLike, OperationRef};
+use melior::ir::{Attribute, Block, Identifier, Location, Region, RegionLike};
+use melior::Context;
+
+use crate::diagnostics::Append;
+use crate::hierarchy::instance_symbol;
+use crate::{operations, Design, Error};
+
+/// The kinds of FPGA sites an instance can be placed on, `msft::PrimitiveType` in CIRCT.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    M20k,
+    Dsp,
+    Ff,
+}
+
+impl Primitive {
+    fn keyword(self) -> &'static str {
+        match self {
+            Primitive::M20k => "M20K",
+            Primitive::Dsp => "DSP",
+            Primitive::Ff => "FF",
+        }
+    }
+}
+
+/// A site on the device: a primitive at column `x`, row `y`, and index `number` within the tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PhysLocation {
+    pub primitive: Primitive,
+    pub x: u64,
+    pub y: u64,
+    pub number: u64,
+}
+
+impl PhysLocation {
+    pub fn new(primitive: Primitive, x: u64, y: u64, number: u64) -> Self {
+        Self { primitive, x, y, number }
+    }
+
+    /// `#msft.physloc<M20K, x, y, number>`.
+    pub fn attribute<'c>(&self, ctx: &'c Context) -> Attribute<'c> {
+        Attribute::parse(ctx, &self.to_string()).expect("valid physical location")
+    }
+}
+
+impl fmt::Display for PhysLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#msft.physloc<{}, {}, {}, {}>", self.primitive.keyword(), self.x, self.y, self.number)
+    }
+}
+
+/* The `msft.instance.hierarchy` placements in `top` were added to, if any. */
+fn existing_hierarchy<'c, 'a>(design: &'a Design<'c>, top: &str) -> Option<OperationRef<'c, 'a>> {
+    operations(&design.body()).into_iter().find(|operation| {
+        operation.name().as_string_ref().as_str() == Ok("msft.instance.hierarchy")
+            && operation.attribute("topModuleRef").ok()
+                .and_then(|top| FlatSymbolRefAttribute::try_from(top).ok())
+                .is_some_and(|reference| reference.value() == top)
+    })
+}
+
+/// Place the instance at `path`, instance names starting from the module `top`, on `site`: an
+/// `msft.pd.location` nested in an `msft.instance.dynamic` for each instance on the path, in the
+/// `msft.instance.hierarchy @top` at the top level of `design`.
+pub fn place<'c>(design: &Design<'c>, top: &str, path: &[&str], site: PhysLocation,
+                 location: Location<'c>) -> Result<(), Error> {
+    let ctx = design.context();
+    let top = &design.module_name(top);
+    let mut module = top.clone();
+    let mut references = Vec::new();
+    for name in path {
+        let child = instance_symbol(design, &module, name)?;
+        references.push(format!("#hw.innerNameRef<@{module}::@{name}>"));
+        module = child;
+    }
+    let mut op = OperationBuilder::new("msft.pd.location", location)
+        .add_attributes(&[(Identifier::new(ctx, "loc"), site.attribute(ctx))])
+        .build()
+        .expect("valid operation");
+    for reference in references.iter().rev() {
+        let body = Block::new(&[]);
+        body.append(op);
+        let region = Region::new();
+        region.append_block(body);
+        op = OperationBuilder::new("msft.instance.dynamic", location)
+            .add_attributes(&[(Identifier::new(ctx, "instanceRef"),
+                               Attribute::parse(ctx, reference).expect("valid instance reference"))])
+            .add_regions([region])
+            .build()
+            .expect("valid operation");
+    }
+    if let Some(hierarchy) = existing_hierarchy(design, top) {
+        hierarchy.region(0).ok().and_then(|region| region.first_block()).expect("hierarchy has a body").append(op);
+        return Ok(());
+    }
+    let body = Block::new(&[]);
+    body.append(op);
+    let region = Region::new();
+    region.append_block(body);
+    let hierarchy = OperationBuilder::new("msft.instance.hierarchy", location)
+        .add_attributes(&[(Identifier::new(ctx, "topModuleRef"), FlatSymbolRefAttribute::new(ctx, top).into())])
+        .add_regions([region])
+        .build()
+        .expect("valid operation");
+    design.body().append(hierarchy);
+    Ok(())
+}
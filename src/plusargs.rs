@@ -0,0 +1,40 @@
+//! `$test$plusargs` and `$value$plusargs` queries, for simulation controls set on the simulator
+//! command line (`+trace`, `+seed=42`) instead of with macros at compile time.
+//!
+//! ```ignore
+//! let trace = plusargs::test(&ctx, &body, "trace", here!(ctx));
+//! let (found, seed) = plusargs::value(&ctx, &body, "seed=%d", i32, here!(ctx));
+//! ```
+
+use melior::ir::attribute::StringAttribute;
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, Identifier, Location, Type, Value};
+use melior::Context;
+
+use crate::diagnostics::Append;
+
+/// Append `sv.system.plusargs.test "name"` to `block`: an `i1` that is set when the simulation
+/// was started with `+name`.
+pub fn test<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, name: &str, location: Location<'c>) -> Value<'c, 'a> {
+    let test = OperationBuilder::new("sv.system.plusargs.test", location)
+        .add_attributes(&[(Identifier::new(ctx, "formatString"), StringAttribute::new(ctx, name).into())])
+        .add_results(&[IntegerType::new(ctx, 1).into()])
+        .build()
+        .expect("valid operation");
+    block.append(test).result(0).unwrap().into()
+}
+
+/// Append `sv.system.plusargs.value "format"` to `block`, reading a plusarg like `+seed=%d` into
+/// a `r#type` value. Returns an `i1` that is set when the plusarg was given, and the value, which
+/// is undefined when it wasn't.
+pub fn value<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, format: &str, r#type: Type<'c>,
+                     location: Location<'c>) -> (Value<'c, 'a>, Value<'c, 'a>) {
+    let value = OperationBuilder::new("sv.system.plusargs.value", location)
+        .add_attributes(&[(Identifier::new(ctx, "formatString"), StringAttribute::new(ctx, format).into())])
+        .add_results(&[IntegerType::new(ctx, 1).into(), r#type])
+        .build()
+        .expect("valid operation");
+    let value = block.append(value);
+    (value.result(0).unwrap().into(), value.result(1).unwrap().into())
+}
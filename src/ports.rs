@@ -0,0 +1,337 @@
+//! Building modules from port lists, and reading back the ports of the modules in a [`Design`].
+//!
+//! Wide interfaces can group related ports into one struct typed port:
+//!
+//! ```ignore
+//! let request = ports::struct_type(&ctx, &[("valid", i1), ("addr", i32), ("data", i64)]);
+//! ports::module(&design, "Sink", &[("req", PortDirection::Input, request)], |body| {
+//!     let fields = ports::struct_fields(body, body.argument(0)?.into(), here!(ctx));
+//!     ...
+//! }, here!(ctx))?;
+//! ```
+//!
+//! A struct deriving [`Ports`](crate::Ports) names the ports instead of block argument indices:
+//!
+//! ```ignore
+//! #[derive(Ports)]
+//! struct Counter { clk: Input<1>, enable: Input<1>, count: Output<8> }
+//!
+//! ports::bundle_module::<Counter, _>(&design, "Counter", |ports| {
+//!     ...
+//!     Ok(CounterOutputs { count })
+//! }, here!(ctx))?;
+//! ```
+
+use std::fmt;
+
+use melior::ir::attribute::{ArrayAttribute, StringAttribute, TypeAttribute};
+use melior::ir::operation::{OperationBuilder, OperationLike, OperationRef};
+use melior::ir::{AttributeLike, Block, Identifier, Location, Operation, Region, Type, TypeLike, Value, ValueLike};
+use melior::{Context, StringRef};
+
+use crate::diagnostics::Append;
+use crate::param;
+use crate::pipeline::PassPipeline;
+use crate::{operations, symbol_name, Design, Error};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortDirection {
+    Input,
+    Output,
+    InOut,
+}
+
+impl PortDirection {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            PortDirection::Input => "input",
+            PortDirection::Output => "output",
+            PortDirection::InOut => "inout",
+        }
+    }
+
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        [PortDirection::Input, PortDirection::Output, PortDirection::InOut].into_iter()
+            .find(|direction| direction.keyword() == keyword)
+    }
+
+    fn to_raw(self) -> mlir_sys::HWModulePortDirection {
+        match self {
+            PortDirection::Input => mlir_sys::HWModulePortDirection_Input,
+            PortDirection::Output => mlir_sys::HWModulePortDirection_Output,
+            PortDirection::InOut => mlir_sys::HWModulePortDirection_InOut,
+        }
+    }
+}
+
+/// `!hw.modty<...>` with `ports` in order, for the `module_type` of an `hw.module`. The type of an
+/// inout port is its element type, as in the IR; its block argument is `!hw.inout` of it.
+pub fn module_type<'c>(ctx: &'c Context, ports: &[(&str, PortDirection, Type<'c>)]) -> Type<'c> {
+    let ports: Vec<mlir_sys::HWModulePort> = ports.iter().map(|(name, direction, r#type)| mlir_sys::HWModulePort {
+        name: StringAttribute::new(ctx, name).to_raw(),
+        type_: r#type.to_raw(),
+        dir: direction.to_raw(),
+    }).collect();
+    unsafe { Type::from_raw(mlir_sys::hwModuleTypeGet(ctx.to_raw(), ports.len() as isize, ports.as_ptr())) }
+}
+
+/// A port of an `hw.module`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Port {
+    pub name: String,
+    pub direction: PortDirection,
+    /// The port type as printed in the IR, e.g. `i8`.
+    pub r#type: String,
+}
+
+impl Port {
+    /// The port type, parsed in `ctx`.
+    pub fn parse_type<'c>(&self, ctx: &'c Context) -> Result<Type<'c>, Error> {
+        Type::parse(ctx, &self.r#type).ok_or_else(|| Error::UnknownWidth(self.r#type.clone()))
+    }
+}
+
+/// Append `hw.module.extern @name` with `ports` to the top level of `design`: a module defined
+/// elsewhere, which can be instantiated but isn't emitted.
+pub fn declare_extern<'c>(design: &Design<'c>, name: &str, ports: &[Port],
+                          location: Location<'c>) -> Result<(), Error> {
+    let ctx = design.context();
+    let types = ports.iter().map(|port| port.parse_type(ctx)).collect::<Result<Vec<_>, _>>()?;
+    let ports: Vec<(&str, PortDirection, Type)> = ports.iter().zip(types)
+        .map(|(port, r#type)| (port.name.as_str(), port.direction, r#type))
+        .collect();
+    let name = design.module_name(name);
+    let extern_module = OperationBuilder::new("hw.module.extern", location)
+        .add_attributes(&[(Identifier::new(ctx, "sym_name"), StringAttribute::new(ctx, &name).into()),
+                          (Identifier::new(ctx, "module_type"), TypeAttribute::new(module_type(ctx, &ports)).into()),
+                          (Identifier::new(ctx, "parameters"), ArrayAttribute::new(ctx, &[]).into())])
+        .add_regions([Region::new()])
+        .build()
+        .expect("valid operation");
+    design.body().append(extern_module);
+    Ok(())
+}
+
+/// Append `hw.module @name` with `ports` to the top level of `design`, named by
+/// [`Design::module_name`]. `body` builds the module body, whose arguments are the input and inout
+/// ports in order, an inout port's as `!hw.inout` of its type, and returns the values of the
+/// output ports in order.
+pub fn module<'c, F>(design: &Design<'c>, name: &str, ports: &[(&str, PortDirection, Type<'c>)], body: F,
+                     location: Location<'c>) -> Result<(), Error>
+where
+    F: FnOnce(&Block<'c>) -> Result<Vec<Value<'c, '_>>, Error>,
+{
+    param::module(design, name, &[], ports, body, location)
+}
+
+/// An input port `W` bits wide, for a field of a `#[derive(Ports)]` struct.
+pub struct Input<const W: usize>;
+
+/// An output port `W` bits wide, for a field of a `#[derive(Ports)]` struct.
+pub struct Output<const W: usize>;
+
+/// An inout port of a `W` bit integer, for a field of a `#[derive(Ports)]` struct.
+pub struct InOut<const W: usize>;
+
+/// A module's ports declared as a struct of [`Input`], [`Output`], and [`InOut`] fields, as
+/// implemented by `#[derive(Ports)]`. The inputs and outputs of a body are structs with a field
+/// per port, instead of block arguments and results by index.
+pub trait PortBundle {
+    /// The values of the input and inout ports in the module body.
+    type Inputs<'c: 'a, 'a>;
+    /// The values the body drives the output ports with.
+    type Outputs<'c: 'a, 'a>;
+
+    /// The ports, in field order.
+    fn ports<'c>(ctx: &'c Context) -> Vec<(&'static str, PortDirection, Type<'c>)>;
+
+    /// The input and inout ports of the module body `block`.
+    fn inputs<'c: 'a, 'a>(block: &'a Block<'c>) -> Self::Inputs<'c, 'a>;
+
+    /// The output values in port order.
+    fn outputs<'c: 'a, 'a>(outputs: Self::Outputs<'c, 'a>) -> Vec<Value<'c, 'a>>;
+}
+
+/// [`module`] with the ports of the bundle `B`: `body` gets the values of its inputs and returns
+/// the values of its outputs.
+pub fn bundle_module<'c, B, F>(design: &Design<'c>, name: &str, body: F, location: Location<'c>) -> Result<(), Error>
+where
+    B: PortBundle,
+    F: for<'a> FnOnce(B::Inputs<'c, 'a>) -> Result<B::Outputs<'c, 'a>, Error>,
+{
+    let ports = B::ports(design.context());
+    module(design, name, &ports, |block| Ok(B::outputs(body(B::inputs(block))?)), location)
+}
+
+/// Append `hw.instance "name" @module` to `block`, connecting `inputs` to the module's input and
+/// inout ports by name, in port order, and returning the values of `outputs`, its output ports.
+/// `module` is the name given to [`module`]; the instance is named by the naming policy when
+/// `name` is `None`.
+pub fn instance<'c, 'a>(design: &Design<'c>, block: &'a Block<'c>, name: Option<&str>, module: &str,
+                        inputs: &[(&str, Value<'c, '_>)], outputs: &[(&str, Type<'c>)],
+                        location: Location<'c>) -> Vec<Value<'c, 'a>> {
+    param::instance(design, block, name, module, &[], inputs, outputs, location)
+}
+
+/// The `hw.module` or `hw.module.extern` named `name` in `design`.
+pub fn find_module<'c, 'a>(design: &'a Design<'c>, name: &str) -> Option<OperationRef<'c, 'a>> {
+    let name = &design.module_name(name);
+    operations(&design.body()).into_iter().find(|operation| {
+        let op = operation.name().as_string_ref().as_str().unwrap_or_default().to_string();
+        (op == "hw.module" || op == "hw.module.extern") && symbol_name(operation).as_deref() == Some(name)
+    })
+}
+
+/// The ports of `module`, an `hw.module` or `hw.module.extern`, in declaration order. The type of
+/// an inout port is its element type, as [`module_type`] takes it.
+pub fn module_ports(module: &Operation) -> Vec<Port> {
+    let Some(module_type) = module.attribute("module_type").ok()
+        .and_then(|attribute| TypeAttribute::try_from(attribute).ok())
+        .map(|attribute| attribute.value())
+    else {
+        return Vec::new();
+    };
+    let order = output_order(&module_type.to_string());
+    let module_type = module_type.to_raw();
+    let string = |name| unsafe { StringRef::from_raw(name) }.as_str().unwrap_or_default().to_string();
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    unsafe {
+        for index in 0..mlir_sys::hwModuleTypeGetNumInputs(module_type) {
+            let r#type = Type::from_raw(mlir_sys::hwModuleTypeGetInputType(module_type, index));
+            let (direction, r#type) = if mlir_sys::hwTypeIsAInOut(r#type.to_raw()) {
+                (PortDirection::InOut, Type::from_raw(mlir_sys::hwInOutTypeGetElementType(r#type.to_raw())))
+            } else {
+                (PortDirection::Input, r#type)
+            };
+            inputs.push(Port { name: string(mlir_sys::hwModuleTypeGetInputName(module_type, index)),
+                               direction, r#type: r#type.to_string() });
+        }
+        for index in 0..mlir_sys::hwModuleTypeGetNumOutputs(module_type) {
+            let r#type = Type::from_raw(mlir_sys::hwModuleTypeGetOutputType(module_type, index));
+            outputs.push(Port { name: string(mlir_sys::hwModuleTypeGetOutputName(module_type, index)),
+                                direction: PortDirection::Output, r#type: r#type.to_string() });
+        }
+    }
+    let (mut inputs, mut outputs) = (inputs.into_iter(), outputs.into_iter());
+    let mut ports: Vec<Port> = order.into_iter()
+        .filter_map(|output| if output { outputs.next() } else { inputs.next() })
+        .collect();
+    ports.extend(inputs.chain(outputs));
+    ports
+}
+
+/* Whether each port of the printed `!hw.modty<...>` is an output, in declaration order, since the
+   C API only reads back the inputs and the outputs separately. Each port starts with its direction
+   keyword, after the `<` or a `,` at the top level. */
+fn output_order(module_type: &str) -> Vec<bool> {
+    let mut order = Vec::new();
+    let (mut depth, mut quoted, mut escaped) = (0, false, false);
+    for (index, c) in module_type.char_indices() {
+        if quoted {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                quoted = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            '<' | '(' | '[' | '{' => depth += 1,
+            '>' | ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+        if (c == '<' || c == ',') && depth == 1 {
+            order.push(module_type[index + 1..].trim_start().starts_with("output "));
+        }
+    }
+    order
+}
+
+/// A port [`prune_unused`] removed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrunedPort {
+    pub module: String,
+    pub port: Port,
+}
+
+impl fmt::Display for PrunedPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} ({} {})", self.module, self.port.name, self.port.direction.keyword(), self.port.r#type)
+    }
+}
+
+/* Module name -> ports, for every `hw.module` in the design. */
+fn ports_by_module(design: &Design) -> Vec<(String, Vec<Port>)> {
+    operations(&design.body()).iter()
+        .filter(|operation| operation.name().as_string_ref().as_str() == Ok("hw.module"))
+        .filter_map(|operation| Some((symbol_name(operation)?, module_ports(operation))))
+        .collect()
+}
+
+/// Remove the ports that are never read or driven anywhere in `design`, as generic templates
+/// stamped out for a particular use often leave some unconnected, and report what was removed.
+pub fn prune_unused(design: &Design) -> Result<Vec<PrunedPort>, Error> {
+    let before = ports_by_module(design);
+    PassPipeline::new().remove_unused_ports().run(design)?;
+    let after = ports_by_module(design);
+    let mut pruned = Vec::new();
+    for (module, ports) in before {
+        let remaining = after.iter()
+            .find(|(name, _)| *name == module)
+            .map(|(_, ports)| ports.as_slice())
+            .unwrap_or_default();
+        pruned.extend(ports.into_iter()
+            .filter(|port| !remaining.contains(port))
+            .map(|port| PrunedPort { module: module.clone(), port }));
+    }
+    Ok(pruned)
+}
+
+/// `!hw.struct<fields...>`, for declaring a group of related ports as one struct typed port.
+pub fn struct_type<'c>(ctx: &'c Context, fields: &[(&str, Type<'c>)]) -> Type<'c> {
+    let fields: Vec<mlir_sys::HWStructFieldInfo> = fields.iter().map(|(name, r#type)| mlir_sys::HWStructFieldInfo {
+        name: Identifier::new(ctx, name).to_raw(),
+        type_: r#type.to_raw(),
+    }).collect();
+    unsafe { Type::from_raw(mlir_sys::hwStructTypeGet(ctx.to_raw(), fields.len() as isize, fields.as_ptr())) }
+}
+
+/// The fields of the struct typed `value`, a struct port's block argument for example, by name
+/// in order, from one `hw.struct_explode` appended to `block`.
+pub fn struct_fields<'c, 'a>(block: &'a Block<'c>, value: Value<'c, '_>,
+                             location: Location<'c>) -> Vec<(String, Value<'c, 'a>)> {
+    let r#type = value.r#type().to_raw();
+    let fields: Vec<(String, Type)> = unsafe {
+        (0..mlir_sys::hwStructTypeGetNumFields(r#type)).map(|index| {
+            let field = mlir_sys::hwStructTypeGetFieldNum(r#type, index);
+            let name = StringRef::from_raw(mlir_sys::mlirIdentifierStr(field.name));
+            (name.as_str().unwrap_or_default().to_string(), Type::from_raw(field.type_))
+        }).collect()
+    };
+    let types: Vec<Type> = fields.iter().map(|(_, r#type)| *r#type).collect();
+    let explode = OperationBuilder::new("hw.struct_explode", location)
+        .add_operands(&[value])
+        .add_results(&types)
+        .build()
+        .expect("valid operation");
+    let explode = block.append(explode);
+    fields.into_iter().enumerate()
+        .map(|(index, (name, _))| (name, explode.result(index).unwrap().into()))
+        .collect()
+}
+
+/// `hw.struct_create` of the struct `r#type` from `fields` in field order, for driving a struct
+/// typed output port.
+pub fn struct_create<'c, 'a>(block: &'a Block<'c>, r#type: Type<'c>, fields: &[Value<'c, '_>],
+                             location: Location<'c>) -> Value<'c, 'a> {
+    let create = OperationBuilder::new("hw.struct_create", location)
+        .add_operands(fields)
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    block.append(create).result(0).unwrap().into()
+}
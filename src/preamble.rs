@@ -0,0 +1,201 @@
+//! The register randomization preamble firtool emits, so generated designs behave like firtool
+//! output in existing simulation flows (`+define+RANDOMIZE_REG_INIT` etc.).
+
+use melior::dialect::ods::{comb, sv};
+use melior::ir::attribute::{FlatSymbolRefAttribute, IntegerAttribute, StringAttribute};
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, Location, Type, TypeLike, Value, ValueLike};
+use melior::Context;
+
+use crate::diagnostics::Append;
+use crate::macros::{define, KnownMacro};
+use crate::{bit_width, ifdef, region, Design, Error};
+
+const RANDOM_WIDTH: i64 = 32;
+
+/* The macros the preamble and register initializers test or define. */
+const PREAMBLE: [KnownMacro; 11] = [
+    KnownMacro::Random,
+    KnownMacro::InitRandom,
+    KnownMacro::RandomizeDelay,
+    KnownMacro::InitRandomProlog,
+    KnownMacro::Randomize,
+    KnownMacro::RandomizeRegInit,
+    KnownMacro::Verilator,
+    KnownMacro::Synthesis,
+    KnownMacro::EnableInitialReg,
+    KnownMacro::FirrtlBeforeInitial,
+    KnownMacro::FirrtlAfterInitial,
+];
+
+fn comment<'c>(ctx: &'c Context, block: &Block<'c>, text: &str, location: Location<'c>) {
+    block.append(sv::verbatim(ctx, &[], StringAttribute::new(ctx, text), location).into());
+}
+
+/* `ifdef guard / `define name if_def / `else / `define name if_not_def / `endif */
+fn guarded_define<'c>(ctx: &'c Context, block: &Block<'c>, guard: KnownMacro, name: KnownMacro,
+                      if_def: Option<&str>, if_not_def: Option<&str>, location: Location<'c>) {
+    let name = name.name();
+    let ifdef = ifdef::build(ctx, guard.name(),
+                             |then| if let Some(value) = if_def { define(ctx, then, name, value, location) },
+                             Some(|else_: &Block<'c>| if let Some(value) = if_not_def {
+                                 define(ctx, else_, name, value, location)
+                             }),
+                             false, location);
+    block.append(ifdef);
+}
+
+/* `ifndef guard / body / `endif */
+fn ifndef<'c>(ctx: &'c Context, block: &Block<'c>, guard: KnownMacro, body: impl FnOnce(&Block<'c>),
+              location: Location<'c>) {
+    block.append(ifdef::build(ctx, guard.name(), |_| {}, Some(body), false, location));
+}
+
+/// Append the firtool register randomization preamble to the top level of `design`:
+///
+/// ```verilog
+/// `ifndef RANDOM
+///   `define RANDOM $random
+/// `endif // not def RANDOM
+/// ...
+/// `ifndef SYNTHESIS
+///   `ifndef ENABLE_INITIAL_REG_
+///     `define ENABLE_INITIAL_REG_
+///   `endif // not def ENABLE_INITIAL_REG_
+/// `endif // not def SYNTHESIS
+/// ```
+///
+/// Emit it once per design, before any module that uses [`random_init`].
+pub fn randomize_preamble<'c>(design: &Design<'c>, location: Location<'c>) {
+    let ctx = design.context();
+    for known in PREAMBLE {
+        design.declare_macro(known.name(), location);
+    }
+
+    let top = design.body();
+    comment(ctx, &top, "// Standard header to adapt well known macros for register randomization.", location);
+    ifndef(ctx, &top, KnownMacro::Randomize, |else_| {
+        guarded_define(ctx, else_, KnownMacro::RandomizeRegInit, KnownMacro::Randomize, Some(""), None, location)
+    }, location);
+
+    comment(ctx, &top, "\n// RANDOM may be set to an expression that produces a 32-bit random unsigned value.", location);
+    guarded_define(ctx, &top, KnownMacro::Random, KnownMacro::Random, None, Some("$random"), location);
+
+    comment(ctx, &top, "\n// Users can define INIT_RANDOM as general code that gets injected into the\n\
+                         // initializer block for modules with registers.", location);
+    guarded_define(ctx, &top, KnownMacro::InitRandom, KnownMacro::InitRandom, None, Some(""), location);
+
+    comment(ctx, &top, "\n// If using random initialization, you can also define RANDOMIZE_DELAY to\n\
+                         // customize the delay used, otherwise 0.002 is used.", location);
+    guarded_define(ctx, &top, KnownMacro::RandomizeDelay, KnownMacro::RandomizeDelay, None, Some("0.002"),
+                   location);
+
+    comment(ctx, &top, "\n// Define INIT_RANDOM_PROLOG_ for use in our modules below.", location);
+    ifndef(ctx, &top, KnownMacro::InitRandomProlog, |else_| {
+        let randomize = ifdef::build(ctx, KnownMacro::Randomize.name(),
+                                     |then| guarded_define(ctx, then, KnownMacro::Verilator,
+                                                           KnownMacro::InitRandomProlog,
+                                                           Some("`INIT_RANDOM"),
+                                                           Some("`INIT_RANDOM #`RANDOMIZE_DELAY begin end"),
+                                                           location),
+                                     Some(|else_: &Block<'c>| {
+                                         define(ctx, else_, KnownMacro::InitRandomProlog.name(), "", location)
+                                     }),
+                                     false, location);
+        else_.append(randomize);
+    }, location);
+
+    comment(ctx, &top, "\n// Include register initializers in init blocks unless synthesis is set", location);
+    ifndef(ctx, &top, KnownMacro::Synthesis, |else_| {
+        guarded_define(ctx, else_, KnownMacro::EnableInitialReg, KnownMacro::EnableInitialReg, None, Some(""), location)
+    }, location);
+}
+
+/* `RANDOM is 32 bits wide: concatenate enough of them and take the low `width` bits, which is at
+   least 1. */
+fn random_value<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, width: i64, location: Location<'c>) -> Value<'c, 'a> {
+    let i32_type = IntegerType::new(ctx, RANDOM_WIDTH as u32);
+    let words = (width + RANDOM_WIDTH - 1) / RANDOM_WIDTH;
+    let randoms: Vec<Value> = (0..words).map(|_| {
+        let random = sv::macro_ref_expr_se(ctx, i32_type.into(), &[],
+                                           FlatSymbolRefAttribute::new(ctx, KnownMacro::Random.name()), location);
+        block.append(random.into()).result(0).unwrap().into()
+    }).collect();
+    let mut value = randoms[0];
+    if words > 1 {
+        value = block.append(comb::concat(ctx, &randoms, location).into()).result(0).unwrap().into();
+    }
+    if width != words * RANDOM_WIDTH {
+        let result_type = IntegerType::new(ctx, width as u32);
+        let extract = comb::extract(ctx, result_type.into(), value,
+                                    IntegerAttribute::new(IntegerType::new(ctx, 32).into(), 0), location);
+        value = block.append(extract.into()).result(0).unwrap().into();
+    }
+    value
+}
+
+/// Append the firtool style register initializer for `registers` (`sv.reg` inout values) to
+/// a module body:
+///
+/// ```verilog
+/// `ifdef ENABLE_INITIAL_REG_
+///   `ifdef FIRRTL_BEFORE_INITIAL
+///     `FIRRTL_BEFORE_INITIAL
+///   `endif // FIRRTL_BEFORE_INITIAL
+///   initial begin
+///     `ifdef INIT_RANDOM_PROLOG_
+///       `INIT_RANDOM_PROLOG_
+///     `endif // INIT_RANDOM_PROLOG_
+///     `ifdef RANDOMIZE_REG_INIT
+///       r = `RANDOM;
+///     `endif // RANDOMIZE_REG_INIT
+///   end // initial
+///   `ifdef FIRRTL_AFTER_INITIAL
+///     `FIRRTL_AFTER_INITIAL
+///   `endif // FIRRTL_AFTER_INITIAL
+/// `endif // ENABLE_INITIAL_REG_
+/// ```
+///
+/// The macros are defined by [`randomize_preamble`]. Every register needs a known, non-zero width.
+pub fn random_init<'c>(design: &Design<'c>, block: &Block<'c>, registers: &[Value<'c, '_>],
+                       location: Location<'c>) -> Result<(), Error> {
+    let ctx = design.context();
+    let widths = registers.iter().map(|register| {
+        let element = unsafe { Type::from_raw(mlir_sys::hwInOutTypeGetElementType(register.r#type().to_raw())) };
+        match bit_width(element) {
+            Some(0) => Err(Error::ZeroWidth(element.to_string())),
+            None => Err(Error::UnknownWidth(element.to_string())),
+            Some(width) => Ok(width as i64),
+        }
+    }).collect::<Result<Vec<_>, _>>()?;
+    let hook = |known: KnownMacro| {
+        ifdef::build(ctx, known.name(), |then| comment(ctx, then, &format!("`{known}"), location),
+                     None::<fn(&Block<'c>)>,
+                     false, location)
+    };
+
+    let initial = sv::initial(ctx, region(|initial| {
+        let prolog = ifdef::build(ctx, KnownMacro::InitRandomProlog.name(),
+                                  |then| comment(ctx, then, "`INIT_RANDOM_PROLOG_", location),
+                                  None::<fn(&Block<'c>)>, true, location);
+        initial.append(prolog);
+        let randomize = ifdef::build(ctx, KnownMacro::RandomizeRegInit.name(), |then| {
+            for (register, width) in registers.iter().zip(&widths) {
+                let random = random_value(ctx, then, *width, location);
+                then.append(sv::bpassign(ctx, *register, random, location).into());
+            }
+        }, None::<fn(&Block<'c>)>, true, location);
+        initial.append(randomize);
+    }), location);
+
+    let ordered = sv::ordered(ctx, region(|ordered| {
+        ordered.append(hook(KnownMacro::FirrtlBeforeInitial));
+        ordered.append(initial.into());
+        ordered.append(hook(KnownMacro::FirrtlAfterInitial));
+    }), location);
+
+    let enable = ifdef::build(ctx, KnownMacro::EnableInitialReg.name(), |then| { then.append(ordered.into()); },
+                              None::<fn(&Block<'c>)>, false, location);
+    block.append(enable);
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! The types this crate's API uses, re-exported from the melior version it is built against, so
+//! users don't need a matching melior dependency of their own.
+//!
+//! ```ignore
+//! use circt_sv_basic::prelude::*;
+//!
+//! let ctx = Context::new();
+//! load_dialects(&ctx);
+//! let design = Design::new(&ctx, here!(ctx));
+//! ```
+
+pub use melior::ir::attribute::{IntegerAttribute, StringAttribute};
+pub use melior::ir::block::BlockRef;
+pub use melior::ir::operation::{OperationLike, OperationRef};
+pub use melior::ir::r#type::IntegerType;
+pub use melior::ir::{
+    Attribute, AttributeLike, Block, BlockLike, Location, Module, Operation, Region, RegionLike, Type, TypeLike,
+    Value, ValueLike,
+};
+pub use melior::Context;
+
+pub use crate::always::EventControl;
+pub use crate::builder::IrBuilder;
+pub use crate::constant::Signedness;
+pub use crate::lowering::{LanguageStandard, LoweringOptions};
+pub use crate::{here, load_dialects, Design, Error};
@@ -0,0 +1,51 @@
+//! Reductions of an integer to a single `i1`, for any/all bit checks and parity.
+//!
+//! ```ignore
+//! let parity = reduce::reduce_xor(&ctx, &body, data, here!(ctx))?;
+//! ```
+
+use melior::dialect::ods::hw;
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, Location, Value, ValueLike};
+use melior::Context;
+
+use crate::compare::{icmp, Predicate};
+use crate::constant::wide_integer_attribute;
+use crate::diagnostics::Append;
+use crate::{bit_width, Error};
+
+/* `hw.constant` with every bit of `value`'s width set to `bit`. */
+fn fill<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, '_>, bit: bool,
+                location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let width = bit_width(value.r#type()).ok_or(Error::UnknownWidth(value.r#type().to_string()))?;
+    let attribute = wide_integer_attribute(ctx, width as u32, if bit { "-1" } else { "0" })?;
+    let constant = hw::constant(ctx, IntegerType::new(ctx, width as u32).into(), attribute, location);
+    Ok(block.append(constant.into()).result(0).unwrap().into())
+}
+
+/// `&value`: an `i1` set when every bit of the integer `value` is set.
+pub fn reduce_and<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, '_>,
+                          location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let ones = fill(ctx, block, value, true, location)?;
+    Ok(icmp(ctx, block, Predicate::Eq, value, ones, location))
+}
+
+/// `|value`: an `i1` set when any bit of the integer `value` is set.
+pub fn reduce_or<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, '_>,
+                         location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let zero = fill(ctx, block, value, false, location)?;
+    Ok(icmp(ctx, block, Predicate::Ne, value, zero, location))
+}
+
+/// `^value`: `comb.parity`, an `i1` set when an odd number of bits of the integer `value` are set.
+pub fn reduce_xor<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, '_>,
+                          location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    bit_width(value.r#type()).ok_or(Error::UnknownWidth(value.r#type().to_string()))?;
+    let parity = OperationBuilder::new("comb.parity", location)
+        .add_operands(&[value])
+        .add_results(&[IntegerType::new(ctx, 1).into()])
+        .build()
+        .expect("valid operation");
+    Ok(block.append(parity).result(0).unwrap().into())
+}
@@ -0,0 +1,130 @@
+//! Registers: a value updated on the rising edge of a clock, with an optional reset of any
+//! [`ResetKind`] and clock enable.
+//!
+//! ```ignore
+//! let reset = Reset { kind: ResetKind::AsyncActiveLow, signal: rst_n, value: zero };
+//! let count = register::register(&design, &body, "count", i8, clk, Some(reset),
+//!                                Some((enable, EnableStyle::CompReg)), |count| Ok(increment(count)),
+//!                                here!(ctx))?;
+//! ```
+
+use melior::dialect::ods::sv;
+use melior::ir::attribute::{DenseI32ArrayAttribute, StringAttribute};
+use melior::ir::operation::{OperationBuilder, OperationLike};
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, Identifier, Location, Type, Value, ValueLike};
+
+use crate::always::{always_ff, always_ff_with_reset, if_, Posedge, ResetKind};
+use crate::constant::{const_int, Signedness};
+use crate::diagnostics::Append;
+use crate::scheduled::to_clock;
+use crate::{decl, region, Design, Error};
+
+/// How a register's clock enable is built.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnableStyle {
+    /// An `sv.reg` whose update in its `always_ff` is wrapped in `if (enable)`, so synthesis can
+    /// map the enable to a gated clock or an enable flop.
+    #[default]
+    Gated,
+    /// A `seq.compreg.ce`, which CIRCT lowers to a register holding its value through a mux while
+    /// the enable is clear.
+    CompReg,
+}
+
+/// A register's reset: while `signal` is asserted, the register loads `value`.
+#[derive(Clone, Copy, Debug)]
+pub struct Reset<'c, 'v> {
+    pub kind: ResetKind,
+    pub signal: Value<'c, 'v>,
+    pub value: Value<'c, 'v>,
+}
+
+/// Append a register named `name` to `block`, updated to `next(current)` on the rising edge of
+/// `clock` and reset by `reset`. With `enable`, it only updates while the enable is set, built as
+/// its [`EnableStyle`] says. Returns the current value.
+pub fn register<'c, 'a>(design: &Design<'c>, block: &'a Block<'c>, name: &str, r#type: Type<'c>,
+                        clock: Value<'c, '_>, reset: Option<Reset<'c, '_>>,
+                        enable: Option<(Value<'c, '_>, EnableStyle)>,
+                        next: impl FnOnce(Value<'c, 'a>) -> Result<Value<'c, 'a>, Error>,
+                        location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    match enable {
+        Some((enable, EnableStyle::CompReg)) => {
+            compreg(design, block, name, r#type, clock, reset, enable, next, location)
+        }
+        enable => gated(design, block, name, r#type, clock, reset, enable.map(|(enable, _)| enable), next,
+                        location),
+    }
+}
+
+/* An `sv.reg` assigned in an `always_ff`, with the reset and enable as `sv.if`s around the
+   update. */
+fn gated<'c, 'a>(design: &Design<'c>, block: &'a Block<'c>, name: &str, r#type: Type<'c>, clock: Value<'c, '_>,
+                 reset: Option<Reset<'c, '_>>, enable: Option<Value<'c, '_>>,
+                 next: impl FnOnce(Value<'c, 'a>) -> Result<Value<'c, 'a>, Error>,
+                 location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let ctx = design.context();
+    let reg = decl::reg(ctx, block, name, r#type, location);
+    let current = block.append(sv::read_inout(ctx, r#type, reg, location).into()).result(0).unwrap().into();
+    let next = next(current)?;
+    let update = |then: &Block<'c>| { then.append(sv::passign(ctx, reg, next, location).into()); };
+    let enabled = |block: &Block<'c>| match enable {
+        Some(enable) => { block.append(if_(enable, region(update), region(|_| {}), location)); }
+        None => update(block),
+    };
+    match reset {
+        Some(reset) => always_ff_with_reset(design, block, Posedge, clock, reset.kind, reset.signal, |initial| {
+            initial.append(sv::passign(ctx, reg, reset.value, location).into());
+        }, enabled, location),
+        None => always_ff(design, block, Posedge, clock, enabled, location),
+    }
+    Ok(current)
+}
+
+/* A `seq.compreg.ce`. Its input is `next` of its own result, so it's built with a placeholder
+   input that's replaced once `next` is. Its reset is synchronous and active high, so an active
+   low one is inverted and an asynchronous one is an error. */
+fn compreg<'c, 'a>(design: &Design<'c>, block: &'a Block<'c>, name: &str, r#type: Type<'c>, clock: Value<'c, '_>,
+                   reset: Option<Reset<'c, '_>>, enable: Value<'c, '_>,
+                   next: impl FnOnce(Value<'c, 'a>) -> Result<Value<'c, 'a>, Error>,
+                   location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let ctx = design.context();
+    let reset = match reset {
+        Some(reset) if reset.kind.is_async() => {
+            let message = format!("`{name}` has an asynchronous reset, which `seq.compreg.ce` doesn't support");
+            return Err(Error::InvalidParameter(message));
+        }
+        Some(Reset { kind: ResetKind::SyncActiveLow, signal, value }) => {
+            let one = const_int(ctx, block, 1, 1, Signedness::Unsigned, location)?;
+            let inverted = OperationBuilder::new("comb.xor", location)
+                .add_operands(&[signal, one])
+                .add_results(&[IntegerType::new(ctx, 1).into()])
+                .build()
+                .expect("valid operation");
+            let signal = block.append(inverted).result(0).unwrap().into();
+            Some(Reset { kind: ResetKind::SyncActiveHigh, signal, value })
+        }
+        reset => reset,
+    };
+    let placeholder = block.append(sv::constant_x(ctx, r#type, location).into());
+    let clock = to_clock(ctx, block, clock, location);
+    let mut operands = vec![placeholder.result(0).unwrap().into(), clock, enable];
+    operands.extend(reset.iter().flat_map(|reset| [reset.signal, reset.value]));
+    let resets = i32::from(reset.is_some());
+    let compreg = OperationBuilder::new("seq.compreg.ce", location)
+        .add_operands(&operands)
+        .add_results(&[r#type])
+        .add_attributes(&[(Identifier::new(ctx, "name"), StringAttribute::new(ctx, name).into()),
+                          (Identifier::new(ctx, "operandSegmentSizes"),
+                           DenseI32ArrayAttribute::new(ctx, &[1, 1, 1, resets, resets, 0]).into())])
+        .build()
+        .expect("valid operation");
+    let compreg = block.append(compreg);
+    let current = compreg.result(0).unwrap().into();
+    let next = next(current)?;
+    unsafe {
+        mlir_sys::mlirOperationSetOperand(compreg.to_raw(), 0, next.to_raw());
+        mlir_sys::mlirOperationDestroy(placeholder.to_raw());
+    }
+    Ok(current)
+}
@@ -0,0 +1,95 @@
+//! Rust source describing the ports and parameters of a [`Design`]'s modules, so firmware and
+//! verification code written in Rust can refer to the hardware interface instead of copying widths
+//! and names by hand.
+//!
+//! For a module `Fifo` with a `WIDTH` parameter and an 8 bit `data` input:
+//!
+//! ```ignore
+//! pub mod fifo {
+//!     pub const MODULE: &str = "Fifo";
+//!     pub const WIDTH: i64 = 8;
+//!     pub mod ports {
+//!         pub const DATA: &str = "data";
+//!         pub const DATA_WIDTH: u32 = 8;
+//!         pub const DATA_DIRECTION: &str = "input";
+//!     }
+//! }
+//! ```
+
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+use melior::ir::operation::OperationLike;
+use melior::ir::Type;
+use regex::Regex;
+
+use crate::ports::module_ports;
+use crate::{bit_width, operations, symbol_name, Design};
+
+/* `#hw.param.decl<"NAME": i32 = 8>`, the printed form of a module parameter. */
+static PARAMETER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"#hw\.param\.decl<"([^"]+)"\s*:\s*[^=>]+(?:=\s*(-?\d+))?"#).unwrap());
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+];
+
+/* `name` as a Rust identifier in lower (module) or upper (constant) snake case. */
+fn identifier(name: &str, upper: bool) -> String {
+    let mut identifier = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && previous_lower {
+            identifier.push('_');
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        identifier.push(if c.is_ascii_alphanumeric() { c } else { '_' });
+    }
+    identifier = if upper { identifier.to_ascii_uppercase() } else { identifier.to_ascii_lowercase() };
+    if identifier.starts_with(|c: char| c.is_ascii_digit()) || identifier.is_empty() {
+        identifier.insert(0, '_');
+    }
+    if RUST_KEYWORDS.contains(&identifier.as_str()) {
+        identifier.push('_');
+    }
+    identifier
+}
+
+/// A Rust module for each `hw.module` and `hw.module.extern` in `design`: the module's name, its
+/// parameters' default values, and the name, width, and direction of each port. Ports without a
+/// fixed width have no width constant.
+pub fn rust_api(design: &Design) -> String {
+    let ctx = design.context();
+    let mut source = String::from("// Generated by circt-sv-basic; do not edit.\n");
+    for operation in operations(&design.body()) {
+        let op = operation.name().as_string_ref().as_str().unwrap_or_default().to_string();
+        if op != "hw.module" && op != "hw.module.extern" {
+            continue;
+        }
+        let Some(name) = symbol_name(&operation) else { continue };
+        let _ = writeln!(source, "\n/// Ports and parameters of `{name}`.");
+        let _ = writeln!(source, "pub mod {} {{", identifier(&name, false));
+        let _ = writeln!(source, "    pub const MODULE: &str = {name:?};");
+        if let Ok(parameters) = operation.attribute("parameters") {
+            for parameter in PARAMETER.captures_iter(&parameters.to_string()) {
+                if let Some(value) = parameter.get(2) {
+                    let _ = writeln!(source, "    pub const {}: i64 = {};", identifier(&parameter[1], true),
+                                     value.as_str());
+                }
+            }
+        }
+        let _ = writeln!(source, "\n    pub mod ports {{");
+        for port in module_ports(&operation) {
+            let constant = identifier(&port.name, true);
+            let _ = writeln!(source, "        pub const {constant}: &str = {:?};", port.name);
+            if let Some(width) = Type::parse(ctx, &port.r#type).and_then(bit_width) {
+                let _ = writeln!(source, "        pub const {constant}_WIDTH: u32 = {width};");
+            }
+            let _ = writeln!(source, "        pub const {constant}_DIRECTION: &str = {:?};", port.direction.keyword());
+        }
+        let _ = writeln!(source, "    }}\n}}");
+    }
+    source
+}
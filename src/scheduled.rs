@@ -0,0 +1,154 @@
+//! `pipeline.scheduled` builders, for describing a deeply pipelined datapath stage by stage and
+//! letting [`PassPipeline::lower_pipelines`](crate::pipeline::PassPipeline::lower_pipelines)
+//! insert the stage registers and valid/stall logic.
+//!
+//! ```ignore
+//! let control = Control { clock, reset, go, stall: Some(stall) };
+//! let (outputs, done) = scheduled::pipeline(&ctx, &body, "mul", &[("a", a), ("b", b)], &[("product", i32)],
+//!                                           control, |stages| {
+//!     let product = multiply(&stages.block(), stages.input(0), stages.input(1));
+//!     Ok(stages.stage(&[("product", product)]))
+//! }, here!(ctx))?;
+//! ```
+
+use melior::ir::attribute::{ArrayAttribute, DenseI32ArrayAttribute, IntegerAttribute, StringAttribute};
+use melior::ir::block::BlockRef;
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Attribute, Block, BlockLike, Identifier, Location, Region, RegionLike, Type, Value, ValueLike};
+use melior::Context;
+
+use crate::diagnostics::Append;
+use crate::Error;
+
+/// The control signals of a pipeline.
+#[derive(Clone, Copy, Debug)]
+pub struct Control<'c, 'a> {
+    /// An `i1` clock, converted to a `!seq.clock` for the pipeline.
+    pub clock: Value<'c, 'a>,
+    /// Synchronous, active high reset, which also flushes the stages' valid bits.
+    pub reset: Value<'c, 'a>,
+    /// Set when the inputs are valid and should enter the pipeline.
+    pub go: Value<'c, 'a>,
+    /// While set, every stage holds its registers.
+    pub stall: Option<Value<'c, 'a>>,
+}
+
+/// The stages of a pipeline being built by [`pipeline`]. Operations are appended to the current
+/// stage's [`Stages::block`] until [`Stages::stage`] registers values into the next one.
+pub struct Stages<'c, 'r> {
+    ctx: &'c Context,
+    region: &'r Region<'c>,
+    current: BlockRef<'c, 'r>,
+    location: Location<'c>,
+}
+
+impl<'c, 'r> Stages<'c, 'r> {
+    /// The block of the current stage.
+    pub fn block(&self) -> BlockRef<'c, 'r> {
+        self.current
+    }
+
+    /// The current stage's enable: set when its values are valid and the pipeline isn't stalled.
+    pub fn enable(&self) -> Value<'c, 'r> {
+        let count = self.current.argument_count();
+        self.current.argument(count - 1).unwrap().into()
+    }
+
+    /// Input `index` of the pipeline, in the entry stage.
+    pub fn input(&self, index: usize) -> Value<'c, 'r> {
+        self.current.argument(index).unwrap().into()
+    }
+
+    /// End the current stage with `pipeline.stage`, registering `registers` into a new stage and
+    /// returning their registered values there, in order.
+    pub fn stage(&mut self, registers: &[(&str, Value<'c, '_>)]) -> Vec<Value<'c, 'r>> {
+        let ctx = self.ctx;
+        let mut arguments: Vec<(Type, Location)> = registers.iter()
+            .map(|(_, value)| (value.r#type(), self.location))
+            .collect();
+        arguments.push((IntegerType::new(ctx, 1).into(), self.location));
+        let next = self.region.append_block(Block::new(&arguments));
+        let values: Vec<Value> = registers.iter().map(|(_, value)| *value).collect();
+        let names: Vec<Attribute> = registers.iter().map(|(name, _)| StringAttribute::new(ctx, name).into()).collect();
+        let no_clock_gates: Vec<Attribute> = registers.iter()
+            .map(|_| IntegerAttribute::new(IntegerType::new(ctx, 64).into(), 0).into())
+            .collect();
+        let stage = OperationBuilder::new("pipeline.stage", self.location)
+            .add_operands(&values)
+            .add_successors(&[&*next])
+            .add_attributes(&[(Identifier::new(ctx, "operandSegmentSizes"),
+                               DenseI32ArrayAttribute::new(ctx, &[values.len() as i32, 0, 0]).into()),
+                              (Identifier::new(ctx, "clockGatesPerRegister"),
+                               ArrayAttribute::new(ctx, &no_clock_gates).into()),
+                              (Identifier::new(ctx, "registerNames"), ArrayAttribute::new(ctx, &names).into())])
+            .build()
+            .expect("valid operation");
+        self.current.append(stage);
+        self.current = next;
+        (0..registers.len()).map(|index| next.argument(index).unwrap().into()).collect()
+    }
+}
+
+/* `seq.to_clock`, converting an `i1` to the `!seq.clock` that `seq` and `pipeline` ops take. */
+pub(crate) fn to_clock<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, clock: Value<'c, '_>,
+                               location: Location<'c>) -> Value<'c, 'a> {
+    let clock_type = Type::parse(ctx, "!seq.clock").expect("valid clock type");
+    let to_clock = OperationBuilder::new("seq.to_clock", location)
+        .add_operands(&[clock])
+        .add_results(&[clock_type])
+        .build()
+        .expect("valid operation");
+    block.append(to_clock).result(0).unwrap().into()
+}
+
+/// Append `pipeline.scheduled` named `name` to the module `block`. `body` builds the stages from
+/// the entry stage, whose block arguments are `inputs`, and returns the values of `outputs` from
+/// the last stage. Returns the outputs and the pipeline's `done` signal, set when they are valid.
+pub fn pipeline<'c, 'a, F>(ctx: &'c Context, block: &'a Block<'c>, name: &str, inputs: &[(&str, Value<'c, '_>)],
+                           outputs: &[(&str, Type<'c>)], control: Control<'c, '_>, body: F,
+                           location: Location<'c>) -> Result<(Vec<Value<'c, 'a>>, Value<'c, 'a>), Error>
+where
+    F: for<'r> FnOnce(&mut Stages<'c, 'r>) -> Result<Vec<Value<'c, 'r>>, Error>,
+{
+    let i1: Type = IntegerType::new(ctx, 1).into();
+    let region = Region::new();
+    let mut arguments: Vec<(Type, Location)> = inputs.iter().map(|(_, value)| (value.r#type(), location)).collect();
+    arguments.push((i1, location));
+    let entry = region.append_block(Block::new(&arguments));
+    let mut stages = Stages { ctx, region: &region, current: entry, location };
+    let results = body(&mut stages)?;
+    let output = OperationBuilder::new("pipeline.return", location)
+        .add_operands(&results)
+        .build()
+        .expect("valid operation");
+    stages.current.append(output);
+
+    let names = |names: Vec<&str>| -> Attribute<'c> {
+        let names: Vec<Attribute> = names.into_iter().map(|name| StringAttribute::new(ctx, name).into()).collect();
+        ArrayAttribute::new(ctx, &names).into()
+    };
+    let clock = to_clock(ctx, block, control.clock, location);
+    let mut operands: Vec<Value> = inputs.iter().map(|(_, value)| *value).collect();
+    operands.extend(control.stall);
+    operands.extend([clock, control.reset, control.go]);
+    let mut result_types: Vec<Type> = outputs.iter().map(|(_, r#type)| *r#type).collect();
+    result_types.push(i1);
+    let input_names = names(inputs.iter().map(|(name, _)| *name).collect());
+    let output_names = names(outputs.iter().map(|(name, _)| *name).collect());
+    let segments = [inputs.len() as i32, i32::from(control.stall.is_some()), 1, 1, 1];
+    let pipeline = OperationBuilder::new("pipeline.scheduled", location)
+        .add_operands(&operands)
+        .add_results(&result_types)
+        .add_attributes(&[(Identifier::new(ctx, "operandSegmentSizes"),
+                           DenseI32ArrayAttribute::new(ctx, &segments).into()),
+                          (Identifier::new(ctx, "name"), StringAttribute::new(ctx, name).into()),
+                          (Identifier::new(ctx, "inputNames"), input_names),
+                          (Identifier::new(ctx, "outputNames"), output_names)])
+        .add_regions([region])
+        .build()
+        .expect("valid operation");
+    let pipeline = block.append(pipeline);
+    let values = (0..outputs.len()).map(|index| pipeline.result(index).unwrap().into()).collect();
+    Ok((values, pipeline.result(outputs.len()).unwrap().into()))
+}
@@ -0,0 +1,74 @@
+//! Modules described by a JSON spec, for driving the generator from outside Rust (see
+//! [`crate::capi`]). A spec gives a module's name and ports; outputs are tied to zero, so the
+//! result is a stub with the right interface to fill in or integrate against.
+//!
+//! ```json
+//! { "name": "adder", "ports": [{ "name": "a", "direction": "input", "type": "i8" },
+//!                               { "name": "sum", "direction": "output", "type": "i9" }] }
+//! ```
+
+use serde_json::Value as Json;
+
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Location, Type, Value};
+
+use crate::cast::bitcast;
+use crate::constant::{const_int, Signedness};
+use crate::legalize::Legalizer;
+use crate::ports::{module, Port, PortDirection};
+use crate::{bit_width, Design, Error};
+
+/// A module's name and ports.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleSpec {
+    pub name: String,
+    pub ports: Vec<Port>,
+}
+
+impl ModuleSpec {
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        let spec: Json = serde_json::from_str(text).map_err(|err| Error::InvalidSpec(err.to_string()))?;
+        let field = |value: &Json, name: &str| -> Result<String, Error> {
+            value[name].as_str().map(str::to_string).ok_or_else(|| Error::InvalidSpec(format!("missing `{name}`")))
+        };
+        let mut ports = Vec::new();
+        for port in spec["ports"].as_array().map(Vec::as_slice).unwrap_or_default() {
+            let direction = field(port, "direction")?;
+            ports.push(Port {
+                name: field(port, "name")?,
+                direction: PortDirection::from_keyword(&direction)
+                    .ok_or_else(|| Error::InvalidSpec(format!("unknown port direction `{direction}`")))?,
+                r#type: field(port, "type")?,
+            });
+        }
+        Ok(Self { name: field(&spec, "name")?, ports })
+    }
+
+    /// Append the module to `design`, with its module and port names legalized, returning the
+    /// module's name.
+    pub fn build<'c>(&self, design: &Design<'c>, location: Location<'c>) -> Result<String, Error> {
+        let ctx = design.context();
+        let name = design.module_name(&self.name);
+        let mut port_names = Legalizer::new();
+        let names: Vec<String> = self.ports.iter().map(|port| port_names.legalize(&port.name)).collect();
+        let types = self.ports.iter().map(|port| port.parse_type(ctx)).collect::<Result<Vec<Type>, _>>()?;
+        let ports: Vec<_> = names.iter().zip(&self.ports).zip(&types)
+            .map(|((name, port), r#type)| (name.as_str(), port.direction, *r#type))
+            .collect();
+        module(design, &name, &ports, |body| {
+            let mut outputs: Vec<Value> = Vec::new();
+            for (_, _, r#type) in ports.iter().filter(|(_, direction, _)| *direction == PortDirection::Output) {
+                let width = bit_width(*r#type).ok_or_else(|| Error::UnknownWidth(r#type.to_string()))?;
+                let zero = const_int(ctx, body, width as u32, 0, Signedness::Unsigned, location)?;
+                let output = if IntegerType::try_from(*r#type).is_ok() {
+                    zero
+                } else {
+                    bitcast(ctx, body, zero, *r#type, location)?
+                };
+                outputs.push(output);
+            }
+            Ok(outputs)
+        }, location)?;
+        Ok(name)
+    }
+}
@@ -0,0 +1,67 @@
+//! Metrics about a generated design, for finding the hotspots of large generators.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use melior::ir::operation::OperationLike;
+
+use crate::hierarchy::InstanceGraph;
+use crate::ports::find_module;
+use crate::{walk, Design};
+
+/// The operations in one module's body.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleStats {
+    pub name: String,
+    /// Operation name -> how many the module contains, including nested ones.
+    pub operations: BTreeMap<String, usize>,
+}
+
+impl ModuleStats {
+    pub fn total(&self) -> usize {
+        self.operations.values().sum()
+    }
+}
+
+/// The operation counts of every module in `design`, sorted by module name.
+pub fn module_stats(design: &Design) -> Vec<ModuleStats> {
+    InstanceGraph::new(design).modules().filter_map(|name| {
+        let module = find_module(design, name)?;
+        let mut operations = BTreeMap::new();
+        walk(&module, &mut |operation| {
+            let name = operation.name().as_string_ref().as_str().unwrap_or_default().to_string();
+            *operations.entry(name).or_insert(0) += 1;
+        });
+        Some(ModuleStats { name: name.to_string(), operations })
+    }).collect()
+}
+
+/// A report of a generator run. The times are measured by the caller.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub modules: Vec<ModuleStats>,
+    /// Time spent building the design.
+    pub generation: Duration,
+    pub verification: Duration,
+    /// The files emitted and their sizes in bytes.
+    pub files: Vec<(PathBuf, u64)>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "generation: {:.3?}", self.generation)?;
+        writeln!(f, "verification: {:.3?}", self.verification)?;
+        for module in &self.modules {
+            writeln!(f, "module {}: {} operations", module.name, module.total())?;
+            for (operation, count) in &module.operations {
+                writeln!(f, "  {count:>8} {operation}")?;
+            }
+        }
+        for (file, size) in &self.files {
+            writeln!(f, "file {}: {size} bytes", file.display())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+//! Tri-state drivers, `assign pad = oe ? data : 'z`, for pad and IO cell wrappers driving an
+//! inout port.
+//!
+//! ```ignore
+//! tristate::drive(&ctx, &body, pad, output_enable, data, here!(ctx))?;
+//! let pad_in = tristate::read(&ctx, &body, pad, here!(ctx));
+//! ```
+
+use melior::dialect::ods::sv;
+use melior::ir::operation::OperationBuilder;
+use melior::ir::{Block, Location, Type, TypeLike, Value, ValueLike};
+use melior::Context;
+
+use crate::constant::constant_z;
+use crate::diagnostics::Append;
+use crate::Error;
+
+/* The type an `!hw.inout` carries. */
+fn element_type<'c>(inout: Value<'c, '_>) -> Type<'c> {
+    unsafe { Type::from_raw(mlir_sys::hwInOutTypeGetElementType(inout.r#type().to_raw())) }
+}
+
+/// Append a continuous assignment driving the inout `pad` with `data` while `enable` is set and
+/// releasing it to high impedance otherwise. `pad` must be an `!hw.inout` of `data`'s type.
+pub fn drive<'c>(ctx: &'c Context, block: &Block<'c>, pad: Value<'c, '_>, enable: Value<'c, '_>,
+                 data: Value<'c, '_>, location: Location<'c>) -> Result<(), Error> {
+    if !unsafe { mlir_sys::hwTypeIsAInOut(pad.r#type().to_raw()) } {
+        return Err(Error::TypeMismatch { expected: "an inout".to_string(), found: pad.r#type().to_string() });
+    }
+    let r#type = element_type(pad);
+    if r#type != data.r#type() {
+        return Err(Error::TypeMismatch { expected: format!("a `{}`", r#type), found: data.r#type().to_string() });
+    }
+    let released = constant_z(ctx, block, r#type, location);
+    let mux = OperationBuilder::new("comb.mux", location)
+        .add_operands(&[enable, data, released])
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    let driven = block.append(mux).result(0).unwrap().into();
+    block.append(sv::assign(ctx, pad, driven, location).into());
+    Ok(())
+}
+
+/// Append `sv.read_inout` of `pad`: the value on the pad, whether this or another driver drives it.
+pub fn read<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, pad: Value<'c, '_>,
+                    location: Location<'c>) -> Value<'c, 'a> {
+    block.append(sv::read_inout(ctx, element_type(pad), pad, location).into()).result(0).unwrap().into()
+}
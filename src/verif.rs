@@ -0,0 +1,216 @@
+//! `verif` dialect properties, which the formal exports ([`export_btor2`](crate::export::export_btor2),
+//! ...) check and ExportVerilog emits as immediate assertions.
+//!
+//! ```ignore
+//! let in_range = comb::icmp(&ctx, ..);
+//! verif::assert(&ctx, block, in_range, Some("count_in_range"), here!(ctx));
+//! ```
+//!
+//! Immediate assertions in combinational code can fail on glitches while their inputs settle;
+//! [`assert_deferred`] and friends emit `sv.assert` and co. with a [`Defer`] mode instead.
+//!
+//! [`guard_assertions`] puts all of a design's verification code behind the `` `ifdef `` guards
+//! its [`AssertionGuard`] names, which the exports apply.
+
+use melior::ir::attribute::{IntegerAttribute, StringAttribute};
+use melior::ir::operation::{OperationBuilder, OperationLike, OperationRef};
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Attribute, Block, BlockLike, Identifier, Location, Operation, Value};
+use melior::Context;
+use mlir_sys::MlirOperation;
+
+use crate::diagnostics::Append;
+use crate::macros::KnownMacro;
+use crate::{ifdef, macros, walk, Design};
+
+fn property<'c>(ctx: &'c Context, block: &Block<'c>, name: &str, property: Value<'c, '_>, label: Option<&str>,
+                location: Location<'c>) {
+    let mut builder = OperationBuilder::new(name, location).add_operands(&[property]);
+    if let Some(label) = label {
+        builder = builder.add_attributes(&[(Identifier::new(ctx, "label"), StringAttribute::new(ctx, label).into())]);
+    }
+    block.append(builder.build().expect("valid operation"));
+}
+
+/// Append `verif.assert property label "label" : i1` to `block`: `property` must always hold.
+pub fn assert<'c>(ctx: &'c Context, block: &Block<'c>, property: Value<'c, '_>, label: Option<&str>,
+                  location: Location<'c>) {
+    self::property(ctx, block, "verif.assert", property, label, location)
+}
+
+/// Append `verif.assume property`, constraining the inputs formal tools consider.
+pub fn assume<'c>(ctx: &'c Context, block: &Block<'c>, property: Value<'c, '_>, label: Option<&str>,
+                  location: Location<'c>) {
+    self::property(ctx, block, "verif.assume", property, label, location)
+}
+
+/// Append `verif.cover property`, a condition formal tools should show can be reached.
+pub fn cover<'c>(ctx: &'c Context, block: &Block<'c>, property: Value<'c, '_>, label: Option<&str>,
+                 location: Location<'c>) {
+    self::property(ctx, block, "verif.cover", property, label, location)
+}
+
+/// When an immediate assertion is checked, `sv::DeferAssert` in CIRCT.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Defer {
+    /// `assert (...)`, checked as soon as it is executed.
+    #[default]
+    Immediate,
+    /// `assert #0 (...)`, checked in the Observed region once the time step's values settle.
+    Observed,
+    /// `assert final (...)`, checked at the end of the time step.
+    Final,
+}
+
+impl Defer {
+    /// The `sv::DeferAssert` enum attribute for this mode.
+    pub fn attribute<'c>(self, ctx: &'c Context) -> Attribute<'c> {
+        let value = match self {
+            Defer::Immediate => 0,
+            Defer::Observed => 1,
+            Defer::Final => 2,
+        };
+        IntegerAttribute::new(IntegerType::new(ctx, 32).into(), value).into()
+    }
+}
+
+fn deferred<'c>(ctx: &'c Context, block: &Block<'c>, name: &str, property: Value<'c, '_>, defer: Defer,
+                label: Option<&str>, location: Location<'c>) {
+    let mut builder = OperationBuilder::new(name, location)
+        .add_operands(&[property])
+        .add_attributes(&[(Identifier::new(ctx, "defer"), defer.attribute(ctx))]);
+    if let Some(label) = label {
+        builder = builder.add_attributes(&[(Identifier::new(ctx, "label"), StringAttribute::new(ctx, label).into())]);
+    }
+    block.append(builder.build().expect("valid operation"));
+}
+
+/// Append `sv.assert property, <defer>` to the procedural `block`, an immediate assertion checked
+/// as `defer` says.
+pub fn assert_deferred<'c>(ctx: &'c Context, block: &Block<'c>, property: Value<'c, '_>, defer: Defer,
+                           label: Option<&str>, location: Location<'c>) {
+    deferred(ctx, block, "sv.assert", property, defer, label, location)
+}
+
+/// Append `sv.assume property, <defer>` to the procedural `block`.
+pub fn assume_deferred<'c>(ctx: &'c Context, block: &Block<'c>, property: Value<'c, '_>, defer: Defer,
+                           label: Option<&str>, location: Location<'c>) {
+    deferred(ctx, block, "sv.assume", property, defer, label, location)
+}
+
+/// Append `sv.cover property, <defer>` to the procedural `block`.
+pub fn cover_deferred<'c>(ctx: &'c Context, block: &Block<'c>, property: Value<'c, '_>, defer: Defer,
+                          label: Option<&str>, location: Location<'c>) {
+    deferred(ctx, block, "sv.cover", property, defer, label, location)
+}
+
+/// Which `` `ifdef `` guards [`guard_assertions`] wraps assertions, assumptions, and cover points
+/// in, set for a design with [`Design::set_assertion_guard`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AssertionGuard {
+    /// No guards.
+    #[default]
+    Unguarded,
+    /// `` `ifndef SYNTHESIS ``, so synthesis tools never see them.
+    NotSynthesis,
+    /// `` `ifdef ASSERT_ON ``, so they are off unless the simulator defines `ASSERT_ON`.
+    AssertOn,
+    /// `` `ifndef SYNTHESIS `` around `` `ifdef ASSERT_ON ``.
+    NotSynthesisAssertOn,
+}
+
+/* The ops `guard_assertions` guards. */
+const ASSERTION_OPS: &[&str] = &[
+    "verif.assert", "verif.assume", "verif.cover", "verif.clocked_assert", "verif.clocked_assume",
+    "verif.clocked_cover", "sv.assert", "sv.assume", "sv.cover", "sv.assert.concurrent", "sv.assume.concurrent",
+    "sv.cover.concurrent",
+];
+
+/* The ops whose regions are not procedural, so need `sv.ifdef` rather than `sv.ifdef.procedural`. */
+const NON_PROCEDURAL_OPS: &[&str] = &["hw.module", "sv.ifdef", "builtin.module"];
+
+fn name_of<'a>(operation: &'a Operation) -> &'a str {
+    operation.name().as_string_ref().as_str().unwrap_or_default()
+}
+
+/* Whether `operation` is the innermost guard `guard` puts assertions in, from an earlier call. */
+fn is_guard(design: &Design, operation: &Operation, guard: AssertionGuard) -> bool {
+    let cond = match guard {
+        AssertionGuard::Unguarded => return false,
+        AssertionGuard::NotSynthesis => KnownMacro::Synthesis,
+        AssertionGuard::AssertOn | AssertionGuard::NotSynthesisAssertOn => KnownMacro::AssertOn,
+    };
+    name_of(operation).starts_with("sv.ifdef")
+        && operation.attribute("cond").is_ok_and(|attribute| {
+            attribute == macros::ident(design.context(), &design.macro_name(cond.name()))
+        })
+}
+
+/// Wrap each run of consecutive assertions, assumptions, and cover points in `design` in the
+/// guards of its [`Design::assertion_guard`], declaring `ASSERT_ON` if it is needed. Runs already
+/// guarded are left alone, so this can run before every export; it does nothing for unguarded
+/// designs.
+pub fn guard_assertions(design: &Design) {
+    let guard = design.assertion_guard();
+    if guard == AssertionGuard::Unguarded {
+        return;
+    }
+    let ctx = design.context();
+    let location = design.module().as_operation().location();
+    design.declare_macro(KnownMacro::Synthesis.name(), location);
+    if guard != AssertionGuard::NotSynthesis {
+        design.declare_macro(KnownMacro::AssertOn.name(), location);
+    }
+    // Runs of assertions following each other in the same block
+    let mut runs: Vec<Vec<MlirOperation>> = Vec::new();
+    walk(&design.module().as_operation(), &mut |operation| {
+        if !ASSERTION_OPS.contains(&name_of(operation)) {
+            return;
+        }
+        let parent = operation.parent_operation();
+        if parent.is_some_and(|parent| is_guard(design, &parent, guard)) {
+            return;
+        }
+        let follows = runs.last().and_then(|run| run.last()).is_some_and(|last| {
+            unsafe { mlir_sys::mlirOperationGetNextInBlock(*last).ptr == operation.to_raw().ptr }
+        });
+        match runs.last_mut() {
+            Some(run) if follows => run.push(operation.to_raw()),
+            _ => runs.push(vec![operation.to_raw()]),
+        }
+    });
+    for run in runs {
+        let (first, last) = (run[0], run[run.len() - 1]);
+        let (block, next, procedural) = unsafe {
+            let block = mlir_sys::mlirOperationGetBlock(first);
+            let parent = OperationRef::from_raw(mlir_sys::mlirBlockGetParentOperation(block));
+            (block, mlir_sys::mlirOperationGetNextInBlock(last), !NON_PROCEDURAL_OPS.contains(&name_of(&parent)))
+        };
+        let fill = |then: &Block<'_>| {
+            for operation in &run {
+                unsafe {
+                    mlir_sys::mlirOperationRemoveFromParent(*operation);
+                    mlir_sys::mlirBlockAppendOwnedOperation(then.to_raw(), *operation);
+                }
+            }
+        };
+        let synthesis = design.macro_name(KnownMacro::Synthesis.name());
+        let assert_on = design.macro_name(KnownMacro::AssertOn.name());
+        let guarded = match guard {
+            AssertionGuard::Unguarded => unreachable!("returned early"),
+            AssertionGuard::NotSynthesis => ifdef::build(ctx, &synthesis, |_| {}, Some(fill), procedural, location),
+            AssertionGuard::AssertOn => ifdef::build(ctx, &assert_on, fill, None::<fn(&Block)>, procedural, location),
+            AssertionGuard::NotSynthesisAssertOn => ifdef::build(ctx, &synthesis, |_| {}, Some(|else_: &Block<'_>| {
+                else_.append(ifdef::build(ctx, &assert_on, fill, None::<fn(&Block)>, procedural, location));
+            }), procedural, location),
+        };
+        unsafe {
+            let guarded = guarded.into_raw();
+            if next.ptr.is_null() {
+                mlir_sys::mlirBlockAppendOwnedOperation(block, guarded);
+            } else {
+                mlir_sys::mlirBlockInsertOwnedOperationBefore(block, next, guarded);
+            }
+        }
+    }
+}
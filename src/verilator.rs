@@ -0,0 +1,150 @@
+//! A Verilator C++ harness skeleton for a top module: a class wrapping the verilated model with a
+//! clock, a reset sequence, and an accessor for each port, to start a testbench from.
+//!
+//! ```ignore
+//! let options = HarnessOptions { clock: Some("clk".into()), ..HarnessOptions::default() };
+//! std::fs::write("Top_harness.cpp", verilator::harness(&design, "Top", &options)?)?;
+//! ```
+
+use std::fmt::Write;
+
+use crate::ports::{find_module, module_ports, Port, PortDirection};
+use crate::{Design, Error};
+
+/// Options for [`harness`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HarnessOptions {
+    /// The clock input. When not set, an input named `clock` or `clk` is used.
+    pub clock: Option<String>,
+    /// The reset input. When not set, an input named `reset` or `rst` is used if there is one,
+    /// and the harness has no reset sequence otherwise.
+    pub reset: Option<String>,
+    /// Whether the reset is asserted low.
+    pub reset_active_low: bool,
+    /// Cycles the reset sequence holds the reset for.
+    pub reset_cycles: u32,
+    /// Also trace the model to a VCD file.
+    pub trace: bool,
+}
+
+impl Default for HarnessOptions {
+    fn default() -> Self {
+        Self { clock: None, reset: None, reset_active_low: false, reset_cycles: 4, trace: false }
+    }
+}
+
+/* The input of `ports` named `name`, or the first named one of `defaults`. */
+fn input<'p>(ports: &'p [Port], module: &str, name: Option<&str>,
+             defaults: &[&str]) -> Result<Option<&'p Port>, Error> {
+    let inputs = || ports.iter().filter(|port| port.direction == PortDirection::Input);
+    match name {
+        Some(name) => inputs().find(|port| port.name == name)
+            .map(Some)
+            .ok_or_else(|| Error::UnknownPort(format!("{module}::{name}"))),
+        None => Ok(defaults.iter().find_map(|name| inputs().find(|port| port.name == *name))),
+    }
+}
+
+/* The width of `port` as Verilator sees it; clocks are single bits, and `module_ports` reports the element
+   type of an inout port, whose member is as wide as that. */
+fn width(design: &Design, port: &Port) -> Result<u64, Error> {
+    if port.r#type == "!seq.clock" {
+        return Ok(1);
+    }
+    let r#type = port.parse_type(design.context())?;
+    crate::bit_width(r#type).ok_or_else(|| Error::UnknownWidth(port.r#type.clone()))
+}
+
+/// A C++ harness for `top` verilated as `V<top>`: a `<top>Harness` class with `tick()` toggling
+/// the clock for a cycle, `reset()` holding the reset for [`HarnessOptions::reset_cycles`], and
+/// a `get_` accessor for each port plus a `set_` one for each input. Ports wider than 64 bits are accessed as
+/// Verilator's `VlWide` words.
+pub fn harness(design: &Design, top: &str, options: &HarnessOptions) -> Result<String, Error> {
+    let module = find_module(design, top).ok_or_else(|| Error::UnknownModule(top.to_string()))?;
+    let ports = module_ports(&module);
+    let clock = input(&ports, top, options.clock.as_deref(), &["clock", "clk"])?
+        .ok_or_else(|| Error::UnknownPort(format!("{top}::clock")))?;
+    let reset = input(&ports, top, options.reset.as_deref(), &["reset", "rst"])?;
+    let (asserted, deasserted) = if options.reset_active_low { (0, 1) } else { (1, 0) };
+    let class = format!("{top}Harness");
+    let model = format!("V{top}");
+
+    let mut source = String::from("// Generated by circt-sv-basic; a starting point for a testbench.\n");
+    let _ = writeln!(source, "#include <cstdint>\n#include <memory>\n\n#include \"verilated.h\"");
+    if options.trace {
+        let _ = writeln!(source, "#include \"verilated_vcd_c.h\"");
+    }
+    let _ = writeln!(source, "#include \"{model}.h\"\n");
+    let _ = writeln!(source, "class {class} {{\n  public:");
+    let _ = writeln!(source, "    {class}() : context(std::make_unique<VerilatedContext>()),");
+    let _ = writeln!(source, "                model(std::make_unique<{model}>(context.get())) {{");
+    if options.trace {
+        let _ = writeln!(source, "        context->traceEverOn(true);");
+        let _ = writeln!(source, "        trace = std::make_unique<VerilatedVcdC>();");
+        let _ = writeln!(source, "        model->trace(trace.get(), 99);");
+        let _ = writeln!(source, "        trace->open(\"{top}.vcd\");");
+    }
+    let _ = writeln!(source, "        model->{} = 0;\n        model->eval();\n    }}\n", clock.name);
+    let _ = writeln!(source, "    ~{class}() {{");
+    if options.trace {
+        let _ = writeln!(source, "        trace->close();");
+    }
+    let _ = writeln!(source, "        model->final();\n    }}\n");
+
+    let _ = writeln!(source, "    /// One clock cycle: a rising then a falling edge of `{}`.", clock.name);
+    let _ = writeln!(source, "    void tick() {{");
+    for level in [1, 0] {
+        let _ = writeln!(source, "        model->{} = {level};\n        model->eval();", clock.name);
+        if options.trace {
+            let _ = writeln!(source, "        trace->dump(context->time());");
+        }
+        let _ = writeln!(source, "        context->timeInc(1);");
+    }
+    let _ = writeln!(source, "    }}\n");
+    if let Some(reset) = reset {
+        let _ = writeln!(source, "    /// Hold `{}` for `cycles` cycles, then release it.", reset.name);
+        let _ = writeln!(source, "    void reset(unsigned cycles = {}) {{", options.reset_cycles);
+        let _ = writeln!(source, "        model->{} = {asserted};", reset.name);
+        let _ = writeln!(source, "        for (unsigned cycle = 0; cycle < cycles; ++cycle) tick();");
+        let _ = writeln!(source, "        model->{} = {deasserted};\n    }}\n", reset.name);
+    }
+
+    for port in &ports {
+        let width = width(design, port)?;
+        let name = &port.name;
+        if width > 64 {
+            let _ = writeln!(source, "    /// `{name}`, {width} bits.");
+            let _ = writeln!(source, "    VlWide<{}>& get_{name}() {{ return model->{name}; }}", width.div_ceil(32));
+            continue;
+        }
+        let r#type = match width {
+            0..=8 => "uint8_t",
+            9..=16 => "uint16_t",
+            17..=32 => "uint32_t",
+            _ => "uint64_t",
+        };
+        let _ = writeln!(source, "    /// `{name}`, {width} bits.");
+        let _ = writeln!(source, "    {type} get_{name}() const {{ return model->{name}; }}");
+        if port.direction == PortDirection::Input {
+            let _ = writeln!(source, "    void set_{name}({type} value) {{ model->{name} = value; }}");
+        }
+    }
+
+    let _ = writeln!(source, "\n  private:");
+    let _ = writeln!(source, "    std::unique_ptr<VerilatedContext> context;");
+    let _ = writeln!(source, "    std::unique_ptr<{model}> model;");
+    if options.trace {
+        let _ = writeln!(source, "    std::unique_ptr<VerilatedVcdC> trace;");
+    }
+    let _ = writeln!(source, "}};\n");
+
+    let _ = writeln!(source, "int main(int argc, char** argv) {{");
+    let _ = writeln!(source, "    Verilated::commandArgs(argc, argv);");
+    let _ = writeln!(source, "    {class} harness;");
+    if reset.is_some() {
+        let _ = writeln!(source, "    harness.reset();");
+    }
+    let _ = writeln!(source, "    for (int cycle = 0; cycle < 100; ++cycle) {{\n        harness.tick();\n    }}");
+    let _ = writeln!(source, "    return 0;\n}}");
+    Ok(source)
+}
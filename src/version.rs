@@ -0,0 +1,45 @@
+//! The versions of the libraries this crate was built against, since skew between melior, the C
+//! API bindings, and the installed CIRCT shows up as confusing failures.
+
+use std::fmt;
+
+use melior::Context;
+
+use crate::DIALECTS;
+
+/// Library versions, as recorded when this crate was built, and the dialects a context has.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Version {
+    pub crate_version: &'static str,
+    /// The LLVM/MLIR release at build time, from `llvm-config --version`. The library loaded at
+    /// run time may be a different build.
+    pub llvm: &'static str,
+    /// The CIRCT version at build time, from `circt-opt --version`.
+    pub circt: &'static str,
+    /// The dialects [`load_dialects`](crate::load_dialects) loads that the context has.
+    pub dialects: Vec<&'static str>,
+}
+
+/// The versions of the linked libraries, and the dialects `ctx` has loaded. Checking them
+/// doesn't load any.
+pub fn version(ctx: &Context) -> Version {
+    let dialects = DIALECTS.iter()
+        .filter(|(_, operation)| ctx.is_registered_operation(operation))
+        .filter_map(|(_, operation)| operation.split_once('.').map(|(dialect, _)| dialect))
+        .collect();
+    Version {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        llvm: env!("CIRCT_SV_BASIC_LLVM_VERSION"),
+        circt: env!("CIRCT_SV_BASIC_CIRCT_VERSION"),
+        dialects,
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "circt-sv-basic {}", self.crate_version)?;
+        writeln!(f, "LLVM/MLIR {} (at build time)", self.llvm)?;
+        writeln!(f, "CIRCT {} (at build time)", self.circt)?;
+        write!(f, "dialects: {}", self.dialects.join(", "))
+    }
+}
@@ -0,0 +1,84 @@
+//! Explicit integer width changes, so mismatched widths are an error where they are made instead
+//! of invalid IR found later.
+//!
+//! ```ignore
+//! let wide = width::sext(&ctx, &body, offset, 32, here!(ctx))?;
+//! let low = width::trunc(&ctx, &body, sum, 8, here!(ctx))?;
+//! ```
+
+use melior::dialect::ods::comb;
+use melior::ir::attribute::IntegerAttribute;
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, Location, Value, ValueLike};
+use melior::Context;
+
+use crate::constant::{const_int, Signedness};
+use crate::diagnostics::Append;
+use crate::Error;
+
+/* The width of `value`, which must be a non-zero width integer, checking resizing it to `to` bits
+   is an `operation`: growing for extensions, shrinking for truncation. */
+fn check(value: Value, to: u64, operation: &'static str) -> Result<u64, Error> {
+    let r#type = IntegerType::try_from(value.r#type()).map_err(|_| {
+        Error::TypeMismatch { expected: "an integer".to_string(), found: value.r#type().to_string() }
+    })?;
+    let from = r#type.width() as u64;
+    if from == 0 {
+        return Err(Error::ZeroWidth(r#type.to_string()));
+    }
+    let valid = match operation {
+        "truncate" => to <= from,
+        _ => to >= from,
+    };
+    if to == 0 || !valid {
+        return Err(Error::InvalidResize { operation, from, to });
+    }
+    Ok(from)
+}
+
+/* `width` bits of `value` starting at `low_bit`. */
+pub(crate) fn extract<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, '_>, low_bit: u64,
+                              width: u64, location: Location<'c>) -> Value<'c, 'a> {
+    let low_bit = IntegerAttribute::new(IntegerType::new(ctx, 32).into(), low_bit as i64);
+    let extract = comb::extract(ctx, IntegerType::new(ctx, width as u32).into(), value, low_bit, location);
+    block.append(extract.into()).result(0).unwrap().into()
+}
+
+/// Zero extend the integer `value` to `width` bits, concatenating zeros above it.
+pub fn zext<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, 'a>, width: u64,
+                    location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let from = check(value, width, "zero extend")?;
+    if from == width {
+        return Ok(value);
+    }
+    let zeros = const_int(ctx, block, (width - from) as u32, 0, Signedness::Unsigned, location)?;
+    Ok(block.append(comb::concat(ctx, &[zeros, value], location).into()).result(0).unwrap().into())
+}
+
+/// Sign extend the integer `value` to `width` bits, replicating its top bit above it.
+pub fn sext<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, 'a>, width: u64,
+                    location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let from = check(value, width, "sign extend")?;
+    if from == width {
+        return Ok(value);
+    }
+    let sign = extract(ctx, block, value, from - 1, 1, location);
+    let replicate = OperationBuilder::new("comb.replicate", location)
+        .add_operands(&[sign])
+        .add_results(&[IntegerType::new(ctx, (width - from) as u32).into()])
+        .build()
+        .expect("valid operation");
+    let signs = block.append(replicate).result(0).unwrap().into();
+    Ok(block.append(comb::concat(ctx, &[signs, value], location).into()).result(0).unwrap().into())
+}
+
+/// Truncate the integer `value` to its low `width` bits.
+pub fn trunc<'c, 'a>(ctx: &'c Context, block: &'a Block<'c>, value: Value<'c, 'a>, width: u64,
+                     location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let from = check(value, width, "truncate")?;
+    if from == width {
+        return Ok(value);
+    }
+    Ok(extract(ctx, block, value, 0, width, location))
+}
@@ -0,0 +1,109 @@
+//! Cross module references: an `hw.hierpath` naming a signal deep in the hierarchy and an
+//! `sv.xmr.ref` reading it, for testbench and debug logic that observes internals without
+//! threading ports through every level.
+//!
+//! ```ignore
+//! let graph = InstanceGraph::new(&design);
+//! let path = [graph.children("Top")[0].clone(), ...];
+//! let count = xmr::reference(&design, &body, "Top", &path, "count", here!(ctx))?;
+//! ```
+
+use melior::ir::attribute::{ArrayAttribute, FlatSymbolRefAttribute, StringAttribute};
+use melior::ir::operation::{OperationBuilder, OperationLike};
+use melior::ir::{Attribute, Block, Identifier, Location, Type, Value, ValueLike};
+use melior::StringRef;
+
+use crate::diagnostics::Append;
+use crate::hierarchy::{instance_symbol, Instance, InstanceGraph};
+use crate::ports::find_module;
+use crate::{operations, symbol_name, walk, Design, Error};
+
+/* The ops declaring a signal an XMR can refer to. */
+const SIGNAL_OPS: &[&str] = &["sv.wire", "sv.reg", "sv.logic"];
+
+/* The declaration of `signal` in `module`, given an inner symbol of its name if it has none.
+   Returns its `!hw.inout` type. */
+fn signal_symbol<'c>(design: &Design<'c>, module: &str, signal: &str) -> Result<Type<'c>, Error> {
+    let ctx = design.context();
+    let module_op = find_module(design, module).ok_or_else(|| Error::UnknownModule(module.to_string()))?;
+    let mut found = None;
+    walk(&module_op, &mut |operation| {
+        if found.is_some() || !SIGNAL_OPS.contains(&operation.name().as_string_ref().as_str().unwrap_or_default()) {
+            return;
+        }
+        let name = operation.attribute("name").ok().and_then(|name| StringAttribute::try_from(name).ok());
+        if name.is_none_or(|name| name.value() != signal) {
+            return;
+        }
+        if operation.attribute("inner_sym").is_err() {
+            let symbol = Attribute::parse(ctx, &format!("#hw<innerSym@{signal}>")).expect("valid inner symbol");
+            unsafe {
+                mlir_sys::mlirOperationSetAttributeByName(operation.to_raw(), StringRef::new("inner_sym").to_raw(),
+                                                          symbol.to_raw());
+            }
+        }
+        found = operation.result(0).ok().map(|result| result.r#type());
+    });
+    found.ok_or_else(|| Error::UnknownSignal(format!("{module}::{signal}")))
+}
+
+/// The `hw.hierpath` from `top` through the instances `path` to the wire, reg, or logic `leaf`
+/// in the last instance's module, adding it to `design` unless it is there already. Each instance
+/// must be instantiated by the module before it, per the design's [`InstanceGraph`]. Returns the
+/// path's symbol name and the type of the signal.
+pub fn hierpath<'c>(design: &Design<'c>, top: &str, path: &[Instance], leaf: &str,
+                    location: Location<'c>) -> Result<(String, Type<'c>), Error> {
+    let ctx = design.context();
+    let graph = InstanceGraph::new(design);
+    let top = design.module_name(top);
+    if !graph.contains(&top) {
+        return Err(Error::UnknownModule(top));
+    }
+    let mut module = top.clone();
+    let mut references = Vec::new();
+    for instance in path {
+        if !graph.children(&module).contains(instance) {
+            return Err(Error::UnknownModule(format!("{module}/{}", instance.name)));
+        }
+        instance_symbol(design, &module, &instance.name)?;
+        references.push(format!("#hw.innerNameRef<@{module}::@{}>", instance.name));
+        module = instance.module.clone();
+    }
+    let r#type = signal_symbol(design, &module, leaf)?;
+    references.push(format!("#hw.innerNameRef<@{module}::@{leaf}>"));
+
+    let mut name = vec![top.as_str()];
+    name.extend(path.iter().map(|instance| instance.name.as_str()));
+    name.push(leaf);
+    let name = design.legal_name(&format!("xmr_{}", name.join("_")));
+    let exists = operations(&design.body()).iter().any(|operation| {
+        operation.name().as_string_ref().as_str() == Ok("hw.hierpath") && symbol_name(operation).as_ref() == Some(&name)
+    });
+    if !exists {
+        let references: Vec<Attribute> = references.iter()
+            .map(|reference| Attribute::parse(ctx, reference).expect("valid inner reference"))
+            .collect();
+        let hierpath = OperationBuilder::new("hw.hierpath", location)
+            .add_attributes(&[(Identifier::new(ctx, "sym_name"), StringAttribute::new(ctx, &name).into()),
+                              (Identifier::new(ctx, "sym_visibility"), StringAttribute::new(ctx, "private").into()),
+                              (Identifier::new(ctx, "namepath"), ArrayAttribute::new(ctx, &references).into())])
+            .build()
+            .expect("valid operation");
+        design.body().append(hierpath);
+    }
+    Ok((name, r#type))
+}
+
+/// Append `sv.xmr.ref` to `block`, an inout referring to `leaf` through [`hierpath`], which is
+/// added to `design` as needed. Read it with `sv.read_inout`.
+pub fn reference<'c, 'a>(design: &Design<'c>, block: &'a Block<'c>, top: &str, path: &[Instance], leaf: &str,
+                         location: Location<'c>) -> Result<Value<'c, 'a>, Error> {
+    let ctx = design.context();
+    let (name, r#type) = hierpath(design, top, path, leaf, location)?;
+    let xmr = OperationBuilder::new("sv.xmr.ref", location)
+        .add_attributes(&[(Identifier::new(ctx, "ref"), FlatSymbolRefAttribute::new(ctx, &name).into())])
+        .add_results(&[r#type])
+        .build()
+        .expect("valid operation");
+    Ok(block.append(xmr).result(0).unwrap().into())
+}
@@ -0,0 +1,49 @@
+//! Functions taking module names under a naming policy that renames modules, which must accept the
+//! names the modules were built with, as the builders do.
+
+use circt_sv_basic::hierarchy::remove_unreachable;
+use circt_sv_basic::legalize::NamingPolicy;
+use circt_sv_basic::placement::{self, PhysLocation, Primitive};
+use circt_sv_basic::ports::{self, PortDirection};
+use circt_sv_basic::prelude::*;
+
+/* A design prefixing module names with `acme_`, with a `top` instantiating `leaf` as `leaf0`,
+   and an `unused` module. */
+fn prefixed(ctx: &Context) -> Design<'_> {
+    let location = Location::unknown(ctx);
+    let design = Design::new(ctx, location);
+    design.set_naming_policy(NamingPolicy { module_prefix: "acme_".to_string(), ..Default::default() });
+    let i8 = IntegerType::new(ctx, 8).into();
+    let ports = [("a", PortDirection::Input, i8), ("b", PortDirection::Output, i8)];
+    for module in ["leaf", "unused"] {
+        ports::module(&design, module, &ports, |body| Ok(vec![body.argument(0).unwrap().into()]), location).unwrap();
+    }
+    ports::module(&design, "top", &ports, |body| {
+        let a = body.argument(0).unwrap().into();
+        Ok(ports::instance(&design, body, Some("leaf0"), "leaf", &[("a", a)], &[("b", i8)], location))
+    }, location).unwrap();
+    design
+}
+
+#[test]
+fn place_with_module_prefix() {
+    let ctx = Context::new();
+    load_dialects(&ctx);
+    let design = prefixed(&ctx);
+    let site = PhysLocation::new(Primitive::Ff, 1, 2, 0);
+    placement::place(&design, "top", &["leaf0"], site, Location::unknown(&ctx)).unwrap();
+    design.check().unwrap();
+    let ir = design.module().as_operation().to_string();
+    assert!(ir.contains("msft.instance.hierarchy @acme_top"));
+    assert!(ir.contains("@acme_top::@leaf0"));
+}
+
+#[test]
+fn remove_unreachable_with_module_prefix() {
+    let ctx = Context::new();
+    load_dialects(&ctx);
+    let design = prefixed(&ctx);
+    assert_eq!(remove_unreachable(&design, &["missing"]), Err(Error::UnknownModule("acme_missing".to_string())));
+    assert_eq!(remove_unreachable(&design, &["top"]).unwrap(), ["acme_unused"]);
+    design.check().unwrap();
+}
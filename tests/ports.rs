@@ -0,0 +1,121 @@
+//! Ports read back with `module_ports` and declared again, as the batch wrapper and the module
+//! cache do, or declared by `#[derive(Ports)]`, including inout ports, whose type is only wrapped
+//! in `!hw.inout` for the block argument.
+
+use std::{env, fs};
+
+use melior::dialect::ods::hw;
+use melior::ir::attribute::{ArrayAttribute, StringAttribute, TypeAttribute};
+use melior::ir::operation::OperationBuilder;
+use melior::ir::r#type::IntegerType;
+use melior::ir::{Block, BlockLike, Location, Region, RegionLike, Type, Value};
+use melior::Context;
+
+use circt_sv_basic::batch::{self, BatchOptions, ParameterTable};
+use circt_sv_basic::cache::ModuleCache;
+use circt_sv_basic::decl::inout_type;
+use circt_sv_basic::ports::{self, find_module, module_ports, module_type, InOut, Input, Output, Port, PortDirection};
+use circt_sv_basic::{load_dialects, Design, Ports};
+
+/* Append `hw.module @name` with an inout `pad`, an output `q` reading it, and an input `en`,
+   declared in that order. */
+fn pad_module(design: &Design, name: &str, width: u32) {
+    let ctx = design.context();
+    let location = Location::unknown(ctx);
+    let r#type: Type = IntegerType::new(ctx, width).into();
+    let i1: Type = IntegerType::new(ctx, 1).into();
+    let ports = [("pad", PortDirection::InOut, r#type), ("q", PortDirection::Output, r#type),
+                 ("en", PortDirection::Input, i1)];
+    let body = Block::new(&[(inout_type(r#type), location), (i1, location)]);
+    let read = OperationBuilder::new("sv.read_inout", location)
+        .add_operands(&[body.argument(0).unwrap().into()])
+        .add_results(&[r#type])
+        .build()
+        .unwrap();
+    let q: Value = body.append_operation(read).result(0).unwrap().into();
+    body.append_operation(hw::output(ctx, &[q], location).into());
+    let region = Region::new();
+    region.append_block(body);
+    let module = hw::module(ctx, region, StringAttribute::new(ctx, name),
+                            TypeAttribute::new(module_type(ctx, &ports)), ArrayAttribute::new(ctx, &[]), location);
+    design.body().append_operation(module.into());
+}
+
+#[test]
+fn module_ports_in_declaration_order() {
+    let ctx = Context::new();
+    load_dialects(&ctx);
+    let design = Design::new(&ctx, Location::unknown(&ctx));
+    pad_module(&design, "pads", 8);
+    assert!(design.verify());
+    let port = |name: &str, direction, r#type: &str| {
+        Port { name: name.to_string(), direction, r#type: r#type.to_string() }
+    };
+    assert_eq!(module_ports(&design.body().first_operation().unwrap()),
+               [port("pad", PortDirection::InOut, "i8"), port("q", PortDirection::Output, "i8"),
+                port("en", PortDirection::Input, "i1")]);
+}
+
+#[test]
+fn batch_wrapper_with_inout_port() {
+    let dir = env::temp_dir().join(format!("circt-sv-basic-batch-{}", std::process::id()));
+    let table = ParameterTable::from_csv("width\n4\n8\n").unwrap();
+    let options = BatchOptions { base_name: "pads".to_string(), wrapper: Some("wrapper".to_string()) };
+    let files = batch::generate(&table, &dir, &options, |design, name, row| {
+        pad_module(design, name, row.parse("width")?);
+        Ok(())
+    });
+    let wrapper = files.as_ref().map(|files| {
+        files.iter().filter(|file| file.starts_with(dir.join("wrapper")))
+            .map(|file| fs::read_to_string(file).unwrap())
+            .collect::<String>()
+    });
+    let _ = fs::remove_dir_all(&dir);
+    assert!(wrapper.unwrap().contains("inout"));
+}
+
+#[test]
+fn cache_reuse_with_inout_port() {
+    let path = env::temp_dir().join(format!("circt-sv-basic-cache-{}.json", std::process::id()));
+    let ctx = Context::new();
+    load_dialects(&ctx);
+    let location = Location::unknown(&ctx);
+    let built = Design::new(&ctx, location);
+    pad_module(&built, "pads", 8);
+    let mut cache = ModuleCache::load(&path).unwrap();
+    cache.record(&built, "pads", &8).unwrap();
+    cache.save().unwrap();
+
+    let reused = Design::new(&ctx, location);
+    let cache = ModuleCache::load(&path);
+    let _ = fs::remove_file(&path);
+    assert!(cache.unwrap().reuse(&reused, "pads", &8, location).unwrap());
+    assert!(reused.verify());
+    assert_eq!(module_ports(&find_module(&reused, "pads").unwrap()),
+               module_ports(&find_module(&built, "pads").unwrap()));
+}
+
+/* Only named as a bundle, never built. */
+#[allow(dead_code)]
+#[derive(Ports)]
+struct Pad {
+    pad: InOut<8>,
+    d: Input<8>,
+    q: Output<8>,
+}
+
+#[test]
+fn derived_bundle_with_inout_port() {
+    let ctx = Context::new();
+    load_dialects(&ctx);
+    let location = Location::unknown(&ctx);
+    let design = Design::new(&ctx, location);
+    ports::bundle_module::<Pad, _>(&design, "pad", |ports| Ok(PadOutputs { q: ports.d }), location).unwrap();
+    design.check().unwrap();
+    let port = |name: &str, direction, r#type: &str| {
+        Port { name: name.to_string(), direction, r#type: r#type.to_string() }
+    };
+    assert_eq!(module_ports(&find_module(&design, "pad").unwrap()),
+               [port("pad", PortDirection::InOut, "i8"), port("d", PortDirection::Input, "i8"),
+                port("q", PortDirection::Output, "i8")]);
+}
@@ -0,0 +1,94 @@
+//! Random module specs, checking that the builders only ever produce IR that verifies. proptest
+//! shrinks a failing spec to a minimal one.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use circt_sv_basic::always::{self, Posedge};
+use circt_sv_basic::constant::{self, Signedness};
+use circt_sv_basic::decl;
+use circt_sv_basic::ports::{self, PortDirection};
+use circt_sv_basic::prelude::*;
+
+#[derive(Clone, Debug)]
+enum BodyOp {
+    Constant { width: u32, value: u64 },
+    Wire { width: u32 },
+    Logic { width: u32 },
+    Always,
+}
+
+#[derive(Clone, Debug)]
+struct Spec {
+    inputs: Vec<u32>,
+    outputs: Vec<u32>,
+    body: Vec<BodyOp>,
+}
+
+fn width() -> impl Strategy<Value = u32> {
+    1u32..=64
+}
+
+fn body_op() -> impl Strategy<Value = BodyOp> {
+    prop_oneof![
+        (width(), any::<u64>()).prop_map(|(width, value)| BodyOp::Constant { width, value }),
+        width().prop_map(|width| BodyOp::Wire { width }),
+        width().prop_map(|width| BodyOp::Logic { width }),
+        Just(BodyOp::Always),
+    ]
+}
+
+fn spec() -> impl Strategy<Value = Spec> {
+    (vec(width(), 0..6), vec(width(), 0..6), vec(body_op(), 0..20))
+        .prop_map(|(inputs, outputs, body)| Spec { inputs, outputs, body })
+}
+
+/* Build `spec` as module `random`, with a `clk` input first for the always blocks, and verify it. */
+fn build(spec: &Spec) -> Result<(), Error> {
+    let ctx = Context::new();
+    load_dialects(&ctx);
+    let location = Location::unknown(&ctx);
+    let design = Design::new(&ctx, location);
+    let names: Vec<String> = (0..spec.inputs.len()).map(|index| format!("in{index}"))
+        .chain((0..spec.outputs.len()).map(|index| format!("out{index}")))
+        .collect();
+    let clock_type = IntegerType::new(&ctx, 1).into();
+    let mut port_list: Vec<(&str, PortDirection, Type)> = vec![("clk", PortDirection::Input, clock_type)];
+    port_list.extend(spec.inputs.iter().chain(&spec.outputs).zip(&names).enumerate().map(|(index, (width, name))| {
+        let direction = if index < spec.inputs.len() { PortDirection::Input } else { PortDirection::Output };
+        (name.as_str(), direction, IntegerType::new(&ctx, *width).into())
+    }));
+
+    ports::module(&design, "random", &port_list, |body| {
+        let clock = body.argument(0).unwrap().into();
+        for (index, op) in spec.body.iter().enumerate() {
+            match *op {
+                BodyOp::Constant { width, value } => {
+                    let value = if width == 64 { value } else { value & ((1 << width) - 1) };
+                    constant::const_int(&ctx, body, width, value.into(), Signedness::Unsigned, location)?;
+                }
+                BodyOp::Wire { width } => {
+                    decl::wire(&ctx, body, &format!("w{index}"), IntegerType::new(&ctx, width).into(), location);
+                }
+                BodyOp::Logic { width } => {
+                    decl::logic(&ctx, body, &format!("l{index}"), IntegerType::new(&ctx, width).into(), location);
+                }
+                BodyOp::Always => always::always(&ctx, body, &[(Posedge, clock)], |_| {}, location),
+            }
+        }
+        spec.outputs.iter()
+            .map(|width| constant::const_int(&ctx, body, *width, 0, Signedness::Unsigned, location))
+            .collect()
+    }, location)?;
+    design.check()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig { cases: 64, ..ProptestConfig::default() })]
+
+    #[test]
+    fn random_modules_verify(spec in spec()) {
+        let result = build(&spec);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+}